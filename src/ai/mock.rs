@@ -0,0 +1,351 @@
+//! 离线用的确定性模拟供应商（`LLM_PROVIDER=mock`，也可作为 `LLM_CHAIN` 中的一项）。
+//!
+//! - `MOCK_LLM_FIXTURE`：回复文件，JSON 字符串数组或以单独一行 `---` 分隔的文本，按调用顺序循环返回
+//! - 未设置 fixture 时按提示中采样的字段（`NON_EVENT: (...)` / `EVENT: (...)`）与 `MOCK_LLM_SEED`
+//!   （默认 42）拼出表达式；提示要求 JSON 时输出结构化结果，修复轮提示则逐条修正被拒表达式
+//! - `MOCK_LLM_RATE_LIMIT_EVERY=N`：每第 N 次调用返回限流错误
+//! - `MOCK_LLM_MALFORMED`：生成结果中混入坏行（括号不配对、禁用运算符、说明文字）的比例，默认 0
+//! - `MOCK_LLM_THINK=1`：回复前附带 `<think>` 块，模拟推理模型
+
+use crate::ai::types::{ChatRequest, ChatResponse, LlmError, LlmProvider, TokenUsage};
+use async_trait::async_trait;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+const TEMPLATES: &[&str] = &[
+    "rank(ts_delta({a}, {w}))",
+    "ts_rank({a}, {w})",
+    "zscore(ts_mean({a}, {w}))",
+    "group_rank(ts_zscore({a}, {w}), industry)",
+    "rank({a} / ts_mean({b}, {w}))",
+    "ts_corr({a}, {b}, {w})",
+    "-ts_delta(ts_mean({a}, 5), {w})",
+    "ts_decay_linear(rank({a}) - rank({b}), {w})",
+];
+const WINDOWS: &[u32] = &[5, 10, 20, 60, 120];
+const DEFAULT_FIELDS: &[&str] = &["close", "open", "volume", "returns", "vwap"];
+
+#[derive(Clone)]
+pub struct MockProvider {
+    seed: u64,
+    fixture: Arc<Vec<String>>,
+    rate_limit_every: u64,
+    malformed: f64,
+    think: bool,
+    calls: Arc<AtomicU64>,
+}
+
+impl MockProvider {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            fixture: Arc::new(Vec::new()),
+            rate_limit_every: 0,
+            malformed: 0.0,
+            think: false,
+            calls: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// 多 worker 时种子加上 worker 序号，各 worker 输出不同但仍可复现
+    pub fn from_env(worker: Option<usize>) -> Result<Self, LlmError> {
+        let read = |k: &str| std::env::var(k).ok().filter(|s| !s.trim().is_empty());
+        let seed = read("MOCK_LLM_SEED")
+            .and_then(|s| s.trim().parse::<u64>().ok())
+            .unwrap_or(42);
+        let malformed = match read("MOCK_LLM_MALFORMED") {
+            Some(s) => s
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|r| r.is_finite())
+                .ok_or_else(|| LlmError::Config(format!("MOCK_LLM_MALFORMED 无效: {}", s)))?,
+            None => 0.0,
+        };
+        let mut mock = Self::new(seed + worker.unwrap_or(0) as u64)
+            .rate_limit_every(
+                read("MOCK_LLM_RATE_LIMIT_EVERY")
+                    .and_then(|s| s.trim().parse().ok())
+                    .unwrap_or(0),
+            )
+            .malformed(malformed)
+            .think(read("MOCK_LLM_THINK").is_some_and(|v| {
+                matches!(
+                    v.trim().to_ascii_lowercase().as_str(),
+                    "1" | "true" | "yes" | "on"
+                )
+            }));
+        if let Some(path) = read("MOCK_LLM_FIXTURE") {
+            let text = std::fs::read_to_string(&path)
+                .map_err(|e| LlmError::Config(format!("{}: {}", path, e)))?;
+            mock = mock.with_fixture(parse_fixture(&text));
+        }
+        Ok(mock)
+    }
+
+    pub fn with_fixture(mut self, responses: Vec<String>) -> Self {
+        self.fixture = Arc::new(responses);
+        self
+    }
+
+    pub fn rate_limit_every(mut self, n: u64) -> Self {
+        self.rate_limit_every = n;
+        self
+    }
+
+    pub fn malformed(mut self, rate: f64) -> Self {
+        self.malformed = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn think(mut self, think: bool) -> Self {
+        self.think = think;
+        self
+    }
+
+    fn respond(&self, call: u64, req: &ChatRequest) -> String {
+        if !self.fixture.is_empty() {
+            return self.fixture[(call % self.fixture.len() as u64) as usize].clone();
+        }
+        let mut rng = StdRng::seed_from_u64(self.seed.wrapping_add(call));
+        if req.user.contains("were rejected by validation") {
+            return repair_lines(&req.user);
+        }
+        let fields = sampled_fields(&req.user);
+        let n = requested_count(&req.user).unwrap_or(10);
+        let mut exprs = Vec::with_capacity(n);
+        for _ in 0..n {
+            if rng.gen_bool(self.malformed) {
+                exprs.push(malformed_expr(&mut rng, &fields));
+            } else {
+                exprs.push(random_expr(&mut rng, &fields));
+            }
+        }
+        if req.response_format.is_some() || req.user.contains("\"alphas\"") {
+            let alphas: Vec<serde_json::Value> = exprs
+                .iter()
+                .map(|e| {
+                    serde_json::json!({
+                        "expression": e,
+                        "hypothesis": "mock hypothesis",
+                        "fields": fields.iter().filter(|f| e.contains(f.as_str())).collect::<Vec<_>>(),
+                    })
+                })
+                .collect();
+            let json = serde_json::json!({ "alphas": alphas }).to_string();
+            // 坏输出模式下像不守规矩的模型一样加上说明文字和代码块
+            if self.malformed > 0.0 {
+                return format!("Here are the alphas:\n```json\n{}\n```", json);
+            }
+            return json;
+        }
+        exprs
+            .iter()
+            .map(|e| {
+                if e.starts_with("Here") {
+                    e.clone()
+                } else {
+                    format!("ALPHA_EXPR: {}", e)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[async_trait]
+impl LlmProvider for MockProvider {
+    async fn chat(&self, req: ChatRequest) -> Result<ChatResponse, LlmError> {
+        let call = self.calls.fetch_add(1, Ordering::Relaxed);
+        if self.rate_limit_every > 0 && (call + 1).is_multiple_of(self.rate_limit_every) {
+            return Err(LlmError::RateLimited);
+        }
+        let mut raw = self.respond(call, &req);
+        if self.think {
+            raw = format!("<think>mock reasoning for call {}</think>\n{}", call, raw);
+        }
+        // 与真实供应商一致：fixture 或模拟的 <think> 块从正文中剥离
+        let (text, reasoning) = crate::ai::reasoning::split_think(&raw);
+        // 按约 4 字符 1 token 估算，便于用量统计
+        let usage = TokenUsage {
+            prompt_tokens: ((req.system.len() + req.user.len()) / 4) as u64,
+            completion_tokens: (text.len() / 4) as u64,
+        };
+        Ok(ChatResponse {
+            text,
+            provider: Some("mock".to_string()),
+            model: Some(req.model.clone()),
+            usage: Some(usage),
+            reasoning,
+            ..Default::default()
+        })
+    }
+}
+
+fn parse_fixture(text: &str) -> Vec<String> {
+    if let Ok(list) = serde_json::from_str::<Vec<String>>(text) {
+        return list;
+    }
+    let mut out = Vec::new();
+    let mut cur = String::new();
+    for line in text.lines() {
+        if line.trim() == "---" {
+            out.push(std::mem::take(&mut cur));
+        } else {
+            cur.push_str(line);
+            cur.push('\n');
+        }
+    }
+    out.push(cur);
+    out.into_iter().filter(|s| !s.trim().is_empty()).collect()
+}
+
+/// 提示中 `NON_EVENT: (...)` / `EVENT: (...)` 列出的字段；没有时用几个常见价量字段
+fn sampled_fields(prompt: &str) -> Vec<String> {
+    let mut fields: Vec<String> = prompt
+        .lines()
+        .filter_map(|l| {
+            let l = l.trim();
+            l.strip_prefix("NON_EVENT: (")
+                .or_else(|| l.strip_prefix("EVENT: ("))
+                .and_then(|rest| rest.strip_suffix(')'))
+        })
+        .flat_map(|list| list.split(',').map(|s| s.trim().to_string()))
+        .filter(|s| !s.is_empty())
+        .collect();
+    if fields.is_empty() {
+        fields = DEFAULT_FIELDS.iter().map(|s| s.to_string()).collect();
+    }
+    fields
+}
+
+/// 提示首行 `Generate {n} unique ...` 中的 n
+fn requested_count(prompt: &str) -> Option<usize> {
+    let rest = prompt.split("Generate ").nth(1)?;
+    rest.split_whitespace().next()?.parse().ok()
+}
+
+fn random_expr(rng: &mut StdRng, fields: &[String]) -> String {
+    let template = TEMPLATES.choose(rng).copied().unwrap_or(TEMPLATES[0]);
+    let a = fields.choose(rng).cloned().unwrap_or_default();
+    let b = fields.choose(rng).cloned().unwrap_or_default();
+    let w = WINDOWS.choose(rng).copied().unwrap_or(20);
+    template
+        .replace("{a}", &a)
+        .replace("{b}", &b)
+        .replace("{w}", &w.to_string())
+}
+
+fn malformed_expr(rng: &mut StdRng, fields: &[String]) -> String {
+    let f = fields.choose(rng).cloned().unwrap_or_default();
+    match rng.gen_range(0..3) {
+        0 => format!("ts_rank(ts_mean({}, 10), 20", f),
+        1 => format!("reduce_avg({}, 20)", f),
+        _ => "Here are some more ideas you might like:".to_string(),
+    }
+}
+
+/// 修复轮：逐条补齐右括号、把 reduce_* 换成 ts_*
+fn repair_lines(prompt: &str) -> String {
+    prompt
+        .lines()
+        .filter_map(|l| {
+            let (num, expr) = l.trim().split_once(". ")?;
+            num.parse::<usize>().ok()?;
+            let mut fixed = expr.replace("reduce_", "ts_");
+            let open = fixed.matches('(').count();
+            let close = fixed.matches(')').count();
+            for _ in close..open {
+                fixed.push(')');
+            }
+            Some(format!("ALPHA_EXPR: {}", fixed))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::parser::{
+        alpha_response_format, parse_alpha_exprs, parse_structured_exprs,
+    };
+
+    fn request(json: bool) -> ChatRequest {
+        ChatRequest {
+            model: "mock-model".to_string(),
+            system: String::new(),
+            user: "Generate 20 unique alpha factor expressions.\nNON_EVENT: (close, volume, vwap)"
+                .to_string(),
+            temperature: 0.7,
+            max_tokens: 2048,
+            response_format: json.then(alpha_response_format),
+        }
+    }
+
+    #[tokio::test]
+    async fn same_seed_gives_same_parsed_output() {
+        let a = MockProvider::new(7).chat(request(false)).await.unwrap();
+        let b = MockProvider::new(7).chat(request(false)).await.unwrap();
+        let c = MockProvider::new(8).chat(request(false)).await.unwrap();
+        let parsed = parse_alpha_exprs(&a.text);
+        assert_eq!(parsed.exprs.len(), 20);
+        assert!(parsed.rejected_examples.is_empty());
+        assert_eq!(parsed.exprs, parse_alpha_exprs(&b.text).exprs);
+        assert_ne!(parsed.exprs, parse_alpha_exprs(&c.text).exprs);
+        assert!(parsed
+            .exprs
+            .iter()
+            .all(|e| ["close", "volume", "vwap"].iter().any(|f| e.contains(f))));
+    }
+
+    #[tokio::test]
+    async fn structured_output_parses_with_hypotheses() {
+        let resp = MockProvider::new(7).chat(request(true)).await.unwrap();
+        let parsed = parse_structured_exprs(&resp.text);
+        assert_eq!(parsed.exprs.len(), 20);
+        // 假设按表达式索引，重复的表达式只留一条
+        let unique: std::collections::HashSet<&String> = parsed.exprs.iter().collect();
+        assert_eq!(parsed.hypotheses.len(), unique.len());
+        // 同一种子下与行模式给出相同的表达式
+        let lines = MockProvider::new(7).chat(request(false)).await.unwrap();
+        assert_eq!(parsed.exprs, parse_alpha_exprs(&lines.text).exprs);
+    }
+
+    #[tokio::test]
+    async fn rate_limit_every_nth_call() {
+        let mock = MockProvider::new(1).rate_limit_every(3);
+        for call in 1..=6 {
+            let res = mock.chat(request(false)).await;
+            if call % 3 == 0 {
+                assert!(matches!(res, Err(LlmError::RateLimited)), "call {}", call);
+            } else {
+                assert!(res.is_ok(), "call {}", call);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn malformed_lines_are_rejected() {
+        let mock = MockProvider::new(3).malformed(1.0);
+        let parsed = parse_alpha_exprs(&mock.chat(request(false)).await.unwrap().text);
+        assert!(parsed.exprs.is_empty());
+        assert!(!parsed.rejected_examples.is_empty());
+
+        let mock = MockProvider::new(3).malformed(0.5);
+        let parsed = parse_structured_exprs(&mock.chat(request(true)).await.unwrap().text);
+        assert!(!parsed.exprs.is_empty());
+        assert!(!parsed.rejected_examples.is_empty());
+    }
+
+    #[test]
+    fn from_env_rejects_non_finite_malformed_rate() {
+        std::env::set_var("MOCK_LLM_MALFORMED", "NaN");
+        let res = MockProvider::from_env(None);
+        std::env::remove_var("MOCK_LLM_MALFORMED");
+        assert!(matches!(res, Err(LlmError::Config(_))));
+    }
+}
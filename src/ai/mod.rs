@@ -1,14 +1,37 @@
-pub mod cerebras;
-pub mod openrouter;
+pub mod mock;
+pub mod openai_compat;
+pub mod pricing;
+pub mod reasoning;
+mod sse;
 pub mod types;
 pub mod unified;
-pub mod xirang;
 
-pub use cerebras::CerebrasProvider;
-pub use openrouter::OpenRouterProvider;
 pub use types::{ChatRequest, ChatResponse, LlmError, LlmProvider};
 pub use unified::AnyProvider;
-pub use xirang::XirangProvider;
+
+/// LLM_STREAM=1/true 时生成请求走流式接口（超时也能保留已收到的内容）
+pub fn stream_enabled() -> bool {
+    std::env::var("LLM_STREAM")
+        .map(|v| {
+            matches!(
+                v.trim().to_ascii_lowercase().as_str(),
+                "1" | "true" | "yes" | "on"
+            )
+        })
+        .unwrap_or(false)
+}
+
+/// LLM_JSON_OUTPUT=1/true 时生成请求要求 JSON 结构化输出（表达式 + 假设 + 所用字段）
+pub fn json_output_enabled() -> bool {
+    std::env::var("LLM_JSON_OUTPUT")
+        .map(|v| {
+            matches!(
+                v.trim().to_ascii_lowercase().as_str(),
+                "1" | "true" | "yes" | "on"
+            )
+        })
+        .unwrap_or(false)
+}
 
 pub(crate) fn build_llm_http_client() -> Result<reqwest::Client, LlmError> {
     let mut builder = reqwest::Client::builder();
@@ -0,0 +1,453 @@
+//! 通用 OpenAI 兼容供应商：`<base_url>/chat/completions`，差异（鉴权方式、token 上限字段名、
+//! 额外请求参数）由 [`ProviderSpec`] 描述。
+//!
+//! 内置预设 openrouter / cerebras / xirang / ollama / llamacpp；`LLM_PROVIDERS_FILE`
+//! （默认 `providers.json`，不存在则忽略）中的 JSON 对象可新增或覆盖同名预设，例如：
+//!
+//! ```json
+//! {
+//!   "deepseek": {
+//!     "base_url": "https://api.deepseek.com/v1",
+//!     "key_env": ["DEEPSEEK_API_KEY"],
+//!     "keys_env": "DEEPSEEK_API_KEYS",
+//!     "extra_body": {"top_p": 0.95}
+//!   },
+//!   "vllm": {"base_url": "http://127.0.0.1:8000/v1", "auth": "none"}
+//! }
+//! ```
+//!
+//! 服务不支持 JSON Schema 结构化输出时设置 `"response_format": false`，请求中不再携带该参数。
+
+use crate::ai::build_llm_http_client;
+use crate::ai::reasoning::{self, split_think};
+use crate::ai::sse::read_chat_stream;
+use crate::ai::types::{ChatRequest, ChatResponse, DeltaSink, LlmError, LlmProvider, TokenUsage};
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// 鉴权方式：`"bearer"`（默认）、`"none"`（本地服务）或 `{"header": "api-key"}`（自定义请求头）
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthStyle {
+    #[default]
+    Bearer,
+    None,
+    Header(String),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProviderSpec {
+    pub base_url: String,
+    /// 设置时以该环境变量覆盖 base_url
+    #[serde(default)]
+    pub base_url_env: Option<String>,
+    /// 单个 key 的环境变量名，按顺序取第一个存在的
+    #[serde(default)]
+    pub key_env: Vec<String>,
+    /// 多个 key（逗号/分号/空白分隔）的环境变量名，优先于 key_env
+    #[serde(default)]
+    pub keys_env: Option<String>,
+    #[serde(default)]
+    pub auth: AuthStyle,
+    #[serde(default = "default_max_tokens_field")]
+    pub max_tokens_field: String,
+    /// 原样合并进请求体的额外参数
+    #[serde(default)]
+    pub extra_body: Map<String, Value>,
+    /// 是否支持 `response_format`（JSON Schema 结构化输出），不支持时不发送
+    #[serde(default = "default_true")]
+    pub response_format: bool,
+}
+
+fn default_max_tokens_field() -> String {
+    "max_tokens".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl ProviderSpec {
+    fn preset(
+        base_url: &str,
+        base_url_env: &str,
+        key_env: &[&str],
+        keys_env: Option<&str>,
+        auth: AuthStyle,
+        max_tokens_field: &str,
+    ) -> Self {
+        Self {
+            base_url: base_url.to_string(),
+            base_url_env: Some(base_url_env.to_string()),
+            key_env: key_env.iter().map(|s| s.to_string()).collect(),
+            keys_env: keys_env.map(str::to_string),
+            auth,
+            max_tokens_field: max_tokens_field.to_string(),
+            extra_body: Map::new(),
+            response_format: true,
+        }
+    }
+
+    /// 按名称取配置：配置文件中的同名项优先于内置预设
+    pub fn load(name: &str) -> Result<Self, LlmError> {
+        let name = name.trim().to_ascii_lowercase();
+        if let Some(spec) = load_file()?.remove(&name) {
+            return Ok(spec);
+        }
+        presets()
+            .remove(name.as_str())
+            .ok_or_else(|| LlmError::Config(format!("未知的 LLM 供应商: {}", name)))
+    }
+
+    pub fn base_url(&self) -> String {
+        self.base_url_env
+            .as_deref()
+            .and_then(|k| std::env::var(k).ok())
+            .filter(|s| !s.trim().is_empty())
+            .unwrap_or_else(|| self.base_url.clone())
+    }
+
+    /// 读取 key 的环境变量名（keys_env 在前），用于提示配置
+    pub fn key_vars(&self) -> Vec<String> {
+        self.keys_env
+            .iter()
+            .chain(self.key_env.iter())
+            .cloned()
+            .collect()
+    }
+
+    /// 可用的 key 列表；无需鉴权时为空
+    pub fn keys(&self) -> Result<Vec<String>, LlmError> {
+        if self.auth == AuthStyle::None {
+            return Ok(Vec::new());
+        }
+        if let Some(raw) = self.keys_env.as_deref().and_then(|k| std::env::var(k).ok()) {
+            let keys = split_keys(&raw);
+            if !keys.is_empty() {
+                return Ok(keys);
+            }
+        }
+        for k in &self.key_env {
+            if let Ok(v) = std::env::var(k) {
+                if !v.trim().is_empty() {
+                    return Ok(vec![v.trim().to_string()]);
+                }
+            }
+        }
+        Err(LlmError::MissingEnv(
+            self.key_env
+                .first()
+                .cloned()
+                .or_else(|| self.keys_env.clone())
+                .unwrap_or_else(|| "API key".to_string()),
+        ))
+    }
+}
+
+fn presets() -> HashMap<&'static str, ProviderSpec> {
+    HashMap::from([
+        (
+            "openrouter",
+            ProviderSpec::preset(
+                "https://openrouter.ai/api/v1",
+                "OPENROUTER_BASE_URL",
+                &["OPENROUTER_API_KEY"],
+                Some("OPENROUTER_API_KEYS"),
+                AuthStyle::Bearer,
+                "max_tokens",
+            ),
+        ),
+        (
+            "cerebras",
+            ProviderSpec::preset(
+                "https://api.cerebras.ai/v1",
+                "CEREBRAS_BASE_URL",
+                &["CEREBRAS_API_KEY"],
+                Some("CEREBRAS_API_KEYS"),
+                AuthStyle::Bearer,
+                "max_completion_tokens",
+            ),
+        ),
+        (
+            "xirang",
+            ProviderSpec {
+                response_format: false,
+                ..ProviderSpec::preset(
+                    "https://wishub-x6.ctyun.cn/v1",
+                    "XIRANG_BASE_URL",
+                    &["XIRANG_APP_KEY", "XIRANG_app_key"],
+                    Some("XIRANG_APP_KEYS"),
+                    AuthStyle::Bearer,
+                    "max_tokens",
+                )
+            },
+        ),
+        (
+            "ollama",
+            ProviderSpec::preset(
+                "http://127.0.0.1:11434/v1",
+                "OLLAMA_BASE_URL",
+                &[],
+                None,
+                AuthStyle::None,
+                "max_tokens",
+            ),
+        ),
+        (
+            "llamacpp",
+            ProviderSpec::preset(
+                "http://127.0.0.1:8080/v1",
+                "LLAMACPP_BASE_URL",
+                &[],
+                None,
+                AuthStyle::None,
+                "max_tokens",
+            ),
+        ),
+    ])
+}
+
+fn load_file() -> Result<HashMap<String, ProviderSpec>, LlmError> {
+    let path = std::env::var("LLM_PROVIDERS_FILE").unwrap_or_else(|_| "providers.json".to_string());
+    let text = match std::fs::read_to_string(&path) {
+        Ok(t) => t,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(LlmError::Config(format!("{}: {}", path, e))),
+    };
+    let specs: HashMap<String, ProviderSpec> =
+        serde_json::from_str(&text).map_err(|e| LlmError::Config(format!("{}: {}", path, e)))?;
+    Ok(specs
+        .into_iter()
+        .map(|(k, v)| (k.to_ascii_lowercase(), v))
+        .collect())
+}
+
+pub(crate) fn split_keys(raw: &str) -> Vec<String> {
+    raw.split([',', ';', '\n', '\t', ' '])
+        .map(|x| x.trim().to_string())
+        .filter(|x| !x.is_empty())
+        .collect()
+}
+
+#[derive(Clone)]
+pub struct OpenAiCompatProvider {
+    client: reqwest::Client,
+    spec: Arc<ProviderSpec>,
+    base_url: String,
+    api_keys: Vec<String>,
+    index: Arc<AtomicUsize>,
+}
+
+impl OpenAiCompatProvider {
+    /// `worker` 为 Some 时固定使用第 worker 个 key（多 worker 分摊限流），否则每次请求轮换
+    pub fn from_env(name: &str, worker: Option<usize>) -> Result<Self, LlmError> {
+        let spec = ProviderSpec::load(name)?;
+        let mut api_keys = spec.keys()?;
+        if let Some(wi) = worker.filter(|_| !api_keys.is_empty()) {
+            api_keys = vec![api_keys[wi % api_keys.len()].clone()];
+        }
+        Ok(Self {
+            client: build_llm_http_client()?,
+            base_url: spec.base_url(),
+            spec: Arc::new(spec),
+            api_keys,
+            index: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    fn request_body(&self, req: &ChatRequest, stream: bool) -> Value {
+        let mut body = serde_json::json!({
+            "model": req.model,
+            "temperature": req.temperature,
+            "messages": [
+                {"role": "system", "content": req.system},
+                {"role": "user", "content": req.user}
+            ],
+            "stream": stream
+        });
+        if let Value::Object(obj) = &mut body {
+            obj.insert(
+                self.spec.max_tokens_field.clone(),
+                Value::from(req.max_tokens),
+            );
+            if stream {
+                // 让最后一个流式事件带上 token 用量
+                obj.insert(
+                    "stream_options".to_string(),
+                    serde_json::json!({"include_usage": true}),
+                );
+            }
+            if let Some(format) = req
+                .response_format
+                .as_ref()
+                .filter(|_| self.spec.response_format)
+            {
+                obj.insert("response_format".to_string(), format.clone());
+            }
+            for (k, v) in &self.spec.extra_body {
+                obj.insert(k.clone(), v.clone());
+            }
+        }
+        body
+    }
+
+    fn next_key(&self) -> Option<&str> {
+        if self.api_keys.is_empty() {
+            return None;
+        }
+        let i = self.index.fetch_add(1, Ordering::Relaxed);
+        Some(&self.api_keys[i % self.api_keys.len()])
+    }
+
+    /// 发送请求（超时换 key 重试一次），并把鉴权/限流/非 2xx 状态转为错误
+    async fn send(&self, body: &Value) -> Result<reqwest::Response, LlmError> {
+        let url = format!("{}/chat/completions", self.base_url.trim_end_matches('/'));
+
+        let mut resp = None;
+        for _ in 0..2 {
+            let mut builder = self
+                .client
+                .post(url.clone())
+                .header("Content-Type", "application/json")
+                .json(body);
+            if let Some(key) = self.next_key() {
+                builder = match &self.spec.auth {
+                    AuthStyle::Bearer => builder.bearer_auth(key),
+                    AuthStyle::Header(h) => builder.header(h.as_str(), key),
+                    AuthStyle::None => builder,
+                };
+            }
+            match builder.send().await {
+                Ok(r) => {
+                    resp = Some(r);
+                    break;
+                }
+                Err(e) => {
+                    if e.is_timeout() {
+                        continue;
+                    } else {
+                        return Err(LlmError::Http(e.to_string()));
+                    }
+                }
+            }
+        }
+        let resp = resp.ok_or_else(|| LlmError::Http("timeout".to_string()))?;
+
+        match resp.status() {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => return Err(LlmError::Unauthorized),
+            StatusCode::TOO_MANY_REQUESTS => return Err(LlmError::RateLimited),
+            _ => {}
+        }
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let raw = resp.text().await.unwrap_or_default();
+            return Err(LlmError::Http(format!("{} {}", status.as_u16(), raw)));
+        }
+        Ok(resp)
+    }
+}
+
+/// 兼容不同模型的返回结构：
+/// - 标准：choices[0].message.content (string 或 content parts 数组，含 text)
+/// - 一些模型：choices[0].text 或顶层 output_text
+/// - 兼容：choices[0].content (直接内容或数组)
+fn parse_chat_response(raw: String) -> Result<ChatResponse, LlmError> {
+    let v: Value = serde_json::from_str(&raw)
+        .map_err(|e| LlmError::InvalidResponse(format!("json parse failed: {e}, raw={raw}")))?;
+
+    let choice0 = v
+        .get("choices")
+        .and_then(|c| c.get(0))
+        .ok_or_else(|| LlmError::InvalidResponse(format!("missing choices[0], raw={raw}")))?;
+
+    let content = choice0
+        .get("message")
+        .and_then(|m| m.get("content"))
+        .or_else(|| choice0.get("content"));
+
+    let text = if let Some(content) = content {
+        match content {
+            Value::String(s) => s.clone(),
+            Value::Array(arr) => {
+                let mut parts = Vec::new();
+                for it in arr {
+                    if let Some(t) = it.get("text").and_then(|x| x.as_str()) {
+                        parts.push(t.to_string());
+                    } else if let Some(t) = it.get("content").and_then(|x| x.as_str()) {
+                        parts.push(t.to_string());
+                    } else if let Some(t) = it.as_str() {
+                        parts.push(t.to_string());
+                    }
+                }
+                parts.join("\n")
+            }
+            Value::Object(obj) => {
+                if let Some(Value::String(s)) = obj.get("text") {
+                    s.clone()
+                } else if let Some(Value::String(s)) = obj.get("output_text") {
+                    s.clone()
+                } else if let Some(Value::String(s)) = obj.get("content") {
+                    s.clone()
+                } else {
+                    return Err(LlmError::InvalidResponse(format!(
+                        "unexpected content type, raw={raw}"
+                    )));
+                }
+            }
+            _ => {
+                return Err(LlmError::InvalidResponse(format!(
+                    "unexpected content type, raw={raw}"
+                )))
+            }
+        }
+    } else if let Some(Value::String(s)) = choice0.get("text") {
+        s.clone()
+    } else if let Some(Value::String(s)) = v.get("output_text") {
+        s.clone()
+    } else {
+        return Err(LlmError::InvalidResponse(format!(
+            "missing content/text in choices[0], raw={raw}"
+        )));
+    };
+
+    let field = choice0
+        .get("message")
+        .and_then(|m| m.get("reasoning_content").or_else(|| m.get("reasoning")))
+        .and_then(|r| r.as_str())
+        .map(str::to_string);
+    let (text, inline) = split_think(&text);
+    Ok(ChatResponse {
+        text,
+        usage: TokenUsage::from_json(&v),
+        reasoning: reasoning::merge(field, inline),
+        raw: Some(raw),
+        ..Default::default()
+    })
+}
+
+#[async_trait]
+impl LlmProvider for OpenAiCompatProvider {
+    async fn chat(&self, req: ChatRequest) -> Result<ChatResponse, LlmError> {
+        let resp = self.send(&self.request_body(&req, false)).await?;
+        let raw = resp
+            .text()
+            .await
+            .map_err(|e| LlmError::Http(e.to_string()))?;
+        parse_chat_response(raw)
+    }
+
+    async fn chat_stream(
+        &self,
+        req: ChatRequest,
+        on_delta: DeltaSink<'_>,
+    ) -> Result<ChatResponse, LlmError> {
+        let resp = self.send(&self.request_body(&req, true)).await?;
+        read_chat_stream(resp, on_delta).await
+    }
+}
@@ -0,0 +1,54 @@
+//! 按模型估算调用费用。
+//!
+//! 价格表来自 `LLM_PRICES`，单位为每百万 token 的美元价格（输入/输出），例如：
+//! `LLM_PRICES=gpt-oss-120b=0.25/0.69, deepseek/deepseek-r1=0.55/2.19, qwen2.5:7b=0/0`。
+//! 先按完整模型名匹配（不区分大小写），再按去掉 `供应商/` 前缀后的名称匹配；未配置的模型不估算。
+
+use crate::ai::types::TokenUsage;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+#[derive(Clone, Copy, Debug)]
+struct Price {
+    input: f64,
+    output: f64,
+}
+
+fn table() -> &'static HashMap<String, Price> {
+    static TABLE: OnceLock<HashMap<String, Price>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        std::env::var("LLM_PRICES")
+            .map(|raw| parse(&raw))
+            .unwrap_or_default()
+    })
+}
+
+fn parse(raw: &str) -> HashMap<String, Price> {
+    raw.split([',', ';', '\n'])
+        .filter_map(|item| {
+            // 模型名里可能有 '='，按最后一个切分
+            let (model, price) = item.trim().rsplit_once('=')?;
+            let (i, o) = price.split_once('/')?;
+            Some((
+                model.trim().to_ascii_lowercase(),
+                Price {
+                    input: i.trim().parse().ok()?,
+                    output: o.trim().parse().ok()?,
+                },
+            ))
+        })
+        .collect()
+}
+
+/// 估算费用（美元）；模型不在价格表中时返回 None
+pub fn estimate_cost(model: &str, usage: &TokenUsage) -> Option<f64> {
+    let model = model.trim().to_ascii_lowercase();
+    let t = table();
+    let price = t
+        .get(&model)
+        .or_else(|| model.rsplit_once('/').and_then(|(_, short)| t.get(short)))?;
+    Some(
+        (usage.prompt_tokens as f64 * price.input + usage.completion_tokens as f64 * price.output)
+            / 1_000_000.0,
+    )
+}
@@ -0,0 +1,127 @@
+//! 推理模型（DeepSeek-R1 等）输出中的思考过程与最终回答分离。
+//!
+//! 思考过程可能以 `reasoning_content` / `reasoning` 字段单独返回，也可能以 `<think>...</think>`
+//! 混在正文中；部分聊天模板已在提示里写入 `<think>`，正文只出现结束标签。
+
+const OPEN: &str = "<think>";
+const CLOSE: &str = "</think>";
+
+/// 拆出正文中的 `<think>` 块，返回 (回答, 思考过程)。未闭合的块（截断）整体视为思考过程
+pub fn split_think(text: &str) -> (String, Option<String>) {
+    let mut answer = String::new();
+    let mut reasoning: Vec<&str> = Vec::new();
+    let mut rest = text;
+    // 只有结束标签：之前的内容都是思考过程
+    if let Some(end) = rest.find(CLOSE) {
+        if !rest[..end].contains(OPEN) {
+            reasoning.push(&rest[..end]);
+            rest = &rest[end + CLOSE.len()..];
+        }
+    }
+    while let Some(start) = rest.find(OPEN) {
+        answer.push_str(&rest[..start]);
+        let after = &rest[start + OPEN.len()..];
+        match after.find(CLOSE) {
+            Some(end) => {
+                reasoning.push(&after[..end]);
+                rest = &after[end + CLOSE.len()..];
+            }
+            None => {
+                reasoning.push(after);
+                rest = "";
+            }
+        }
+    }
+    answer.push_str(rest);
+    let reasoning: Vec<&str> = reasoning
+        .into_iter()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect();
+    (
+        answer.trim().to_string(),
+        (!reasoning.is_empty()).then(|| reasoning.join("\n\n")),
+    )
+}
+
+/// 合并单独返回的推理字段与正文中拆出的思考过程
+pub fn merge(field: Option<String>, inline: Option<String>) -> Option<String> {
+    match (field.filter(|s| !s.trim().is_empty()), inline) {
+        (Some(a), Some(b)) => Some(format!("{}\n\n{}", a.trim(), b)),
+        (a, b) => a.map(|s| s.trim().to_string()).or(b),
+    }
+}
+
+/// LLM_ARCHIVE_REASONING=1/true 时保存产出 Alpha 的请求的思考过程，供详情页查看
+pub fn archive_enabled() -> bool {
+    std::env::var("LLM_ARCHIVE_REASONING")
+        .map(|v| {
+            matches!(
+                v.trim().to_ascii_lowercase().as_str(),
+                "1" | "true" | "yes" | "on"
+            )
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_think_cases() {
+        let cases: [(&str, &str, Option<&str>); 9] = [
+            ("ALPHA_EXPR: rank(close)", "ALPHA_EXPR: rank(close)", None),
+            (
+                "<think>先看动量</think>\nALPHA_EXPR: x",
+                "ALPHA_EXPR: x",
+                Some("先看动量"),
+            ),
+            // 只有结束标签
+            (
+                "模板已写入开始标签\n</think>\n\nanswer",
+                "answer",
+                Some("模板已写入开始标签"),
+            ),
+            ("r1</think>a<think>r2</think>b", "ab", Some("r1\n\nr2")),
+            // 未闭合（截断）
+            ("<think>想到一半", "", Some("想到一半")),
+            ("answer <think>truncated", "answer", Some("truncated")),
+            // 多个块
+            (
+                "a<think>r1</think>b<think> r2 </think>c",
+                "abc",
+                Some("r1\n\nr2"),
+            ),
+            ("<think>r1</think>a<think>r2", "a", Some("r1\n\nr2")),
+            // 空块不产生思考过程
+            ("<think>  </think>answer", "answer", None),
+        ];
+        for (input, answer, reasoning) in cases {
+            let (a, r) = split_think(input);
+            assert_eq!(a, answer, "{:?}", input);
+            assert_eq!(r.as_deref(), reasoning, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn merge_cases() {
+        let s = |v: &str| Some(v.to_string());
+        let cases = [
+            (None, None, None),
+            (s(" field "), None, s("field")),
+            (None, s("inline"), s("inline")),
+            (s("  "), s("inline"), s("inline")),
+            (s("field\n"), s("inline"), s("field\n\ninline")),
+        ];
+        for (field, inline, expected) in cases {
+            assert_eq!(
+                merge(field.clone(), inline.clone()),
+                expected,
+                "{:?} {:?}",
+                field,
+                inline
+            );
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! OpenAI 兼容 `/chat/completions` 的流式（SSE）响应读取。
+//!
+//! 每个事件为 `data: {json}`，增量文本在 `choices[0].delta.content`，以 `data: [DONE]` 结束；
+//! 请求带 `stream_options.include_usage` 时最后一个事件附带 `usage`。
+//! 推理模型的 `delta.reasoning_content` / `delta.reasoning` 单独累积，不回调也不计入正文。
+//! 读取途中超时、连接断开或收到无法解析/报错的事件时，若已收到内容则返回部分结果
+//! （`truncated = true`）而不是报错。
+
+use crate::ai::reasoning;
+use crate::ai::types::{ChatResponse, DeltaSink, LlmError, TokenUsage};
+use serde_json::Value;
+
+pub(crate) async fn read_chat_stream(
+    mut resp: reqwest::Response,
+    on_delta: DeltaSink<'_>,
+) -> Result<ChatResponse, LlmError> {
+    let mut buf: Vec<u8> = Vec::new();
+    let mut out = ChatResponse::default();
+    let mut thinking = String::new();
+    loop {
+        let chunk = match resp.chunk().await {
+            Ok(Some(c)) => c,
+            Ok(None) => break,
+            Err(e) if !out.text.is_empty() => {
+                log::warn!("流式响应中断，保留已收到的 {} 字符: {}", out.text.len(), e);
+                out.truncated = true;
+                return Ok(finish(out, thinking));
+            }
+            Err(e) => return Err(LlmError::Http(e.to_string())),
+        };
+        buf.extend_from_slice(&chunk);
+        while let Some(pos) = buf.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = buf.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            let event = match parse_event(line.trim()) {
+                Ok(ev) => ev,
+                Err(e) if !out.text.is_empty() => {
+                    log::warn!("流式事件异常，保留已收到的 {} 字符: {}", out.text.len(), e);
+                    out.truncated = true;
+                    return Ok(finish(out, thinking));
+                }
+                Err(e) => return Err(e),
+            };
+            match event {
+                Event::Data {
+                    delta,
+                    reasoning,
+                    usage,
+                } => {
+                    if let Some(d) = delta {
+                        on_delta(&d);
+                        out.text.push_str(&d);
+                    }
+                    if let Some(r) = reasoning {
+                        thinking.push_str(&r);
+                    }
+                    if usage.is_some() {
+                        out.usage = usage;
+                    }
+                }
+                Event::Done => return Ok(finish(out, thinking)),
+                Event::Skip => {}
+            }
+        }
+    }
+    // 部分服务不发送 [DONE]，连接正常关闭即视为结束
+    if out.text.is_empty() {
+        return Err(LlmError::InvalidResponse(
+            "stream ended without content".to_string(),
+        ));
+    }
+    Ok(finish(out, thinking))
+}
+
+/// 从正文剥离 `<think>` 块，与单独收到的推理内容合并
+fn finish(mut out: ChatResponse, thinking: String) -> ChatResponse {
+    let (text, inline) = reasoning::split_think(&out.text);
+    out.text = text;
+    out.reasoning = reasoning::merge(Some(thinking), inline);
+    out
+}
+
+enum Event {
+    Data {
+        delta: Option<String>,
+        reasoning: Option<String>,
+        usage: Option<TokenUsage>,
+    },
+    Done,
+    Skip,
+}
+
+fn parse_event(line: &str) -> Result<Event, LlmError> {
+    // 空行为事件分隔；以 ':' 开头的是注释/保活
+    let Some(data) = line.strip_prefix("data:") else {
+        return Ok(Event::Skip);
+    };
+    let data = data.trim();
+    if data == "[DONE]" {
+        return Ok(Event::Done);
+    }
+    let v: Value = serde_json::from_str(data).map_err(|e| {
+        LlmError::InvalidResponse(format!("stream json parse failed: {e}, data={data}"))
+    })?;
+    if let Some(err) = v.get("error") {
+        return Err(LlmError::InvalidResponse(format!("stream error: {err}")));
+    }
+    let choice0 = v.get("choices").and_then(|c| c.get(0));
+    let delta = choice0
+        .and_then(|c| c.get("delta"))
+        .and_then(|d| d.get("content"))
+        .or_else(|| choice0.and_then(|c| c.get("text")))
+        .and_then(|d| d.as_str())
+        .filter(|s| !s.is_empty())
+        .map(str::to_string);
+    let reasoning = choice0
+        .and_then(|c| c.get("delta"))
+        .and_then(|d| d.get("reasoning_content").or_else(|| d.get("reasoning")))
+        .and_then(|r| r.as_str())
+        .filter(|s| !s.is_empty())
+        .map(str::to_string);
+    Ok(Event::Data {
+        delta,
+        reasoning,
+        usage: TokenUsage::from_json(&v),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(line: &str) -> (Option<String>, Option<String>, Option<TokenUsage>) {
+        match parse_event(line) {
+            Ok(Event::Data {
+                delta,
+                reasoning,
+                usage,
+            }) => (delta, reasoning, usage),
+            Ok(_) => panic!("不是数据事件: {}", line),
+            Err(e) => panic!("解析失败: {} => {}", line, e),
+        }
+    }
+
+    #[test]
+    fn done_and_non_data_lines() {
+        assert!(matches!(parse_event("data: [DONE]"), Ok(Event::Done)));
+        assert!(matches!(parse_event("data:[DONE]"), Ok(Event::Done)));
+        for line in ["", ": keep-alive", ": OPENROUTER PROCESSING", "event: ping"] {
+            assert!(matches!(parse_event(line), Ok(Event::Skip)), "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn content_reasoning_and_usage() {
+        let (delta, reasoning, usage) =
+            data(r#"data: {"choices":[{"delta":{"content":"ALPHA_EXPR: rank(close)"}}]}"#);
+        assert_eq!(delta.as_deref(), Some("ALPHA_EXPR: rank(close)"));
+        assert_eq!((reasoning, usage), (None, None));
+
+        let (delta, reasoning, _) =
+            data(r#"data: {"choices":[{"delta":{"content":"","reasoning_content":"想一想"}}]}"#);
+        assert_eq!((delta, reasoning.as_deref()), (None, Some("想一想")));
+        let (_, reasoning, _) = data(r#"data: {"choices":[{"delta":{"reasoning":"r"}}]}"#);
+        assert_eq!(reasoning.as_deref(), Some("r"));
+
+        // 旧式 completions 的 text 字段
+        let (delta, _, _) = data(r#"data: {"choices":[{"text":"abc"}]}"#);
+        assert_eq!(delta.as_deref(), Some("abc"));
+
+        let (delta, _, usage) =
+            data(r#"data: {"choices":[],"usage":{"prompt_tokens":12,"completion_tokens":34}}"#);
+        assert_eq!(delta, None);
+        assert_eq!(
+            usage,
+            Some(TokenUsage {
+                prompt_tokens: 12,
+                completion_tokens: 34
+            })
+        );
+    }
+
+    #[test]
+    fn error_events_and_bad_json() {
+        for line in [
+            r#"data: {"error":{"message":"rate limited","code":429}}"#,
+            "data: {\"choices\":[{\"delta\":",
+        ] {
+            assert!(
+                matches!(parse_event(line), Err(LlmError::InvalidResponse(_))),
+                "{}",
+                line
+            );
+        }
+    }
+}
@@ -7,18 +7,52 @@ pub struct ChatRequest {
     pub user: String,
     pub temperature: f32,
     pub max_tokens: u32,
+    /// 结构化输出的 `response_format`；供应商不支持时忽略
+    pub response_format: Option<serde_json::Value>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct ChatResponse {
     pub text: String,
     pub raw: Option<String>,
+    /// 流式读取途中超时/断开，text 只是已收到的部分
+    pub truncated: bool,
+    /// 实际响应的供应商与模型（经供应商链转发时填写）
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    /// 响应中的 `usage`；服务未返回时为 None
+    pub usage: Option<TokenUsage>,
+    /// 推理模型的思考过程（`reasoning_content` 字段或 `<think>` 块），已从 text 中剥离
+    pub reasoning: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TokenUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+impl TokenUsage {
+    /// 从响应（或流式事件）的 `usage` 字段读取
+    pub fn from_json(v: &serde_json::Value) -> Option<Self> {
+        let u = v.get("usage").filter(|u| u.is_object())?;
+        let n = |k: &str| u.get(k).and_then(|x| x.as_u64()).unwrap_or(0);
+        Some(Self {
+            prompt_tokens: n("prompt_tokens"),
+            completion_tokens: n("completion_tokens"),
+        })
+    }
 }
 
+/// 流式增量回调：每收到一段文本调用一次
+pub type DeltaSink<'a> = &'a (dyn Fn(&str) + Send + Sync);
+
 #[derive(thiserror::Error, Debug)]
 pub enum LlmError {
     #[error("missing env {0}")]
-    MissingEnv(&'static str),
+    MissingEnv(String),
+    #[error("provider config: {0}")]
+    Config(String),
     #[error("http error: {0}")]
     Http(String),
     #[error("unauthorized")]
@@ -27,9 +61,47 @@ pub enum LlmError {
     RateLimited,
     #[error("invalid response: {0}")]
     InvalidResponse(String),
+    /// 供应商链中某一项返回的错误，附带该项的供应商与模型
+    #[error("{provider}/{model}: {source}")]
+    Entry {
+        provider: String,
+        model: String,
+        source: Box<LlmError>,
+    },
+}
+
+impl LlmError {
+    /// 去掉供应商链附加的来源后的错误本身
+    pub fn kind(&self) -> &LlmError {
+        match self {
+            Self::Entry { source, .. } => source.kind(),
+            other => other,
+        }
+    }
+
+    /// 出错的 (供应商, 模型)；未经供应商链时为 None
+    pub fn origin(&self) -> Option<(&str, &str)> {
+        match self {
+            Self::Entry {
+                provider, model, ..
+            } => Some((provider, model)),
+            _ => None,
+        }
+    }
 }
 
 #[async_trait]
 pub trait LlmProvider: Send + Sync {
     async fn chat(&self, req: ChatRequest) -> Result<ChatResponse, LlmError>;
+
+    /// 流式对话：边收边回调增量文本；默认退化为一次性请求
+    async fn chat_stream(
+        &self,
+        req: ChatRequest,
+        on_delta: DeltaSink<'_>,
+    ) -> Result<ChatResponse, LlmError> {
+        let resp = self.chat(req).await?;
+        on_delta(&resp.text);
+        Ok(resp)
+    }
 }
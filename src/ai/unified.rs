@@ -1,137 +1,349 @@
-use crate::ai::cerebras::CerebrasProvider;
-use crate::ai::types::{ChatRequest, ChatResponse, LlmError, LlmProvider};
-use crate::ai::OpenRouterProvider;
-use crate::ai::XirangProvider;
+//! 由环境变量选择的供应商，支持按顺序故障转移的供应商链。
+//!
+//! - `LLM_CHAIN=openrouter:deepseek/deepseek-r1, cerebras:gpt-oss-120b@120, ollama:qwen2.5:7b`：
+//!   每项为 `供应商[:模型][@冷却秒数]`，省略模型时沿用请求中的模型
+//! - 未设置 `LLM_CHAIN` 时只有 `LLM_PROVIDER`（默认 openrouter）一项
+//! - `LLM_COOLDOWN_SECS`：未单独指定时的冷却时长，默认 60
+//!
+//! 某项返回限流/HTTP/鉴权错误后进入冷却，请求转给下一项；冷却结束后重新优先使用排在前面的项。
+//! 冷却状态按 `供应商[:模型]` 在进程内共享，各 worker 不会重复撞上同一个已限流的项。
+//! 返回的错误带有出错项的供应商与模型（[`LlmError::Entry`]）。
+//! 名称为 `mock` 的项使用离线模拟供应商（见 [`crate::ai::mock`]）。
+
+use crate::ai::mock::MockProvider;
+use crate::ai::openai_compat::OpenAiCompatProvider;
+use crate::ai::types::{ChatRequest, ChatResponse, DeltaSink, LlmError, LlmProvider};
 use async_trait::async_trait;
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
 
-#[derive(Clone)]
-pub enum InnerProvider {
-    OpenRouter(OpenRouterProvider),
-    Cerebras(CerebrasProvider),
-    Xirang(XirangProvider),
+enum Backend {
+    Compat(OpenAiCompatProvider),
+    Mock(MockProvider),
+}
+
+impl Backend {
+    fn from_env(name: &str, worker: Option<usize>) -> Result<Self, LlmError> {
+        if name.eq_ignore_ascii_case("mock") {
+            return Ok(Self::Mock(MockProvider::from_env(worker)?));
+        }
+        Ok(Self::Compat(OpenAiCompatProvider::from_env(name, worker)?))
+    }
+
+    fn provider(&self) -> &dyn LlmProvider {
+        match self {
+            Self::Compat(p) => p,
+            Self::Mock(p) => p,
+        }
+    }
+}
+
+/// 各项的冷却截止时间，按 `供应商[:模型]` 在进程内共享
+fn cooldowns() -> &'static Mutex<HashMap<String, Instant>> {
+    static COOLDOWNS: OnceLock<Mutex<HashMap<String, Instant>>> = OnceLock::new();
+    COOLDOWNS.get_or_init(Default::default)
+}
+
+struct ChainEntry {
+    provider: Backend,
+    name: String,
+    model: Option<String>,
+    cooldown: Duration,
+}
+
+impl ChainEntry {
+    fn key(&self) -> String {
+        match &self.model {
+            Some(m) => format!("{}:{}", self.name, m),
+            None => self.name.clone(),
+        }
+    }
+
+    fn cooling(&self, now: Instant) -> bool {
+        let map = cooldowns().lock().unwrap_or_else(|e| e.into_inner());
+        map.get(&self.key()).is_some_and(|t| *t > now)
+    }
+
+    fn cool_down(&self) {
+        let mut map = cooldowns().lock().unwrap_or_else(|e| e.into_inner());
+        map.insert(self.key(), Instant::now() + self.cooldown);
+    }
+}
+
+/// `LLM_CHAIN` 中的一项
+#[derive(Debug, PartialEq)]
+struct ChainSpec {
+    provider: String,
+    model: Option<String>,
+    cooldown_secs: u64,
+}
+
+/// 解析 `供应商[:模型][@冷却秒数]` 列表，逗号或分号分隔
+fn parse_chain(raw: &str, default_cooldown: u64) -> Vec<ChainSpec> {
+    raw.split([',', ';'])
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|item| {
+            let (spec, cooldown_secs) = match item.rsplit_once('@') {
+                Some((s, secs)) if secs.trim().parse::<u64>().is_ok() => {
+                    (s.trim(), secs.trim().parse::<u64>().unwrap())
+                }
+                _ => (item, default_cooldown),
+            };
+            // 只按第一个 ':' 切分，模型名本身可以带 ':'（如 qwen2.5:7b）
+            let (provider, model) = match spec.split_once(':') {
+                Some((n, m)) => (n.trim(), Some(m.trim()).filter(|m| !m.is_empty())),
+                None => (spec, None),
+            };
+            ChainSpec {
+                provider: provider.to_string(),
+                model: model.map(str::to_string),
+                cooldown_secs,
+            }
+        })
+        .collect()
 }
 
 #[derive(Clone)]
 pub struct AnyProvider {
-    inner: InnerProvider,
+    chain: Arc<Vec<ChainEntry>>,
 }
 
 impl AnyProvider {
     pub fn from_env() -> Result<Self, LlmError> {
-        let which = std::env::var("LLM_PROVIDER")
-            .unwrap_or_else(|_| "openrouter".to_string())
-            .to_lowercase();
-        match which.as_str() {
-            "cerebras" => {
-                let p = CerebrasProvider::from_env()?;
-                Ok(Self {
-                    inner: InnerProvider::Cerebras(p),
-                })
-            }
-            "xirang" => {
-                let p = XirangProvider::from_env()?;
-                Ok(Self {
-                    inner: InnerProvider::Xirang(p),
-                })
-            }
-            _ => {
-                let p = OpenRouterProvider::from_env()?;
-                Ok(Self {
-                    inner: InnerProvider::OpenRouter(p),
-                })
-            }
-        }
+        Self::build(None)
     }
 
     pub fn from_env_for_worker(worker_idx: usize) -> Result<Self, LlmError> {
-        let which = std::env::var("LLM_PROVIDER")
-            .unwrap_or_else(|_| "openrouter".to_string())
-            .to_lowercase();
-        match which.as_str() {
-            "cerebras" => {
-                let keys_raw = std::env::var("CEREBRAS_API_KEYS").ok();
-                let keys: Vec<String> = keys_raw
-                    .map(|s| {
-                        s.split(|c| c == ',' || c == ';' || c == '\n' || c == '\t' || c == ' ')
-                            .map(|x| x.trim().to_string())
-                            .filter(|x| !x.is_empty())
-                            .collect::<Vec<_>>()
-                    })
-                    .unwrap_or_default();
-                let base_url = std::env::var("CEREBRAS_BASE_URL")
-                    .unwrap_or_else(|_| "https://api.cerebras.ai/v1".to_string());
-                let key = if keys.is_empty() {
-                    std::env::var("CEREBRAS_API_KEY")
-                        .map_err(|_| LlmError::MissingEnv("CEREBRAS_API_KEY"))?
-                } else {
-                    let idx = worker_idx % keys.len();
-                    keys[idx].clone()
-                };
-                let p = CerebrasProvider::new(key, base_url);
-                Ok(Self {
-                    inner: InnerProvider::Cerebras(p),
-                })
+        Self::build(Some(worker_idx))
+    }
+
+    fn build(worker: Option<usize>) -> Result<Self, LlmError> {
+        let default_cooldown = std::env::var("LLM_COOLDOWN_SECS")
+            .ok()
+            .and_then(|s| s.trim().parse::<u64>().ok())
+            .unwrap_or(60);
+        let raw = std::env::var("LLM_CHAIN")
+            .ok()
+            .filter(|s| !s.trim().is_empty())
+            .unwrap_or_else(|| {
+                std::env::var("LLM_PROVIDER").unwrap_or_else(|_| "openrouter".to_string())
+            });
+        let mut chain = Vec::new();
+        for spec in parse_chain(&raw, default_cooldown) {
+            chain.push(ChainEntry {
+                provider: Backend::from_env(&spec.provider, worker)?,
+                name: spec.provider.to_ascii_lowercase(),
+                model: spec.model,
+                cooldown: Duration::from_secs(spec.cooldown_secs),
+            });
+        }
+        if chain.is_empty() {
+            return Err(LlmError::Config("LLM_CHAIN 为空".to_string()));
+        }
+        Ok(Self {
+            chain: Arc::new(chain),
+        })
+    }
+
+    /// 按顺序尝试未冷却的项；`attempt` 返回可转移的错误时冷却该项并换下一项
+    async fn with_failover<'a, F>(
+        &'a self,
+        req: ChatRequest,
+        attempt: F,
+    ) -> Result<ChatResponse, LlmError>
+    where
+        F: Fn(&'a dyn LlmProvider, ChatRequest) -> BoxFuture<'a, Result<ChatResponse, LlmError>>,
+    {
+        let now = Instant::now();
+        let mut last_err = None;
+        for (i, entry) in self.chain.iter().enumerate() {
+            if entry.cooling(now) {
+                continue;
             }
-            "xirang" => {
-                let keys_raw = std::env::var("XIRANG_APP_KEYS").ok();
-                let keys: Vec<String> = keys_raw
-                    .map(|s| {
-                        s.split(|c| c == ',' || c == ';' || c == '\n' || c == '\t' || c == ' ')
-                            .map(|x| x.trim().to_string())
-                            .filter(|x| !x.is_empty())
-                            .collect::<Vec<_>>()
-                    })
-                    .unwrap_or_default();
-                let base_url = std::env::var("XIRANG_BASE_URL")
-                    .unwrap_or_else(|_| "https://wishub-x6.ctyun.cn/v1".to_string());
-                let key = if keys.is_empty() {
-                    std::env::var("XIRANG_APP_KEY")
-                        .or_else(|_| std::env::var("XIRANG_app_key"))
-                        .map_err(|_| LlmError::MissingEnv("XIRANG_APP_KEY"))?
-                } else {
-                    let idx = worker_idx % keys.len();
-                    keys[idx].clone()
-                };
-                let p = XirangProvider::new(key, base_url);
-                Ok(Self {
-                    inner: InnerProvider::Xirang(p),
-                })
+            let mut r = req.clone();
+            if let Some(m) = &entry.model {
+                r.model = m.clone();
             }
-            _ => {
-                let keys_raw = std::env::var("OPENROUTER_API_KEYS").ok();
-                let keys: Vec<String> = keys_raw
-                    .map(|s| {
-                        s.split(|c| c == ',' || c == ';' || c == '\n' || c == '\t' || c == ' ')
-                            .map(|x| x.trim().to_string())
-                            .filter(|x| !x.is_empty())
-                            .collect::<Vec<_>>()
-                    })
-                    .unwrap_or_default();
-                let base_url = std::env::var("OPENROUTER_BASE_URL")
-                    .unwrap_or_else(|_| "https://openrouter.ai/api/v1".to_string());
-                let key = if keys.is_empty() {
-                    std::env::var("OPENROUTER_API_KEY")
-                        .map_err(|_| LlmError::MissingEnv("OPENROUTER_API_KEY"))?
-                } else {
-                    let idx = worker_idx % keys.len();
-                    keys[idx].clone()
-                };
-                let p = OpenRouterProvider::new(key, "unused".to_string(), base_url);
-                Ok(Self {
-                    inner: InnerProvider::OpenRouter(p),
-                })
+            let model = r.model.clone();
+            let label = format!("{}/{}", entry.name, model);
+            let with_origin = |e: LlmError| LlmError::Entry {
+                provider: entry.name.clone(),
+                model: model.clone(),
+                source: Box::new(e),
+            };
+            match attempt(entry.provider.provider(), r).await {
+                Ok(mut resp) => {
+                    resp.provider = Some(entry.name.clone());
+                    resp.model = Some(model);
+                    return Ok(resp);
+                }
+                Err(e @ (LlmError::RateLimited | LlmError::Http(_) | LlmError::Unauthorized)) => {
+                    if self.chain.len() > 1 {
+                        log::warn!(
+                            "{} 失败（{}），冷却 {}s{}",
+                            label,
+                            e,
+                            entry.cooldown.as_secs(),
+                            if i + 1 < self.chain.len() {
+                                "，转下一个供应商"
+                            } else {
+                                ""
+                            }
+                        );
+                        entry.cool_down();
+                    }
+                    last_err = Some(with_origin(e));
+                }
+                Err(e) => return Err(with_origin(e)),
             }
         }
+        // 全部冷却中时按限流处理，由调用方退避
+        Err(last_err.unwrap_or(LlmError::RateLimited))
     }
 }
 
 #[async_trait]
 impl LlmProvider for AnyProvider {
     async fn chat(&self, req: ChatRequest) -> Result<ChatResponse, LlmError> {
-        match &self.inner {
-            InnerProvider::OpenRouter(p) => p.chat(req).await,
-            InnerProvider::Cerebras(p) => p.chat(req).await,
-            InnerProvider::Xirang(p) => p.chat(req).await,
+        self.with_failover(req, |p, r| p.chat(r)).await
+    }
+
+    async fn chat_stream(
+        &self,
+        req: ChatRequest,
+        on_delta: DeltaSink<'_>,
+    ) -> Result<ChatResponse, LlmError> {
+        self.with_failover(req, |p, r| p.chat_stream(r, on_delta))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(provider: &str, model: Option<&str>, cooldown_secs: u64) -> ChainSpec {
+        ChainSpec {
+            provider: provider.to_string(),
+            model: model.map(str::to_string),
+            cooldown_secs,
+        }
+    }
+
+    #[test]
+    fn chain_grammar() {
+        assert_eq!(
+            parse_chain(
+                "openrouter:deepseek/deepseek-r1, cerebras:gpt-oss-120b@120; ollama:qwen2.5:7b",
+                60
+            ),
+            vec![
+                spec("openrouter", Some("deepseek/deepseek-r1"), 60),
+                spec("cerebras", Some("gpt-oss-120b"), 120),
+                spec("ollama", Some("qwen2.5:7b"), 60),
+            ]
+        );
+        assert_eq!(
+            parse_chain("ollama:qwen2.5:7b@30, mock, xirang:@5, ,", 60),
+            vec![
+                spec("ollama", Some("qwen2.5:7b"), 30),
+                spec("mock", None, 60),
+                spec("xirang", None, 5),
+            ]
+        );
+        // '@' 后不是数字时视为模型名的一部分
+        assert_eq!(
+            parse_chain("openrouter:org/model@beta", 60),
+            vec![spec("openrouter", Some("org/model@beta"), 60)]
+        );
+        assert!(parse_chain(" , ; ", 60).is_empty());
+    }
+
+    fn entry(name: &str, mock: MockProvider, cooldown: Duration) -> ChainEntry {
+        ChainEntry {
+            provider: Backend::Mock(mock),
+            name: name.to_string(),
+            model: Some("m".to_string()),
+            cooldown,
+        }
+    }
+
+    fn request() -> ChatRequest {
+        ChatRequest {
+            model: "default".to_string(),
+            system: String::new(),
+            user: "Generate 5 unique alpha factor expressions.".to_string(),
+            temperature: 0.7,
+            max_tokens: 256,
+            response_format: None,
+        }
+    }
+
+    async fn served_by(p: &AnyProvider) -> String {
+        p.chat(request()).await.unwrap().provider.unwrap()
+    }
+
+    #[tokio::test]
+    async fn fails_over_while_primary_cools_down() {
+        // 冷却表在进程内共享，名称各测试唯一
+        let primary = entry(
+            "failover-primary",
+            MockProvider::new(1).rate_limit_every(2),
+            Duration::from_millis(200),
+        );
+        let backup = entry(
+            "failover-backup",
+            MockProvider::new(2),
+            Duration::from_secs(60),
+        );
+        let p = AnyProvider {
+            chain: Arc::new(vec![primary, backup]),
+        };
+
+        assert_eq!(served_by(&p).await, "failover-primary");
+        // 第二次调用主项限流，转给备用项并冷却主项
+        assert_eq!(served_by(&p).await, "failover-backup");
+        // 冷却期内克隆出的实例同样跳过主项
+        let clone = p.clone();
+        assert_eq!(served_by(&clone).await, "failover-backup");
+        let resp = clone.chat(request()).await.unwrap();
+        assert_eq!(resp.model.as_deref(), Some("m"));
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(served_by(&p).await, "failover-primary");
+    }
+
+    #[tokio::test]
+    async fn all_entries_cooling_reports_rate_limit() {
+        let p = AnyProvider {
+            chain: Arc::new(vec![
+                entry(
+                    "cooling-a",
+                    MockProvider::new(1).rate_limit_every(1),
+                    Duration::from_secs(60),
+                ),
+                entry(
+                    "cooling-b",
+                    MockProvider::new(2).rate_limit_every(1),
+                    Duration::from_secs(60),
+                ),
+            ]),
+        };
+        match p.chat(request()).await {
+            Err(LlmError::Entry {
+                provider, source, ..
+            }) => {
+                assert_eq!(provider, "cooling-b");
+                assert!(matches!(*source, LlmError::RateLimited));
+            }
+            other => panic!("unexpected: {:?}", other.map(|r| r.text)),
         }
+        assert!(matches!(
+            p.chat(request()).await,
+            Err(LlmError::RateLimited)
+        ));
     }
 }
@@ -1,4 +1,5 @@
 use crate::app_state::{AlphaSummary, AppEvent};
+use crate::backtest::budget::SimulationBudget;
 use crate::storage::repository::{AlphaRepository, BacktestRepository};
 use sea_orm::DatabaseConnection;
 use serde_json::Value;
@@ -18,28 +19,36 @@ fn checks_has_fail(v: &Value) -> bool {
 }
 
 pub async fn refresh_ui(db: &Arc<DatabaseConnection>, tx: &mpsc::UnboundedSender<AppEvent>) {
-    // 1. 加载全部 Alpha 记录
-    if let Ok(alphas) = AlphaRepository::load_all_by_status(db, "ALL").await {
+    // 1. 加载全部 Alpha 记录（连同来源）
+    if let Ok(alphas) = AlphaRepository::load_all_with_provenance(db).await {
         let list = alphas
             .into_iter()
-            .map(|a| AlphaSummary {
-                expression: a.expression,
-                status: a.status,
-                has_fail: checks_has_fail(&a.checks_json),
-                is_sharpe: a.core_metrics.is_sharpe,
+            .map(|a| {
+                let (source, model) = a
+                    .provenance
+                    .map_or((None, None), |p| (Some(p.source), p.model));
+                AlphaSummary {
+                    expression: a.expression,
+                    status: a.status,
+                    has_fail: checks_has_fail(&a.checks_json),
+                    is_sharpe: a.core_metrics.is_sharpe,
+                    metrics: a.core_metrics,
+                    complexity: a.complexity,
+                    source,
+                    model,
+                }
             })
             .collect();
         let _ = tx.send(AppEvent::Alphas(list));
     }
 
     // 2. 加载回测统计数据
-    if let Ok(stats) = BacktestRepository::get_stats(db).await {
-        let _ = tx.send(AppEvent::Stats(stats));
-    }
+    refresh_stats(db, tx).await;
 }
 
 pub async fn refresh_stats(db: &Arc<DatabaseConnection>, tx: &mpsc::UnboundedSender<AppEvent>) {
-    if let Ok(stats) = BacktestRepository::get_stats(db).await {
-        let _ = tx.send(AppEvent::Stats(stats));
+    if let Ok(mut stats) = BacktestRepository::get_stats(db).await {
+        stats.budget = SimulationBudget::from_env().status(db).await;
+        let _ = tx.send(AppEvent::Stats(Box::new(stats)));
     }
 }
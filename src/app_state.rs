@@ -1,6 +1,9 @@
 use crate::backtest::model::BacktestStats;
 use crate::commands::AppCommand;
-use crate::storage::repository::{AlphaDto, FieldStatsRow};
+use crate::expr::complexity::{Complexity, COMPLEXITY_KEYS};
+use crate::storage::repository::{
+    AlphaDto, CoreMetrics, FamilyRow, FieldStatsRow, UsageRow, METRIC_NAMES, METRIC_PERIODS,
+};
 use crossterm::event::KeyCode;
 use ratatui::widgets::ListState;
 use std::str::FromStr;
@@ -11,6 +14,62 @@ pub struct FilterState {
     pub status: Option<String>,
     pub query: String,
     pub no_fail: bool,
+    pub metric_filters: Vec<MetricFilter>,
+    pub source: Option<String>,
+    pub model: Option<String>,
+}
+
+/// 指标筛选条件，如 `os_sharpe>1`、`test_fitness>=0.8`、`depth<=4`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricFilter {
+    pub key: String,
+    pub op: String,
+    pub value: f64,
+}
+
+impl MetricFilter {
+    /// 解析 `<阶段>_<指标><比较符><数值>`（阶段为 is/os/train/test），
+    /// 或复杂度条件 `<operators|depth|fields|categories|lookback><比较符><数值>`
+    pub fn parse(tok: &str) -> Option<Self> {
+        let t = tok.to_ascii_lowercase();
+        for op in [">=", "<=", "!=", ">", "<", "="] {
+            if let Some((key, val)) = t.split_once(op) {
+                if !COMPLEXITY_KEYS.contains(&key) {
+                    let (period, metric) = key.split_once('_')?;
+                    if !METRIC_PERIODS.contains(&period) || !METRIC_NAMES.contains(&metric) {
+                        return None;
+                    }
+                }
+                let value = val.parse::<f64>().ok()?;
+                return Some(Self {
+                    key: key.to_string(),
+                    op: op.to_string(),
+                    value,
+                });
+            }
+        }
+        None
+    }
+
+    /// 缺少该指标的 Alpha 视为不满足
+    pub fn matches(&self, alpha: &AlphaSummary) -> bool {
+        let v = if COMPLEXITY_KEYS.contains(&self.key.as_str()) {
+            alpha.complexity.and_then(|c| c.get(&self.key))
+        } else {
+            alpha.metrics.get(&self.key)
+        };
+        let Some(v) = v else {
+            return false;
+        };
+        match self.op.as_str() {
+            ">=" => v >= self.value,
+            "<=" => v <= self.value,
+            "!=" => v != self.value,
+            ">" => v > self.value,
+            "<" => v < self.value,
+            _ => v == self.value,
+        }
+    }
 }
 
 #[derive(PartialEq, Debug, Clone)]
@@ -19,6 +78,8 @@ pub enum ViewMode {
     BacktestQueue,
     Detail,
     FieldStats,
+    Families,
+    Usage,
 }
 
 #[derive(PartialEq, Debug, Clone)]
@@ -39,6 +100,11 @@ pub struct AlphaSummary {
     pub status: String,
     pub has_fail: bool,
     pub is_sharpe: Option<f64>,
+    pub metrics: CoreMetrics,
+    pub complexity: Option<Complexity>,
+    /// 来源与生成模型（见 alpha_provenance）；历史记录为 None
+    pub source: Option<String>,
+    pub model: Option<String>,
 }
 
 #[derive(Debug)]
@@ -47,9 +113,15 @@ pub enum AppEvent {
     Message(String),
     Error(String),
     Alphas(Vec<AlphaSummary>),
-    Detail(AlphaDto),
-    Stats(BacktestStats),
+    Detail(Box<AlphaDto>),
+    Stats(Box<BacktestStats>),
     FieldStatsRows(Vec<FieldStatsRow>),
+    Families(Vec<FamilyRow>),
+    Usage(Vec<UsageRow>),
+    Similar {
+        expr: String,
+        items: Vec<(String, f64)>,
+    },
 }
 
 pub struct App {
@@ -64,7 +136,11 @@ pub struct App {
     pub selected_detail: Option<AlphaDto>,
     pub backtest_stats: BacktestStats,
     pub field_stats: Vec<FieldStatsRow>,
+    pub families: Vec<FamilyRow>,
+    pub usage: Vec<UsageRow>,
     pub detail_scroll: u16,
+    pub detail_formatted: bool, // 详情页表达式按缩进树展示
+    pub similar_alphas: Option<(String, Vec<(String, f64)>)>, // (目标表达式, [(相似表达式, 相似度)])
     pub command_input: String,
     pub command_cursor: usize,
     pub command_history: Vec<String>,
@@ -72,6 +148,9 @@ pub struct App {
     pub filter_status: Option<String>,
     pub filter_query: String,
     pub filter_no_fail: bool,
+    pub filter_metrics: Vec<MetricFilter>,
+    pub filter_source: Option<String>, // 来源完全匹配（不区分大小写）
+    pub filter_model: Option<String>,  // 模型名包含（不区分大小写）
     pub log_messages: Vec<String>,
     pub cmd_tx: mpsc::UnboundedSender<AppCommand>,
     pub evt_rx: Option<mpsc::UnboundedReceiver<AppEvent>>, // Changed to Option to allow taking it out
@@ -111,7 +190,11 @@ impl App {
             selected_detail: None,
             backtest_stats: BacktestStats::default(),
             field_stats: Vec::new(),
+            families: Vec::new(),
+            usage: Vec::new(),
             detail_scroll: 0,
+            detail_formatted: false,
+            similar_alphas: None,
             command_input: String::new(),
             command_cursor: 0,
             command_history: Vec::new(),
@@ -119,6 +202,9 @@ impl App {
             filter_status: None,
             filter_query: String::new(),
             filter_no_fail: false,
+            filter_metrics: Vec::new(),
+            filter_source: None,
+            filter_model: None,
             log_messages,
             cmd_tx,
             evt_rx: Some(evt_rx),
@@ -137,6 +223,7 @@ impl App {
     pub fn get_completion_hint(&self) -> Option<String> {
         let commands = vec![
             "catch", "backtest", "help", "generate", "verify", "delete", "quit", "fields",
+            "similar", "cluster", "families", "eval", "usage",
         ];
         let input = self.command_input.trim();
 
@@ -207,6 +294,15 @@ impl App {
             } else {
                 0u8.hash(&mut hasher);
             }
+            for period in ["os", "train", "test"] {
+                for metric in METRIC_NAMES {
+                    let key = format!("{}_{}", period, metric);
+                    a.metrics.get(&key).map(|v| v.to_bits()).hash(&mut hasher);
+                }
+            }
+            a.complexity.hash(&mut hasher);
+            a.source.hash(&mut hasher);
+            a.model.hash(&mut hasher);
         }
         hasher.finish()
     }
@@ -216,6 +312,9 @@ impl App {
             status: self.filter_status.clone(),
             query: self.filter_query.clone(),
             no_fail: self.filter_no_fail,
+            metric_filters: self.filter_metrics.clone(),
+            source: self.filter_source.clone(),
+            model: self.filter_model.clone(),
         };
         let cur_hash = self.compute_alphas_hash();
         if let (Some(cached), Some(last_fs)) = (&self.cached_filtered, &self.last_filter_state) {
@@ -245,6 +344,27 @@ impl App {
                         return false;
                     }
                 }
+                if !self.filter_metrics.iter().all(|f| f.matches(a)) {
+                    return false;
+                }
+                if let Some(source) = &self.filter_source {
+                    if !a
+                        .source
+                        .as_deref()
+                        .is_some_and(|s| s.eq_ignore_ascii_case(source))
+                    {
+                        return false;
+                    }
+                }
+                if let Some(model) = &self.filter_model {
+                    if !a
+                        .model
+                        .as_deref()
+                        .is_some_and(|m| m.to_ascii_lowercase().contains(model))
+                    {
+                        return false;
+                    }
+                }
                 true
             })
             .cloned()
@@ -347,6 +467,20 @@ impl App {
         }
     }
 
+    /// 查询与当前 Alpha 结构最相似的已有 Alpha（结果在详情页展示）
+    pub fn request_similar(&mut self) {
+        let expr = match self.view_mode {
+            ViewMode::Detail => self.selected_detail.as_ref().map(|d| d.expression.clone()),
+            _ => self
+                .alpha_list
+                .get(self.selected_index)
+                .map(|a| a.expression.clone()),
+        };
+        if let Some(expr) = expr {
+            let _ = self.cmd_tx.send(AppCommand::Similar { expr });
+        }
+    }
+
     pub fn request_field_stats(&mut self) {
         let _ = self.cmd_tx.send(AppCommand::FieldStats);
     }
@@ -374,13 +508,34 @@ impl App {
                             if args.is_empty() {
                                 self.filter_query.clear();
                                 self.filter_no_fail = false;
+                                self.filter_metrics.clear();
+                                self.filter_source = None;
+                                self.filter_model = None;
                             } else if args == "clear" || args == "--clear" {
                                 self.filter_query.clear();
                                 self.filter_no_fail = false;
+                                self.filter_metrics.clear();
+                                self.filter_source = None;
+                                self.filter_model = None;
                             } else {
                                 let mut nofail = self.filter_no_fail;
                                 let mut query_parts: Vec<&str> = Vec::new();
+                                let mut metric_filters: Vec<MetricFilter> = Vec::new();
+                                let mut source = None;
+                                let mut model = None;
                                 for tok in args.split_whitespace() {
+                                    if let Some(v) = tok.strip_prefix("source=") {
+                                        source = Some(v.to_ascii_lowercase());
+                                        continue;
+                                    }
+                                    if let Some(v) = tok.strip_prefix("model=") {
+                                        model = Some(v.to_ascii_lowercase());
+                                        continue;
+                                    }
+                                    if let Some(mf) = MetricFilter::parse(tok) {
+                                        metric_filters.push(mf);
+                                        continue;
+                                    }
                                     let t = tok.to_ascii_lowercase();
                                     if t == "nofail" || t == "--nofail" || t == "--no-fail" {
                                         nofail = true;
@@ -403,6 +558,9 @@ impl App {
                                 }
                                 self.filter_no_fail = nofail;
                                 self.filter_query = query_parts.join(" ");
+                                self.filter_metrics = metric_filters;
+                                self.filter_source = source;
+                                self.filter_model = model;
                             }
                             self.apply_filters();
                             self.command_history.push(cmd_owned.clone());
@@ -554,8 +712,11 @@ impl App {
                     }
                 } else {
                     // 在主视图中
-                    if self.view_mode == ViewMode::Detail {
-                        // 详情页向上滚动
+                    if matches!(
+                        self.view_mode,
+                        ViewMode::Detail | ViewMode::Families | ViewMode::Usage
+                    ) {
+                        // 详情页 / 家族页 / 用量页向上滚动
                         self.detail_scroll = self.detail_scroll.saturating_sub(1);
                     } else if self.selected_index > 0 {
                         // 在 Alpha 列表中向上导航
@@ -567,14 +728,17 @@ impl App {
             KeyCode::Down => {
                 if self.focus_area == FocusArea::Menu {
                     // 在菜单中向下导航
-                    let menu_items_count = 4;
+                    let menu_items_count = 6;
                     if self.menu_selected_index < menu_items_count - 1 {
                         self.menu_selected_index += 1;
                     }
                 } else {
                     // 在主视图中
-                    if self.view_mode == ViewMode::Detail {
-                        // 详情页向下滚动
+                    if matches!(
+                        self.view_mode,
+                        ViewMode::Detail | ViewMode::Families | ViewMode::Usage
+                    ) {
+                        // 详情页 / 家族页 / 用量页向下滚动
                         self.detail_scroll = self.detail_scroll.saturating_add(1);
                     } else if self.selected_index < self.alpha_list.len().saturating_sub(1) {
                         // 在 Alpha 列表中向下导航
@@ -602,6 +766,16 @@ impl App {
                             self.view_mode = ViewMode::FieldStats;
                             self.request_field_stats();
                         }
+                        4 => {
+                            self.view_mode = ViewMode::Families;
+                            self.detail_scroll = 0;
+                            let _ = self.cmd_tx.send(AppCommand::Families);
+                        }
+                        5 => {
+                            self.view_mode = ViewMode::Usage;
+                            self.detail_scroll = 0;
+                            let _ = self.cmd_tx.send(AppCommand::Usage { days: 7 });
+                        }
                         _ => {}
                     }
                     // 确认后自动切换焦点到主视图
@@ -623,6 +797,28 @@ impl App {
                 }
                 false
             }
+            KeyCode::Char('v') => {
+                // 详情页：原文 / 格式化 切换
+                if self.focus_area == FocusArea::MainView && self.view_mode == ViewMode::Detail {
+                    self.detail_formatted = !self.detail_formatted;
+                    self.detail_scroll = 0;
+                }
+                false
+            }
+            KeyCode::Char('s') => {
+                // 列表或详情页：查找结构相似的 Alpha，并进入详情页查看
+                if self.focus_area == FocusArea::MainView {
+                    if self.view_mode == ViewMode::AlphaList && !self.alpha_list.is_empty() {
+                        self.request_similar();
+                        self.view_mode = ViewMode::Detail;
+                        self.menu_selected_index = 2;
+                        self.request_detail();
+                    } else if self.view_mode == ViewMode::Detail {
+                        self.request_similar();
+                    }
+                }
+                false
+            }
             KeyCode::Char('f') => {
                 if self.focus_area == FocusArea::MainView && self.view_mode == ViewMode::AlphaList {
                     self.filter_status = match self.filter_status.as_deref() {
@@ -0,0 +1,321 @@
+use crate::storage::repository::BudgetRepository;
+use chrono::{DateTime, Local, Timelike};
+use log::warn;
+use sea_orm::DatabaseConnection;
+
+/// 模拟预算配置：每日/每小时上限 + 允许运行的时间段（本地时间）
+///
+/// - BACKTEST_DAILY_CAP: 每日最多模拟次数（未设置或 0 表示不限）
+/// - BACKTEST_HOURLY_CAP: 每小时最多模拟次数
+/// - BACKTEST_ACTIVE_HOURS: 允许运行的小时段，如 "22-6" 或 "0-8,20-24"，左闭右开，可跨零点
+#[derive(Debug, Clone)]
+pub struct SimulationBudget {
+    pub account: String,
+    pub daily_cap: Option<i64>,
+    pub hourly_cap: Option<i64>,
+    pub active_hours: Vec<(u32, u32)>,
+}
+
+/// 预算阻塞原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetBlock {
+    OutsideActiveHours,
+    DailyExhausted,
+    HourlyExhausted,
+    /// 读取已用额度失败，按不可用处理而不是当作 0
+    Unavailable,
+}
+
+impl BudgetBlock {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            BudgetBlock::OutsideActiveHours => "不在允许运行时段",
+            BudgetBlock::DailyExhausted => "今日额度已用完",
+            BudgetBlock::HourlyExhausted => "本小时额度已用完",
+            BudgetBlock::Unavailable => "读取模拟额度失败",
+        }
+    }
+}
+
+/// TUI 展示用的预算快照
+#[derive(Debug, Clone, Default)]
+pub struct BudgetStatus {
+    pub daily_used: i64,
+    pub daily_cap: Option<i64>,
+    pub hourly_used: i64,
+    pub hourly_cap: Option<i64>,
+    pub active_hours: String,
+    pub blocked: Option<&'static str>,
+}
+
+impl SimulationBudget {
+    pub fn from_env() -> Self {
+        let cap = |key: &str| {
+            std::env::var(key)
+                .ok()
+                .and_then(|s| s.trim().parse::<i64>().ok())
+                .filter(|v| *v > 0)
+        };
+        let active_hours = std::env::var("BACKTEST_ACTIVE_HOURS")
+            .map(|s| parse_active_hours(&s))
+            .unwrap_or_default();
+        let account = std::env::var("WQB_EMAIL").unwrap_or_else(|_| "default".to_string());
+        Self {
+            account,
+            daily_cap: cap("BACKTEST_DAILY_CAP"),
+            hourly_cap: cap("BACKTEST_HOURLY_CAP"),
+            active_hours,
+        }
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.daily_cap.is_none() && self.hourly_cap.is_none() && self.active_hours.is_empty()
+    }
+
+    pub fn in_active_hours(&self, now: DateTime<Local>) -> bool {
+        if self.active_hours.is_empty() {
+            return true;
+        }
+        let h = now.hour();
+        self.active_hours.iter().any(|&(start, end)| {
+            if start <= end {
+                h >= start && h < end
+            } else {
+                h >= start || h < end
+            }
+        })
+    }
+
+    fn day_key(now: DateTime<Local>) -> String {
+        format!("D{}", now.format("%Y-%m-%d"))
+    }
+
+    fn hour_key(now: DateTime<Local>) -> String {
+        format!("H{}", now.format("%Y-%m-%dT%H"))
+    }
+
+    /// 只读检查当前是否允许开始新的模拟
+    pub async fn check(&self, db: &DatabaseConnection) -> Result<(), BudgetBlock> {
+        self.check_at(db, Local::now()).await
+    }
+
+    async fn check_at(
+        &self,
+        db: &DatabaseConnection,
+        now: DateTime<Local>,
+    ) -> Result<(), BudgetBlock> {
+        if self.is_unlimited() {
+            return Ok(());
+        }
+        if !self.in_active_hours(now) {
+            return Err(BudgetBlock::OutsideActiveHours);
+        }
+        let buckets = [
+            (
+                self.daily_cap,
+                Self::day_key(now),
+                BudgetBlock::DailyExhausted,
+            ),
+            (
+                self.hourly_cap,
+                Self::hour_key(now),
+                BudgetBlock::HourlyExhausted,
+            ),
+        ];
+        for (cap, key, block) in buckets {
+            let Some(cap) = cap else { continue };
+            match BudgetRepository::get_count(db, &self.account, &key).await {
+                Ok(used) if used >= cap => return Err(block),
+                Ok(_) => {}
+                Err(e) => {
+                    warn!("读取模拟额度 {} 失败: {}", key, e);
+                    return Err(BudgetBlock::Unavailable);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 消耗一次模拟额度（原子检查上限），额度不足返回 false
+    pub async fn consume(&self, db: &DatabaseConnection) -> Result<bool, sea_orm::DbErr> {
+        let now = Local::now();
+        let buckets = vec![
+            (Self::day_key(now), self.daily_cap),
+            (Self::hour_key(now), self.hourly_cap),
+        ];
+        BudgetRepository::try_consume(db, &self.account, &buckets).await
+    }
+
+    /// 退还一次已扣减的额度（扣减后任务未能开始时调用）
+    pub async fn refund(&self, db: &DatabaseConnection) -> Result<(), sea_orm::DbErr> {
+        let now = Local::now();
+        BudgetRepository::refund(
+            db,
+            &self.account,
+            &[Self::day_key(now), Self::hour_key(now)],
+        )
+        .await
+    }
+
+    pub async fn status(&self, db: &DatabaseConnection) -> BudgetStatus {
+        let now = Local::now();
+        let daily_used = BudgetRepository::get_count(db, &self.account, &Self::day_key(now))
+            .await
+            .unwrap_or(0);
+        let hourly_used = BudgetRepository::get_count(db, &self.account, &Self::hour_key(now))
+            .await
+            .unwrap_or(0);
+        let blocked = self.check(db).await.err().map(|b| b.describe());
+        let active_hours = if self.active_hours.is_empty() {
+            "全天".to_string()
+        } else {
+            self.active_hours
+                .iter()
+                .map(|(s, e)| format!("{:02}-{:02}", s, e))
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        BudgetStatus {
+            daily_used,
+            daily_cap: self.daily_cap,
+            hourly_used,
+            hourly_cap: self.hourly_cap,
+            active_hours,
+            blocked,
+        }
+    }
+}
+
+/// 解析 "22-6,12-13" 形式的小时段，非法片段忽略
+fn parse_active_hours(raw: &str) -> Vec<(u32, u32)> {
+    raw.split(',')
+        .filter_map(|part| {
+            let (a, b) = part.trim().split_once('-')?;
+            let start = a.trim().parse::<u32>().ok()?;
+            let end = b.trim().parse::<u32>().ok()?;
+            if start > 23 || end > 24 || start == end {
+                return None;
+            }
+            Some((start, end))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use sea_orm::ConnectionTrait;
+
+    fn at_hour(h: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(2026, 3, 10, h, 30, 0)
+            .earliest()
+            .unwrap()
+    }
+
+    fn budget(daily_cap: Option<i64>, hourly_cap: Option<i64>, hours: &str) -> SimulationBudget {
+        SimulationBudget {
+            account: "tester".to_string(),
+            daily_cap,
+            hourly_cap,
+            active_hours: parse_active_hours(hours),
+        }
+    }
+
+    #[test]
+    fn parse_active_hours_skips_invalid_pieces() {
+        assert_eq!(parse_active_hours("22-6, 12-13"), vec![(22, 6), (12, 13)]);
+        assert_eq!(
+            parse_active_hours("25-3,5-5,a-b,7,8-25, 0-24 "),
+            vec![(0, 24)]
+        );
+        assert!(parse_active_hours("").is_empty());
+    }
+
+    #[test]
+    fn active_hours_wrap_past_midnight() {
+        let b = budget(None, None, "22-6");
+        for (h, expected) in [
+            (21, false),
+            (22, true),
+            (23, true),
+            (0, true),
+            (5, true),
+            (6, false),
+        ] {
+            assert_eq!(b.in_active_hours(at_hour(h)), expected, "hour {}", h);
+        }
+        let b = budget(None, None, "0-8,20-24");
+        assert!(b.in_active_hours(at_hour(23)));
+        assert!(!b.in_active_hours(at_hour(12)));
+        assert!(budget(None, None, "").in_active_hours(at_hour(12)));
+    }
+
+    #[tokio::test]
+    async fn check_blocks_at_cap_and_after_refund_resumes() {
+        let db = crate::storage::establish_connection("sqlite::memory:")
+            .await
+            .unwrap();
+        let b = budget(Some(5), Some(2), "8-18");
+        let now = at_hour(10);
+        let keys = [
+            SimulationBudget::day_key(now),
+            SimulationBudget::hour_key(now),
+        ];
+        let buckets = vec![
+            (keys[0].clone(), b.daily_cap),
+            (keys[1].clone(), b.hourly_cap),
+        ];
+
+        assert_eq!(b.check_at(&db, now).await, Ok(()));
+        assert_eq!(
+            b.check_at(&db, at_hour(20)).await,
+            Err(BudgetBlock::OutsideActiveHours)
+        );
+        for _ in 0..2 {
+            assert!(BudgetRepository::try_consume(&db, &b.account, &buckets)
+                .await
+                .unwrap());
+        }
+        assert!(!BudgetRepository::try_consume(&db, &b.account, &buckets)
+            .await
+            .unwrap());
+        assert_eq!(
+            BudgetRepository::get_count(&db, &b.account, &keys[0])
+                .await
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            b.check_at(&db, now).await,
+            Err(BudgetBlock::HourlyExhausted)
+        );
+
+        BudgetRepository::refund(&db, &b.account, &keys)
+            .await
+            .unwrap();
+        assert_eq!(b.check_at(&db, now).await, Ok(()));
+        assert_eq!(
+            BudgetRepository::get_count(&db, &b.account, &keys[1])
+                .await
+                .unwrap(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn check_reports_unavailable_when_count_unreadable() {
+        let db = crate::storage::establish_connection("sqlite::memory:")
+            .await
+            .unwrap();
+        db.execute_unprepared("DROP TABLE simulation_budget")
+            .await
+            .unwrap();
+        let b = budget(None, Some(2), "");
+        assert_eq!(
+            b.check_at(&db, at_hour(10)).await,
+            Err(BudgetBlock::Unavailable)
+        );
+    }
+}
@@ -0,0 +1,207 @@
+use sea_orm::FromQueryResult;
+use std::collections::BTreeMap;
+
+/// 吞吐统计窗口（小时）
+pub const THROUGHPUT_WINDOW_HOURS: i64 = 3;
+/// 迷你趋势图覆盖的小时数
+pub const SPARKLINE_HOURS: usize = 24;
+/// 成功率统计的最近尝试次数
+pub const RECENT_ATTEMPTS: usize = 50;
+
+#[derive(Debug, Clone, Default)]
+pub struct WorkerThroughput {
+    pub worker_id: String,
+    pub done: usize,
+    pub failed: usize,
+    pub per_hour: f64,
+    pub median_secs: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ThroughputStats {
+    pub per_hour: f64,
+    pub median_secs: Option<i64>,
+    pub p90_secs: Option<i64>,
+    pub eta_secs: Option<i64>,
+    pub recent_success: usize,
+    pub recent_failure: usize,
+    /// 最近 SPARKLINE_HOURS 小时每小时完成数，最旧在前
+    pub hourly_done: Vec<u64>,
+    pub workers: Vec<WorkerThroughput>,
+}
+
+impl ThroughputStats {
+    pub fn recent_success_rate(&self) -> Option<f64> {
+        let n = self.recent_success + self.recent_failure;
+        if n == 0 {
+            None
+        } else {
+            Some(self.recent_success as f64 / n as f64)
+        }
+    }
+}
+
+/// 一次执行尝试：CLAIMED 开始，到 DONE / RETRY_WAIT / FAILED_PERMANENT 结束；
+/// 由 `BacktestRepository::list_attempts_since` 从状态迁移审计记录配对得到
+#[derive(Debug, Clone, FromQueryResult)]
+pub struct Attempt {
+    pub worker_id: String,
+    pub started_at: i64,
+    pub finished_at: i64,
+    pub success: bool,
+}
+
+/// 由执行尝试计算吞吐指标；`remaining` 为队列中尚未完成的任务数。
+pub fn compute_throughput(attempts: &[Attempt], now: i64, remaining: usize) -> ThroughputStats {
+    let window_start = now - THROUGHPUT_WINDOW_HOURS * 3600;
+    let in_window: Vec<&Attempt> = attempts
+        .iter()
+        .filter(|a| a.finished_at >= window_start)
+        .collect();
+    // 窗口内最早一次尝试之后才开始计时，避免刚启动时吞吐被低估
+    let elapsed_hours = in_window
+        .iter()
+        .map(|a| a.started_at)
+        .min()
+        .map(|first| ((now - first.max(window_start)) as f64 / 3600.0).max(1.0 / 60.0))
+        .unwrap_or(THROUGHPUT_WINDOW_HOURS as f64);
+
+    let done_in_window = in_window.iter().filter(|a| a.success).count();
+    let per_hour = done_in_window as f64 / elapsed_hours;
+
+    let mut durations: Vec<i64> = in_window
+        .iter()
+        .filter(|a| a.success)
+        .map(|a| (a.finished_at - a.started_at).max(0))
+        .collect();
+    durations.sort_unstable();
+
+    let mut by_worker: BTreeMap<String, (usize, usize, Vec<i64>)> = BTreeMap::new();
+    for a in &in_window {
+        let e = by_worker.entry(a.worker_id.clone()).or_default();
+        if a.success {
+            e.0 += 1;
+            e.2.push((a.finished_at - a.started_at).max(0));
+        } else {
+            e.1 += 1;
+        }
+    }
+    let workers = by_worker
+        .into_iter()
+        .map(|(worker_id, (done, failed, mut ds))| {
+            ds.sort_unstable();
+            WorkerThroughput {
+                worker_id,
+                done,
+                failed,
+                per_hour: done as f64 / elapsed_hours,
+                median_secs: percentile(&ds, 0.5),
+            }
+        })
+        .collect();
+
+    let mut recent: Vec<&Attempt> = attempts.iter().collect();
+    recent.sort_by_key(|a| std::cmp::Reverse(a.finished_at));
+    recent.truncate(RECENT_ATTEMPTS);
+    let recent_success = recent.iter().filter(|a| a.success).count();
+
+    let mut hourly_done = vec![0u64; SPARKLINE_HOURS];
+    for a in attempts.iter().filter(|a| a.success) {
+        let age_hours = (now - a.finished_at) / 3600;
+        if age_hours >= 0 && (age_hours as usize) < SPARKLINE_HOURS {
+            hourly_done[SPARKLINE_HOURS - 1 - age_hours as usize] += 1;
+        }
+    }
+
+    let eta_secs = if per_hour > 0.0 {
+        Some((remaining as f64 / per_hour * 3600.0).round() as i64)
+    } else {
+        None
+    };
+
+    ThroughputStats {
+        per_hour,
+        median_secs: percentile(&durations, 0.5),
+        p90_secs: percentile(&durations, 0.9),
+        eta_secs,
+        recent_success,
+        recent_failure: recent.len() - recent_success,
+        hourly_done,
+        workers,
+    }
+}
+
+fn percentile(sorted: &[i64], q: f64) -> Option<i64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let idx = ((sorted.len() - 1) as f64 * q).round() as usize;
+    sorted.get(idx).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 50_000;
+
+    fn attempt(worker: &str, started_at: i64, finished_at: i64, success: bool) -> Attempt {
+        Attempt {
+            worker_id: worker.to_string(),
+            started_at,
+            finished_at,
+            success,
+        }
+    }
+
+    #[test]
+    fn empty_queue_has_no_rates() {
+        let t = compute_throughput(&[], NOW, 10);
+        assert_eq!(t.per_hour, 0.0);
+        assert_eq!(t.median_secs, None);
+        assert_eq!(t.eta_secs, None);
+        assert_eq!(t.recent_success_rate(), None);
+        assert_eq!(t.hourly_done, vec![0; SPARKLINE_HOURS]);
+        assert!(t.workers.is_empty());
+    }
+
+    #[test]
+    fn rates_percentiles_and_workers() {
+        let attempts = [
+            attempt("w1", 43_200, 43_260, true),
+            attempt("w1", 45_000, 45_120, true),
+            attempt("w2", 46_800, 46_980, true),
+            attempt("w2", 47_000, 47_030, false),
+            // 窗口外，只计入趋势图与最近成功率
+            attempt("w1", 30_000, 30_100, true),
+        ];
+        let t = compute_throughput(&attempts, NOW, 6);
+        // 窗口内最早开始于 43_200，经过 6800 秒完成 3 个
+        assert!((t.per_hour - 3.0 * 3600.0 / 6800.0).abs() < 1e-9);
+        assert_eq!(t.median_secs, Some(120));
+        assert_eq!(t.p90_secs, Some(180));
+        assert_eq!(t.eta_secs, Some(13_600));
+        assert_eq!((t.recent_success, t.recent_failure), (4, 1));
+
+        assert_eq!(t.hourly_done.iter().sum::<u64>(), 4);
+        assert_eq!(t.hourly_done[SPARKLINE_HOURS - 1], 1);
+        assert_eq!(t.hourly_done[SPARKLINE_HOURS - 2], 2);
+        assert_eq!(t.hourly_done[SPARKLINE_HOURS - 6], 1);
+
+        assert_eq!(t.workers.len(), 2);
+        let w1 = &t.workers[0];
+        assert_eq!((w1.worker_id.as_str(), w1.done, w1.failed), ("w1", 2, 0));
+        let w2 = &t.workers[1];
+        assert_eq!((w2.done, w2.failed, w2.median_secs), (1, 1, Some(180)));
+    }
+
+    #[test]
+    fn attempt_claimed_before_window_keeps_its_duration() {
+        let window_start = NOW - THROUGHPUT_WINDOW_HOURS * 3600;
+        let t = compute_throughput(&[attempt("w1", 20_000, window_start + 100, true)], NOW, 0);
+        assert_eq!(t.median_secs, Some(window_start + 100 - 20_000));
+        // 计时从窗口开始算起
+        assert!((t.per_hour - 1.0 / THROUGHPUT_WINDOW_HOURS as f64).abs() < 1e-9);
+        assert_eq!(t.eta_secs, Some(0));
+    }
+}
@@ -1,3 +1,5 @@
+pub mod budget;
+pub mod metrics;
 pub mod model;
 pub mod service;
 pub mod worker;
@@ -1,4 +1,6 @@
-use crate::storage::repository::CoreMetrics;
+use crate::backtest::budget::BudgetStatus;
+use crate::backtest::metrics::ThroughputStats;
+use crate::storage::repository::{ActiveJobRow, CoreMetrics};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -55,9 +57,122 @@ pub struct BacktestResult {
 pub struct BacktestStats {
     pub total: usize,
     pub pending: usize,
-    pub running: usize,
+    pub running: usize, // 执行中合计 = claimed + submitting + simulating + fetching
+    pub claimed: usize,
+    pub submitting: usize,
+    pub simulating: usize,
+    pub fetching: usize,
     pub completed: usize,
     pub error_retryable: usize,
     pub error_fatal: usize,
     pub error_exceeded: usize, // 新增：超过重试次数的任务
+    pub active_jobs: Vec<ActiveJobRow>,
+    pub throughput: ThroughputStats,
+    pub budget: BudgetStatus,
+}
+
+/// 回测任务状态机：持久化到 backtest_jobs.status 的唯一合法取值
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum JobStatus {
+    Queued,
+    Claimed,
+    Submitting,
+    Running,
+    Fetching,
+    Done,
+    RetryWait,
+    FailedPermanent,
+}
+
+impl JobStatus {
+    pub const ALL: [JobStatus; 8] = [
+        JobStatus::Queued,
+        JobStatus::Claimed,
+        JobStatus::Submitting,
+        JobStatus::Running,
+        JobStatus::Fetching,
+        JobStatus::Done,
+        JobStatus::RetryWait,
+        JobStatus::FailedPermanent,
+    ];
+
+    /// 已被 worker 持有、尚未结束的阶段
+    pub const IN_FLIGHT: [JobStatus; 4] = [
+        JobStatus::Claimed,
+        JobStatus::Submitting,
+        JobStatus::Running,
+        JobStatus::Fetching,
+    ];
+
+    /// 可被 claim 的状态
+    pub const CLAIMABLE: [JobStatus; 2] = [JobStatus::Queued, JobStatus::RetryWait];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Queued => "QUEUED",
+            JobStatus::Claimed => "CLAIMED",
+            JobStatus::Submitting => "SUBMITTING",
+            JobStatus::Running => "RUNNING",
+            JobStatus::Fetching => "FETCHING",
+            JobStatus::Done => "DONE",
+            JobStatus::RetryWait => "RETRY_WAIT",
+            JobStatus::FailedPermanent => "FAILED_PERMANENT",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|st| st.as_str() == s)
+    }
+
+    pub fn is_in_flight(&self) -> bool {
+        Self::IN_FLIGHT.contains(self)
+    }
+
+    /// 尚未结束（排队中或执行中）的任务，用于入队去重
+    pub fn is_active(&self) -> bool {
+        self.is_in_flight() || Self::CLAIMABLE.contains(self)
+    }
+
+    /// 状态迁移表
+    /// - QUEUED/RETRY_WAIT -> CLAIMED
+    /// - CLAIMED -> SUBMITTING -> RUNNING -> FETCHING -> DONE
+    /// - 任一执行中阶段 -> RETRY_WAIT / FAILED_PERMANENT（出错）或 QUEUED（启动恢复）
+    /// - RETRY_WAIT / FAILED_PERMANENT -> QUEUED（人工重排）
+    pub fn can_transition_to(&self, to: JobStatus) -> bool {
+        use JobStatus::*;
+        match (self, to) {
+            (Queued, Claimed) | (RetryWait, Claimed) => true,
+            (Claimed, Submitting) | (Submitting, Running) | (Running, Fetching) => true,
+            (Fetching, Done) => true,
+            (from, RetryWait) | (from, FailedPermanent) | (from, Queued) if from.is_in_flight() => {
+                true
+            }
+            (RetryWait, Queued) | (FailedPermanent, Queued) => true,
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for JobStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// backtest_jobs.last_error_kind 的取值
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobErrorKind {
+    Retryable,
+    Permanent,
+    RetryExceeded,
+}
+
+impl JobErrorKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobErrorKind::Retryable => "RETRYABLE",
+            JobErrorKind::Permanent => "PERMANENT",
+            JobErrorKind::RetryExceeded => "RETRY_EXCEEDED",
+        }
+    }
 }
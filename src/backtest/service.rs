@@ -1,8 +1,10 @@
-use crate::backtest::model::{BacktestError, BacktestResult};
+use crate::backtest::budget::SimulationBudget;
+use crate::backtest::model::{BacktestError, BacktestResult, JobErrorKind, JobStatus};
 use crate::backtest::worker::BacktestWorker;
 use crate::session::WQBSession;
 use crate::storage::repository::{
-    AlphaRepository, BacktestRepository, DataFieldRepository, OperatorCompatRepository,
+    AlphaDefinition, AlphaRepository, BacktestRepository, DataFieldRepository,
+    OperatorCompatRepository, Provenance, ProvenanceRepository,
 };
 use crate::AppEvent;
 use crate::app_service::refresh_ui;
@@ -17,6 +19,7 @@ pub struct BacktestService {
     session: Arc<WQBSession>,
     evt_tx: mpsc::UnboundedSender<AppEvent>,
     worker_count: usize,
+    budget: SimulationBudget,
 }
 
 impl BacktestService {
@@ -35,18 +38,40 @@ impl BacktestService {
             session,
             evt_tx,
             worker_count: wc,
+            budget: SimulationBudget::from_env(),
         }
     }
 
-    pub async fn add_job(&self, expression: &str) -> Result<Option<i32>, String> {
-        BacktestRepository::create_job(
+    /// 手动提交回测：先在 alphas 主表中占位并记录来源，再入队（已有活跃任务时返回 None）
+    pub async fn add_job(
+        &self,
+        expression: &str,
+        region: String,
+        universe: String,
+        delay: i32,
+    ) -> Result<Option<i32>, String> {
+        let expression = crate::generate::parser::sanitize_expression(expression);
+        let def = AlphaDefinition::with_defaults(
+            expression.clone(),
+            region.clone(),
+            universe.clone(),
+            delay,
+        );
+        AlphaRepository::insert_or_ignore_alpha(&self.db, def)
+            .await
+            .map_err(|e| format!("无法创建 Alpha 记录: {}", e))?;
+        if let Err(e) = ProvenanceRepository::record(
             &self.db,
-            expression.to_string(),
-            "CHN".to_string(),
-            "TOP2000U".to_string(),
+            &Provenance::source("backtest").with_scope(&region, &universe),
+            std::slice::from_ref(&expression),
         )
         .await
-        .map_err(|e| e.to_string())
+        {
+            warn!("记录手动回测来源失败: {}", e);
+        }
+        BacktestRepository::create_job(&self.db, expression, region, universe, delay)
+            .await
+            .map_err(|e| e.to_string())
     }
 
     /// 启动常驻 workers（并发=worker_count），只要没满就会立刻填上
@@ -56,9 +81,32 @@ impl BacktestService {
             let db = self.db.clone();
             let session = self.session.clone();
             let evt_tx = self.evt_tx.clone();
+            let budget = self.budget.clone();
 
             tokio::spawn(async move {
+                let mut last_block = None;
+                // 连续扣减额度出错的次数，用于退避
+                let mut budget_errors: u32 = 0;
                 loop {
+                    // 0) 预算与运行时段检查：不满足时空闲等待
+                    if let Err(block) = budget.check(&db).await {
+                        if last_block != Some(block) {
+                            info!("⏸ [{}] 暂停认领: {}", worker_id, block.describe());
+                            if idx == 0 {
+                                let _ = evt_tx.send(AppEvent::Log(format!(
+                                    "⏸ 回测暂停: {}",
+                                    block.describe()
+                                )));
+                            }
+                            last_block = Some(block);
+                        }
+                        sleep(Duration::from_secs(30)).await;
+                        continue;
+                    }
+                    if last_block.take().is_some() && idx == 0 {
+                        let _ = evt_tx.send(AppEvent::Log("▶ 回测恢复运行".to_string()));
+                    }
+
                     // 1) 原子 claim 下一条可执行任务（QUEUED/RETRY_WAIT 且 next_run_at<=now）
                     let now = chrono::Utc::now().timestamp();
                     let job = match BacktestRepository::claim_next(&db, &worker_id, now).await {
@@ -76,29 +124,95 @@ impl BacktestService {
                         continue;
                     };
 
+                    // 认领成功后原子扣减额度；并发下额度被抢光则放回队列
+                    let consumed = budget.consume(&db).await;
+                    if let Err(e) = &consumed {
+                        warn!("[{}] 扣减模拟额度失败: {}", worker_id, e);
+                        let _ = evt_tx.send(AppEvent::Log(format!(
+                            "⚠ [{}] 扣减模拟额度失败: {}",
+                            worker_id, e
+                        )));
+                    }
+                    if !matches!(consumed, Ok(true)) {
+                        let note = if consumed.is_err() {
+                            "扣减额度失败，放回队列"
+                        } else {
+                            "预算不足，放回队列"
+                        };
+                        if let Err(e) =
+                            BacktestRepository::release_claim(&db, job.id, &worker_id, note).await
+                        {
+                            warn!("[{}] 任务 [{}] 放回队列失败: {}", worker_id, job.id, e);
+                        }
+                        // 数据库出错时指数退避（1s 起，最长 60s），避免反复认领同一任务
+                        let secs = if consumed.is_err() {
+                            budget_errors = budget_errors.saturating_add(1);
+                            (1u64 << budget_errors.min(6)).min(60)
+                        } else {
+                            1
+                        };
+                        sleep(Duration::from_secs(secs)).await;
+                        continue;
+                    }
+                    budget_errors = 0;
+
                     let job_id = job.id;
                     let expression = job.expression.clone();
-                    let region = job.region.clone();
-                    let universe = job.universe.clone();
                     info!(
-                        "🚀 [{}] 开始回测任务 [{}]: {} (region: {}, universe: {})",
-                        worker_id, job_id, expression, region, universe
+                        "🚀 [{}] 开始回测任务 [{}]: {} (region: {}, universe: {}, delay: {})",
+                        worker_id, job_id, expression, job.region, job.universe, job.delay
                     );
 
                     // 2) 标记 SUBMITTING
-                    let _ = BacktestRepository::mark_status(&db, job_id, "SUBMITTING", None).await;
+                    if let Err(e) = BacktestRepository::mark_status(
+                        &db,
+                        job_id,
+                        JobStatus::Submitting,
+                        None,
+                        Some(&worker_id),
+                    )
+                    .await
+                    {
+                        warn!(
+                            "[{}] 任务 [{}] 无法进入 SUBMITTING: {}",
+                            worker_id, job_id, e
+                        );
+                        // 与扣减失败时一样放回队列，并退还已扣减的额度
+                        if let Err(e) = BacktestRepository::release_claim(
+                            &db,
+                            job_id,
+                            &worker_id,
+                            "无法进入 SUBMITTING，放回队列",
+                        )
+                        .await
+                        {
+                            warn!("[{}] 任务 [{}] 放回队列失败: {}", worker_id, job_id, e);
+                        }
+                        if let Err(e) = budget.refund(&db).await {
+                            warn!("[{}] 退还模拟额度失败: {}", worker_id, e);
+                        }
+                        sleep(Duration::from_secs(1)).await;
+                        continue;
+                    }
                     // 同步 Alpha 状态为 SIMULATING（便于 Alpha 列表显示）
                     let _ = AlphaRepository::mark_simulating(&db, &expression, &worker_id).await;
 
-                    // 3) 运行 worker（submit->poll->fetch）
-                    let result =
-                        BacktestWorker::run(&expression, session.clone(), &region, &universe).await;
+                    // 3) 运行 worker（submit->poll->fetch，内部推进 RUNNING/FETCHING）
+                    let result = BacktestWorker::run(&db, &job, &worker_id, session.clone()).await;
                     match result {
                         Ok(res) => {
-                            Self::handle_success(&db, job_id, &expression, res, &evt_tx).await;
+                            Self::handle_success(
+                                &db,
+                                job_id,
+                                &worker_id,
+                                &expression,
+                                res,
+                                &evt_tx,
+                            )
+                            .await;
                         }
                         Err(err) => {
-                            Self::handle_error(&db, job_id, err, &evt_tx).await;
+                            Self::handle_error(&db, job_id, &worker_id, err, &evt_tx).await;
                         }
                     }
                 }
@@ -106,10 +220,11 @@ impl BacktestService {
         }
     }
 
-    /// 处理成功结果：RUNNING -> DONE
+    /// 处理成功结果：FETCHING -> DONE
     async fn handle_success(
         db: &Arc<DatabaseConnection>,
         job_id: i32,
+        worker_id: &str,
         expression: &str,
         result: BacktestResult,
         evt_tx: &mpsc::UnboundedSender<AppEvent>,
@@ -117,15 +232,19 @@ impl BacktestService {
         info!("✓ 任务执行成功 [{}]: {:?}", job_id, result.alpha_id);
 
         // 1. 更新回测任务状态 + 结果
-        let _ = BacktestRepository::mark_done(
+        if let Err(e) = BacktestRepository::mark_done(
             db,
             job_id,
             result.simulation_id.clone(),
             result.alpha_id.clone(),
             result.metrics_json.clone(),
             result.checks_json.clone(),
+            Some(worker_id),
         )
-        .await;
+        .await
+        {
+            warn!("任务 [{}] 无法标记 DONE: {}", job_id, e);
+        }
 
         // 2. 同步到 Alpha 表 (持久化回测结果)
         // 只有获取到了具体的 alpha_id 且有指标时才同步
@@ -149,6 +268,7 @@ impl BacktestService {
     async fn handle_error(
         db: &Arc<DatabaseConnection>,
         job_id: i32,
+        worker_id: &str,
         err: BacktestError,
         evt_tx: &mpsc::UnboundedSender<AppEvent>,
     ) {
@@ -179,15 +299,19 @@ impl BacktestService {
             delay = delay + (delay / 5) * (rand::random::<u8>() as u64 % 5) / 5;
             let next_run_at = chrono::Utc::now().timestamp() + delay as i64;
 
-            let _ = BacktestRepository::mark_failed_retryable(
+            if let Err(e) = BacktestRepository::mark_failed_retryable(
                 db,
                 job_id,
-                "RETRYABLE",
+                JobErrorKind::Retryable,
                 None,
                 Some(err.message.clone()),
                 next_run_at,
+                Some(worker_id),
             )
-            .await;
+            .await
+            {
+                warn!("任务 [{}] 无法进入 RETRY_WAIT: {}", job_id, e);
+            }
             let _ = evt_tx.send(AppEvent::Log(format!(
                 "⚠ 任务重试 [{}/{}]: {}",
                 job.retry_count + 1,
@@ -196,18 +320,22 @@ impl BacktestService {
             )));
         } else {
             let kind = if !err.retryable {
-                "PERMANENT"
+                JobErrorKind::Permanent
             } else {
-                "RETRY_EXCEEDED"
+                JobErrorKind::RetryExceeded
             };
-            let _ = BacktestRepository::mark_failed_permanent(
+            if let Err(e) = BacktestRepository::mark_failed_permanent(
                 db,
                 job_id,
                 kind,
                 None,
                 Some(err.message.clone()),
+                Some(worker_id),
             )
-            .await;
+            .await
+            {
+                warn!("任务 [{}] 无法进入 FAILED_PERMANENT: {}", job_id, e);
+            }
 
             let _ = AlphaRepository::mark_error(db.as_ref(), &job.expression, &err.message).await;
             if let Ok(fields) =
@@ -1,8 +1,10 @@
-use crate::backtest::model::{BacktestError, BacktestResult};
+use crate::backtest::model::{BacktestError, BacktestResult, JobStatus};
 use crate::session::dto::{AlphaDetailResponse, SimulationResponse};
 use crate::session::WQBSession;
-use crate::storage::repository::CoreMetrics;
-use log::info;
+use crate::storage::entity::backtest_job;
+use crate::storage::repository::{BacktestRepository, CoreMetrics, PeriodMetrics};
+use log::{info, warn};
+use sea_orm::DatabaseConnection;
 use serde_json::Value;
 use std::sync::Arc;
 use tokio::time::{sleep, Duration};
@@ -10,15 +12,17 @@ use tokio::time::{sleep, Duration};
 pub struct BacktestWorker;
 
 impl BacktestWorker {
-    /// 执行器：接收表达式，返回结果或分型后的错误
+    /// 执行器：按任务的表达式与回测范围提交模拟，返回结果或分型后的错误
+    /// 执行过程中推进任务阶段：提交成功后 RUNNING，抓取详情前 FETCHING（以 worker_id 记入审计）
     pub async fn run(
-        expression: &str,
+        db: &DatabaseConnection,
+        job: &backtest_job::Model,
+        worker_id: &str,
         session: Arc<WQBSession>,
-        region: &str,
-        universe: &str,
     ) -> Result<BacktestResult, BacktestError> {
+        let job_id = job.id;
         // 1. 提交模拟请求
-        let sim_data = Self::build_sim_data(expression, region, universe);
+        let sim_data = Self::build_sim_data(&job.expression, &job.region, &job.universe, job.delay);
         let resp = session
             .post("https://api.worldquantbrain.com/simulations", |b| {
                 b.json(&sim_data)
@@ -73,6 +77,14 @@ impl BacktestWorker {
         };
 
         info!("▶ 模拟任务已提交: {}", sim_id);
+        Self::advance(
+            db,
+            job_id,
+            worker_id,
+            JobStatus::Running,
+            Some(sim_id.clone()),
+        )
+        .await;
 
         // 2. 轮询结果 (Polling)
         let mut poll_count = 0;
@@ -164,6 +176,7 @@ impl BacktestWorker {
         };
 
         // 3. 抓取 Alpha 详情
+        Self::advance(db, job_id, worker_id, JobStatus::Fetching, None).await;
         let detail_url = format!("https://api.worldquantbrain.com/alphas/{}", final_alpha_id);
         let detail_resp = session
             .get(&detail_url, |r| r)
@@ -194,6 +207,7 @@ impl BacktestWorker {
                 is_returns: is_data.get("returns").and_then(|v| v.as_f64()),
                 is_drawdown: is_data.get("drawdown").and_then(|v| v.as_f64()),
                 is_pnl: is_data.get("pnl").and_then(|v| v.as_f64()),
+                ..Default::default()
             });
 
             // 提取 checks
@@ -202,6 +216,26 @@ impl BacktestWorker {
             }
         }
 
+        // 5. OS / TRAIN / TEST 阶段（平台返回时才有），各自保存到 metrics_json 对应键
+        for (key, data) in [
+            ("OS", detail_info.os),
+            ("TRAIN", detail_info.train),
+            ("TEST", detail_info.test),
+        ] {
+            let Some(data) = data.filter(|v| v.is_object()) else {
+                continue;
+            };
+            let period = PeriodMetrics::from_json(&data);
+            let core = core_metrics.get_or_insert_with(CoreMetrics::default);
+            match key {
+                "OS" => core.os = period,
+                "TRAIN" => core.train = period,
+                _ => core.test = period,
+            }
+            let mj = metrics_json.get_or_insert_with(|| serde_json::json!({}));
+            mj[key] = data;
+        }
+
         Ok(BacktestResult {
             alpha_id: Some(final_alpha_id),
             simulation_id: Some(sim_id),
@@ -211,14 +245,35 @@ impl BacktestWorker {
         })
     }
 
-    fn build_sim_data(expression: &str, region: &str, universe: &str) -> serde_json::Value {
+    /// 阶段推进失败只记录日志，不影响本次回测结果
+    async fn advance(
+        db: &DatabaseConnection,
+        job_id: i32,
+        worker_id: &str,
+        status: JobStatus,
+        simulation_id: Option<String>,
+    ) {
+        if let Err(e) =
+            BacktestRepository::mark_status(db, job_id, status, simulation_id, Some(worker_id))
+                .await
+        {
+            warn!("任务 [{}] 无法进入 {}: {}", job_id, status, e);
+        }
+    }
+
+    fn build_sim_data(
+        expression: &str,
+        region: &str,
+        universe: &str,
+        delay: i32,
+    ) -> serde_json::Value {
         serde_json::json!({
             "type": "REGULAR",
             "settings": {
                 "instrumentType": "EQUITY",
                 "region": region,
                 "universe": universe,
-                "delay": 1,
+                "delay": delay,
                 "decay": 10,
                 "neutralization": "INDUSTRY",
                 "truncation": 0.08,
@@ -7,6 +7,9 @@ pub enum AppCommand {
     },
     Backtest {
         expr: String,
+        region: Option<String>,
+        universe: Option<String>,
+        delay: Option<i32>,
     },
     BacktestsClear,
     BacktestsSanitize {
@@ -33,13 +36,45 @@ pub enum AppCommand {
         auto_backtest: bool,
     },
     GenerateStop,
+    EvolveStart {
+        batch: usize,
+        interval_sec: u64,
+        region: Option<String>,
+        universe: Option<String>,
+        delay: Option<i32>,
+    },
+    EvolveOnce {
+        batch: usize,
+        region: Option<String>,
+        universe: Option<String>,
+        delay: Option<i32>,
+    },
+    EvolveStop,
+    Template {
+        template: String,
+        cap: usize,
+        region: Option<String>,
+        universe: Option<String>,
+        delay: Option<i32>,
+    },
     GetDetail {
         expr: String,
     },
+    Similar {
+        expr: String,
+    },
+    Eval {
+        expr: String,
+    },
     Help,
     Quit,
     FieldsSync,
     FieldStats,
+    Cluster,
+    Families,
+    Usage {
+        days: i64,
+    },
     FieldSample {
         region: Option<String>,
         universe: Option<String>,
@@ -109,29 +144,27 @@ impl FromStr for AppCommand {
                     let limit = parts.get(2).and_then(|s| s.parse::<usize>().ok()).unwrap_or(5000);
                     Ok(AppCommand::BacktestsSanitize { limit })
                 } else {
-                    let expr = parts[1..].join(" ");
+                    let (region, universe, delay, i) = parse_scope(&parts, 1);
+                    let expr = parts[i.min(parts.len())..].join(" ");
                     if !expr.is_empty() {
-                        Ok(AppCommand::Backtest { expr })
+                        Ok(AppCommand::Backtest {
+                            expr,
+                            region,
+                            universe,
+                            delay,
+                        })
                     } else {
-                        Ok(AppCommand::Unknown("用法: backtest <expr> | backtest clear | backtest sanitize [limit]".to_string()))
+                        Ok(AppCommand::Unknown("用法: backtest [region] [universe] [delay] <expr> | backtest clear | backtest sanitize [limit]".to_string()))
                     }
                 }
             }
             "generate" => {
-                match parts.get(1).map(|s| *s) {
+                match parts.get(1).copied() {
                     Some("stop") => Ok(AppCommand::GenerateStop),
                     Some("turbo") => {
                         let n = parts.get(2).and_then(|s| s.parse().ok()).unwrap_or(1);
-                        let provider = std::env::var("LLM_PROVIDER")
-                            .unwrap_or_else(|_| "openrouter".to_string())
-                            .to_lowercase();
                         let mut idx = 3usize;
-                        let mut model = match provider.as_str() {
-                            "cerebras" => "llama-3.3-70b".to_string(),
-                            "xirang" => std::env::var("XIRANG_MODEL_ID")
-                                .unwrap_or_else(|_| "ff3f5c450f3b459cbe5d04a5ea9b2511".to_string()),
-                            _ => "deepseek/deepseek-r1".to_string(),
-                        };
+                        let mut model = default_model();
                         if let Some(tok) = parts.get(idx) {
                             let t = tok.to_string();
                             if !is_region_code(&t) {
@@ -168,16 +201,8 @@ impl FromStr for AppCommand {
                             .get(3)
                             .and_then(|s| parse_interval_seconds(s))
                             .unwrap_or(5);
-                        let provider = std::env::var("LLM_PROVIDER")
-                            .unwrap_or_else(|_| "openrouter".to_string())
-                            .to_lowercase();
                         let mut idx = 4usize;
-                        let mut model = match provider.as_str() {
-                            "cerebras" => "llama-3.3-70b".to_string(),
-                            "xirang" => std::env::var("XIRANG_MODEL_ID")
-                                .unwrap_or_else(|_| "ff3f5c450f3b459cbe5d04a5ea9b2511".to_string()),
-                            _ => "deepseek/deepseek-r1".to_string(),
-                        };
+                        let mut model = default_model();
                         if let Some(tok) = parts.get(idx) {
                             let t = tok.to_string();
                             if !is_region_code(&t) {
@@ -210,16 +235,8 @@ impl FromStr for AppCommand {
                     }
                     Some("once") => {
                         let n = parts.get(2).and_then(|s| s.parse().ok()).unwrap_or(1);
-                        let provider = std::env::var("LLM_PROVIDER")
-                            .unwrap_or_else(|_| "openrouter".to_string())
-                            .to_lowercase();
                         let mut idx = 3usize;
-                        let mut model = match provider.as_str() {
-                            "cerebras" => "llama-3.3-70b".to_string(),
-                            "xirang" => std::env::var("XIRANG_MODEL_ID")
-                                .unwrap_or_else(|_| "ff3f5c450f3b459cbe5d04a5ea9b2511".to_string()),
-                            _ => "deepseek/deepseek-r1".to_string(),
-                        };
+                        let mut model = default_model();
                         if let Some(tok) = parts.get(idx) {
                             let t = tok.to_string();
                             if !is_region_code(&t) {
@@ -253,6 +270,53 @@ impl FromStr for AppCommand {
                     None => Ok(AppCommand::Unknown("用法: generate loop <n> <sec> [model] [region] [universe] [delay] [sample_size] [auto_backtest] | generate once <n> [model] [region] [universe] [delay] [sample_size] [auto_backtest] | generate stop".to_string())),
                 }
             }
+            "evolve" => match parts.get(1).copied() {
+                Some("stop") => Ok(AppCommand::EvolveStop),
+                Some("once") => {
+                    let batch = parts.get(2).and_then(|s| s.parse().ok()).unwrap_or(10);
+                    Ok(AppCommand::EvolveOnce {
+                        batch,
+                        region: parts.get(3).map(|s| s.to_string()),
+                        universe: parts.get(4).map(|s| s.to_string()),
+                        delay: parts.get(5).and_then(|s| s.parse::<i32>().ok()),
+                    })
+                }
+                Some("loop") => {
+                    let batch = parts.get(2).and_then(|s| s.parse().ok()).unwrap_or(10);
+                    let interval_sec = parts
+                        .get(3)
+                        .and_then(|s| parse_interval_seconds(s))
+                        .unwrap_or(60);
+                    Ok(AppCommand::EvolveStart {
+                        batch,
+                        interval_sec,
+                        region: parts.get(4).map(|s| s.to_string()),
+                        universe: parts.get(5).map(|s| s.to_string()),
+                        delay: parts.get(6).and_then(|s| s.parse::<i32>().ok()),
+                    })
+                }
+                _ => Ok(AppCommand::Unknown(
+                    "用法: evolve once <n> [region] [universe] [delay] | evolve loop <n> <sec> [region] [universe] [delay] | evolve stop".to_string(),
+                )),
+            },
+            "template" | "tpl" => {
+                let usage = "用法: template <n> [region] [universe] [delay] <模板>，占位符 {field[:类别/数据集]} {window:5,10,20} {group[:industry,...]}";
+                let Some(cap) = parts.get(1).and_then(|s| s.parse::<usize>().ok()) else {
+                    return Ok(AppCommand::Unknown(usage.to_string()));
+                };
+                let (region, universe, delay, i) = parse_scope(&parts, 2);
+                let template = parts[i.min(parts.len())..].join(" ");
+                if template.is_empty() {
+                    return Ok(AppCommand::Unknown(usage.to_string()));
+                }
+                Ok(AppCommand::Template {
+                    template,
+                    cap: cap.max(1),
+                    region,
+                    universe,
+                    delay,
+                })
+            }
             "errors" => {
                 if parts.get(1) == Some(&"export") {
                     let limit = parts.get(2).and_then(|s| s.parse::<usize>().ok()).unwrap_or(1000);
@@ -262,6 +326,33 @@ impl FromStr for AppCommand {
                     Ok(AppCommand::Unknown("用法: errors export [limit] [path]".to_string()))
                 }
             }
+            "eval" => {
+                let expr = parts[1..].join(" ");
+                if expr.is_empty() {
+                    Ok(AppCommand::Unknown(
+                        "用法: eval <expr>（在 PANEL_DIR 的 CSV 面板上本地评估）".to_string(),
+                    ))
+                } else {
+                    Ok(AppCommand::Eval { expr })
+                }
+            }
+            "cluster" => Ok(AppCommand::Cluster),
+            "families" => Ok(AppCommand::Families),
+            "usage" => match parts.get(1) {
+                None => Ok(AppCommand::Usage { days: 7 }),
+                Some(d) => match d.parse::<i64>() {
+                    Ok(days) if days > 0 => Ok(AppCommand::Usage { days }),
+                    _ => Ok(AppCommand::Unknown("用法: usage [天数，默认 7]".to_string())),
+                },
+            },
+            "similar" => {
+                let expr = parts[1..].join(" ");
+                if expr.is_empty() {
+                    Ok(AppCommand::Unknown("用法: similar <expr>".to_string()))
+                } else {
+                    Ok(AppCommand::Similar { expr })
+                }
+            }
             "__INTERNAL_GET_DETAIL__" => {
                 let expr = parts[1..].join(" ");
                 Ok(AppCommand::GetDetail { expr })
@@ -273,10 +364,50 @@ impl FromStr for AppCommand {
     }
 }
 
+/// 命令未指定模型时使用 LLM_MODEL（默认 deepseek/deepseek-r1）；LLM_CHAIN 中写了模型的项以其为准
+fn default_model() -> String {
+    std::env::var("LLM_MODEL")
+        .ok()
+        .map(|m| m.trim().to_string())
+        .filter(|m| !m.is_empty())
+        .unwrap_or_else(|| "deepseek/deepseek-r1".to_string())
+}
+
 fn is_region_code(s: &str) -> bool {
     s.len() == 3 && s.chars().all(|c| c.is_ascii_uppercase())
 }
 
+fn is_universe_code(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_uppercase())
+        && s.chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// 表达式/模板前的范围参数：区域代码 + universe 代码（大写字母、数字、下划线）+ 整数 delay，
+/// 其后至少要留一段正文；返回 (region, universe, delay, 正文起始下标)
+fn parse_scope(
+    parts: &[&str],
+    start: usize,
+) -> (Option<String>, Option<String>, Option<i32>, usize) {
+    let mut i = start;
+    let (mut region, mut universe, mut delay) = (None, None, None);
+    if parts.len() > i + 1 && is_region_code(parts[i]) {
+        region = Some(parts[i].to_string());
+        i += 1;
+        if parts.len() > i + 1 && is_universe_code(parts[i]) {
+            universe = Some(parts[i].to_string());
+            i += 1;
+            if parts.len() > i + 1 {
+                if let Ok(d) = parts[i].parse::<i32>() {
+                    delay = Some(d);
+                    i += 1;
+                }
+            }
+        }
+    }
+    (region, universe, delay, i)
+}
+
 fn parse_interval_seconds(s: &str) -> Option<u64> {
     let raw = s.trim();
     if raw.is_empty() {
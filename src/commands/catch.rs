@@ -1,5 +1,7 @@
 use crate::session::WQBSession;
-use crate::storage::repository::{AlphaDefinition, AlphaRepository, CoreMetrics};
+use crate::storage::repository::{
+    AlphaDefinition, AlphaRepository, CoreMetrics, PeriodMetrics, Provenance, ProvenanceRepository,
+};
 use crate::AppEvent;
 use log::error;
 use sea_orm::DatabaseConnection;
@@ -95,7 +97,9 @@ async fn save_to_db(
     };
 
     // 2. 插入或忽略定义
+    let provenance = Provenance::source("catch").with_scope(&def.region, &def.universe);
     AlphaRepository::insert_or_ignore_alpha(db, def).await?;
+    ProvenanceRepository::record(db, &provenance, std::slice::from_ref(&expression)).await?;
 
     // 3. 提取核心指标 (IS 阶段)
     let is = &json["is"];
@@ -106,6 +110,9 @@ async fn save_to_db(
         is_returns: is["returns"].as_f64(),
         is_drawdown: is["drawdown"].as_f64(),
         is_pnl: is["pnl"].as_f64(),
+        os: PeriodMetrics::from_json(&json["os"]),
+        train: PeriodMetrics::from_json(&json["train"]),
+        test: PeriodMetrics::from_json(&json["test"]),
     };
 
     // 4. 构建 metrics_json (按要求支持多阶段多视角)
@@ -130,13 +137,19 @@ async fn save_to_db(
         }
     }
 
-    let metrics_json = json!({
+    let mut metrics_json = json!({
         "IS": {
             "raw": raw_metrics,
             "riskNeutralized": is["riskNeutralized"],
             "investabilityConstrained": is["investabilityConstrained"],
         }
     });
+    // OS / TRAIN / TEST 阶段原样保存（平台返回时才有）
+    for (src, key) in [("os", "OS"), ("train", "TRAIN"), ("test", "TEST")] {
+        if json[src].is_object() {
+            metrics_json[key] = json[src].clone();
+        }
+    }
 
     // 5. 提取 checks_json
     let checks_json = json!(is["checks"]);
@@ -1,6 +1,6 @@
 pub mod app_command;
-pub mod backtest;
 pub mod catch;
+pub mod template;
 
 pub use app_command::AppCommand;
 
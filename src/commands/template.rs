@@ -0,0 +1,154 @@
+use crate::expr::checker::SignatureTable;
+use crate::expr::complexity::Complexity;
+use crate::generate::parser::validate_for_scope;
+use crate::generate::template::Template;
+use crate::generate::ComplexityLimits;
+use crate::storage::entity::data_field::Model as DataFieldModel;
+use crate::storage::repository::{
+    AlphaDefinition, AlphaRepository, BacktestRepository, DataFieldRepository, Provenance,
+    ProvenanceRepository,
+};
+use crate::AppEvent;
+use sea_orm::DatabaseConnection;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+/// `template` 命令参数；范围缺省为 CHN / TOP2000U / 1
+#[derive(Debug, Clone)]
+pub struct TemplateArgs {
+    pub template: String,
+    pub cap: usize,
+    pub region: Option<String>,
+    pub universe: Option<String>,
+    pub delay: Option<i32>,
+}
+
+/// 展开模板 -> 按规范化哈希去重 -> 复杂度与范围校验 -> 入库并入队，流程与 `generate once` 一致
+pub async fn run(
+    args: TemplateArgs,
+    db: &Arc<DatabaseConnection>,
+    signatures: &SignatureTable,
+    evt_tx: mpsc::UnboundedSender<AppEvent>,
+) -> Result<(), anyhow::Error> {
+    let TemplateArgs {
+        template,
+        cap,
+        region,
+        universe,
+        delay,
+    } = args;
+    let region = region.unwrap_or_else(|| "CHN".to_string());
+    let universe = universe.unwrap_or_else(|| "TOP2000U".to_string());
+    let delay = delay.unwrap_or(1);
+
+    let tpl = Template::parse(&template).map_err(|e| anyhow::anyhow!(e))?;
+    let fields =
+        DataFieldRepository::fields_in_scope(db.as_ref(), &region, &universe, delay).await?;
+    if fields.is_empty()
+        && tpl
+            .slots
+            .iter()
+            .any(|s| matches!(s, crate::generate::template::Slot::Field(_)))
+    {
+        return Err(anyhow::anyhow!(
+            "{} / {} / {} 下没有已同步的字段，请先执行 fields sync",
+            region,
+            universe,
+            delay
+        ));
+    }
+    let expanded = expand(&tpl, &fields, cap)?;
+    let total = expanded.len();
+
+    let mut seen = HashSet::new();
+    let mut accepted: Vec<String> = expanded
+        .into_iter()
+        .map(|e| crate::generate::parser::sanitize_expression(&e))
+        .filter(|e| seen.insert(crate::expr::canonical_hash(e)))
+        .collect();
+    let existing =
+        AlphaRepository::existing_canonical_hashes(db.as_ref(), seen.into_iter().collect()).await?;
+    accepted.retain(|e| !existing.contains(&crate::expr::canonical_hash(e)));
+    let deduped = accepted.len();
+
+    // 入库前按复杂度与目标范围校验，未通过的既不入库也不入队
+    let limits = ComplexityLimits::from_env();
+    let mut too_complex = 0usize;
+    let mut rejected = 0usize;
+    let mut valid = Vec::with_capacity(accepted.len());
+    for expression in accepted {
+        if let Some(Err(reason)) = Complexity::of(&expression).map(|c| limits.check(&c)) {
+            too_complex += 1;
+            log::debug!("模板展开结果复杂度超限: {} => {}", expression, reason);
+            continue;
+        }
+        if let Err(reason) = validate_for_scope(
+            db.as_ref(),
+            &expression,
+            signatures,
+            &region,
+            &universe,
+            delay,
+        )
+        .await
+        {
+            rejected += 1;
+            log::debug!("模板展开结果被拒绝: {} => {}", expression, reason.message());
+            continue;
+        }
+        valid.push(expression);
+    }
+    let accepted = valid;
+
+    let defs: Vec<AlphaDefinition> = accepted
+        .iter()
+        .map(|expression| {
+            AlphaDefinition::with_defaults(
+                expression.clone(),
+                region.clone(),
+                universe.clone(),
+                delay,
+            )
+        })
+        .collect();
+    let inserted = AlphaRepository::insert_batch(db.as_ref(), defs).await?;
+    let provenance = Provenance {
+        prompt_hash: Some(Provenance::hash_prompt(&template)),
+        batch_id: Some(Provenance::new_batch_id()),
+        ..Provenance::source("template").with_scope(&region, &universe)
+    };
+    ProvenanceRepository::record(db.as_ref(), &provenance, &accepted).await?;
+
+    let mut queued = 0usize;
+    for expression in &accepted {
+        if BacktestRepository::create_job(
+            db.as_ref(),
+            expression.clone(),
+            region.clone(),
+            universe.clone(),
+            delay,
+        )
+        .await?
+        .is_some()
+        {
+            queued += 1;
+        }
+    }
+
+    let _ = evt_tx.send(AppEvent::Message(format!(
+        "模板展开完成: 展开 {}, 去重后 {}, 复杂度过滤 {}, 校验拒绝 {}, 入库 {}, 入队 {}",
+        total, deduped, too_complex, rejected, inserted, queued
+    )));
+    Ok(())
+}
+
+fn expand(
+    tpl: &Template,
+    fields: &[DataFieldModel],
+    cap: usize,
+) -> Result<Vec<String>, anyhow::Error> {
+    let mut rng = rand::thread_rng();
+    tpl.expand(fields, cap, &mut rng)
+        .map_err(|e| anyhow::anyhow!(e))
+}
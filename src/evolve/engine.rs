@@ -0,0 +1,499 @@
+//! 基于语法树的遗传变异：同类运算符替换、回看窗口调整、同数据集字段替换、父代间子树交叉。
+//!
+//! 这里只负责产生候选表达式，合法性（签名、字段范围、复杂度）由调用方统一校验。
+
+use crate::expr::ast::{Expr, ExprKind, Program};
+use crate::expr::checker::{SignatureTable, LOOKBACK_PARAMS};
+use crate::expr::format::render;
+use crate::expr::parse;
+use crate::generate::context::OperatorCatalog;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::HashMap;
+
+/// 回看窗口的候选取值
+const LOOKBACKS: [i64; 9] = [3, 5, 10, 20, 40, 60, 120, 250, 500];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variation {
+    Operator,
+    Lookback,
+    Field,
+    Crossover,
+}
+
+impl Variation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Variation::Operator => "operator",
+            Variation::Lookback => "lookback",
+            Variation::Field => "field",
+            Variation::Crossover => "crossover",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Child {
+    pub expression: String,
+    pub parents: Vec<String>,
+    pub variation: Variation,
+}
+
+/// 变异所需的素材：运算符类别与签名、字段所属数据集
+pub struct GenePool {
+    signatures: SignatureTable,
+    op_category: HashMap<String, String>,
+    ops_by_category: HashMap<String, Vec<String>>,
+    field_dataset: HashMap<String, String>,
+    fields_by_dataset: HashMap<String, Vec<String>>,
+}
+
+impl GenePool {
+    pub fn new(catalog: &OperatorCatalog, fields_by_dataset: HashMap<String, Vec<String>>) -> Self {
+        let mut op_category = HashMap::new();
+        let mut ops_by_category: HashMap<String, Vec<String>> = HashMap::new();
+        for (category, ops) in &catalog.by_category {
+            for op in ops {
+                op_category.insert(op.name.clone(), category.clone());
+                ops_by_category
+                    .entry(category.clone())
+                    .or_default()
+                    .push(op.name.clone());
+            }
+        }
+        let mut field_dataset = HashMap::new();
+        for (dataset, fields) in &fields_by_dataset {
+            for f in fields {
+                field_dataset.insert(f.clone(), dataset.clone());
+            }
+        }
+        Self {
+            signatures: SignatureTable::from_catalog(catalog),
+            op_category,
+            ops_by_category,
+            field_dataset,
+            fields_by_dataset,
+        }
+    }
+
+    /// 随机选择一种变异方式作用于父代；都不适用时返回 None
+    pub fn mutate(&self, parent: &str, rng: &mut impl Rng) -> Option<Child> {
+        let program = parse(parent).ok()?;
+        let mut kinds = [Variation::Operator, Variation::Lookback, Variation::Field];
+        kinds.shuffle(rng);
+        for kind in kinds {
+            let mut p = program.clone();
+            let changed = match kind {
+                Variation::Operator => self.swap_operator(&mut p, rng),
+                Variation::Lookback => change_lookback(&mut p, rng),
+                Variation::Field => self.swap_field(&mut p, rng),
+                Variation::Crossover => false,
+            };
+            if changed {
+                let expression = render(&p);
+                if expression != render(&program) {
+                    return Some(Child {
+                        expression,
+                        parents: vec![parent.to_string()],
+                        variation: kind,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// 用 `b` 中的一个函数调用子树替换 `a` 中某个调用的位置参数
+    pub fn crossover(&self, a: &str, b: &str, rng: &mut impl Rng) -> Option<Child> {
+        let mut pa = parse(a).ok()?;
+        let pb = parse(b).ok()?;
+
+        // 供体：不引用 b 局部变量的调用子树
+        let b_locals = pb.assigned_names();
+        let mut donors: Vec<Expr> = Vec::new();
+        pb.visit(&mut |e| {
+            if matches!(e.kind, ExprKind::Call { .. }) && !references_any(e, &b_locals) {
+                donors.push(e.clone());
+            }
+        });
+        let donor = donors.choose(rng)?.clone();
+
+        // 受体：a 中调用的位置参数，且参数本身是调用或字段
+        let a_locals = pa.assigned_names();
+        let mut slots: Vec<(usize, usize)> = Vec::new();
+        let mut idx = 0usize;
+        pa.visit(&mut |e| {
+            if let ExprKind::Call { args, .. } = &e.kind {
+                for (i, arg) in args.iter().enumerate() {
+                    let replaceable = arg.name.is_none()
+                        && match &arg.value.kind {
+                            ExprKind::Call { .. } => true,
+                            ExprKind::Ident(n) => !a_locals.contains(n),
+                            _ => false,
+                        };
+                    if replaceable {
+                        slots.push((idx, i));
+                    }
+                }
+            }
+            idx += 1;
+        });
+        let (target, arg_idx) = *slots.choose(rng)?;
+        replace_nth(&mut pa, target, |e| {
+            if let ExprKind::Call { args, .. } = &mut e.kind {
+                args[arg_idx].value = donor.clone();
+            }
+        });
+
+        let expression = render(&pa);
+        let original = [a, b].map(crate::expr::canonical_hash);
+        if original.contains(&crate::expr::canonical_hash(&expression)) {
+            return None;
+        }
+        Some(Child {
+            expression,
+            parents: vec![a.to_string(), b.to_string()],
+            variation: Variation::Crossover,
+        })
+    }
+
+    /// 同类别、位置参数个数一致且接受原有命名参数的其他运算符
+    fn alternatives(&self, name: &str, args: &[crate::expr::ast::Arg]) -> Vec<String> {
+        let Some(category) = self.op_category.get(name) else {
+            return Vec::new();
+        };
+        let positional = args.iter().filter(|a| a.name.is_none()).count();
+        self.ops_by_category
+            .get(category)
+            .map(|ops| {
+                ops.iter()
+                    .filter(|op| op.as_str() != name)
+                    .filter(|op| {
+                        self.signatures.get(op).is_some_and(|sig| {
+                            (sig.positional.len() == positional
+                                || (sig.variadic && positional >= sig.positional.len()))
+                                && args
+                                    .iter()
+                                    .filter_map(|a| a.name.as_deref())
+                                    .all(|k| sig.accepts_keyword(k))
+                        })
+                    })
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn swap_operator(&self, program: &mut Program, rng: &mut impl Rng) -> bool {
+        let mut targets: Vec<(usize, Vec<String>)> = Vec::new();
+        let mut idx = 0usize;
+        program.visit(&mut |e| {
+            if let ExprKind::Call { name, args } = &e.kind {
+                let alts = self.alternatives(name, args);
+                if !alts.is_empty() {
+                    targets.push((idx, alts));
+                }
+            }
+            idx += 1;
+        });
+        let Some((target, alts)) = targets.choose(rng) else {
+            return false;
+        };
+        let Some(new_name) = alts.choose(rng).cloned() else {
+            return false;
+        };
+        replace_nth(program, *target, |e| {
+            if let ExprKind::Call { name, .. } = &mut e.kind {
+                *name = new_name.clone();
+            }
+        })
+    }
+
+    fn swap_field(&self, program: &mut Program, rng: &mut impl Rng) -> bool {
+        let locals = program.assigned_names();
+        let mut targets: Vec<(usize, Vec<String>)> = Vec::new();
+        let mut idx = 0usize;
+        program.visit(&mut |e| {
+            if let ExprKind::Ident(name) = &e.kind {
+                if !locals.contains(name) {
+                    if let Some(fields) = self
+                        .field_dataset
+                        .get(name)
+                        .and_then(|d| self.fields_by_dataset.get(d))
+                    {
+                        let alts: Vec<String> =
+                            fields.iter().filter(|f| *f != name).cloned().collect();
+                        if !alts.is_empty() {
+                            targets.push((idx, alts));
+                        }
+                    }
+                }
+            }
+            idx += 1;
+        });
+        let Some((target, alts)) = targets.choose(rng) else {
+            return false;
+        };
+        let Some(new_field) = alts.choose(rng).cloned() else {
+            return false;
+        };
+        replace_nth(program, *target, |e| {
+            e.kind = ExprKind::Ident(new_field.clone());
+        })
+    }
+}
+
+/// 随机调整一个字面量回看窗口（命名参数 d/lookback/days，或 `ts_*` 的第二个位置参数）
+fn change_lookback(program: &mut Program, rng: &mut impl Rng) -> bool {
+    let mut targets: Vec<(usize, usize, i64)> = Vec::new();
+    let mut idx = 0usize;
+    program.visit(&mut |e| {
+        if let ExprKind::Call { name, args } = &e.kind {
+            let is_ts = name.to_ascii_lowercase().starts_with("ts_");
+            let mut positional = 0usize;
+            for (i, a) in args.iter().enumerate() {
+                let is_lookback = match a.name.as_deref() {
+                    Some(k) => LOOKBACK_PARAMS.contains(&k),
+                    None => {
+                        positional += 1;
+                        is_ts && positional == 2
+                    }
+                };
+                if let (true, ExprKind::Number { value, .. }) = (is_lookback, &a.value.kind) {
+                    if value.fract() == 0.0 && *value >= 1.0 {
+                        targets.push((idx, i, *value as i64));
+                    }
+                }
+            }
+        }
+        idx += 1;
+    });
+    let Some(&(target, arg_idx, current)) = targets.choose(rng) else {
+        return false;
+    };
+    let choices: Vec<i64> = LOOKBACKS
+        .iter()
+        .copied()
+        .filter(|v| *v != current)
+        .collect();
+    let Some(&new_value) = choices.choose(rng) else {
+        return false;
+    };
+    replace_nth(program, target, |e| {
+        if let ExprKind::Call { args, .. } = &mut e.kind {
+            args[arg_idx].value.kind = ExprKind::Number {
+                value: new_value as f64,
+                raw: new_value.to_string(),
+            };
+        }
+    })
+}
+
+/// 对前序遍历中第 `n` 个节点应用修改；返回是否找到该节点
+fn replace_nth(program: &mut Program, n: usize, mut f: impl FnMut(&mut Expr)) -> bool {
+    let mut idx = 0usize;
+    let mut done = false;
+    program.visit_mut(&mut |e| {
+        if !done && idx == n {
+            f(e);
+            done = true;
+        }
+        idx += 1;
+    });
+    done
+}
+
+fn references_any(e: &Expr, names: &[String]) -> bool {
+    let mut found = false;
+    e.visit(&mut |x| {
+        if let ExprKind::Ident(n) = &x.kind {
+            if names.contains(n) {
+                found = true;
+            }
+        }
+    });
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::context::OperatorInfo;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn pool() -> GenePool {
+        let defs = [
+            ("Time Series", "ts_mean", "ts_mean(x, d)"),
+            ("Time Series", "ts_sum", "ts_sum(x, d)"),
+            ("Time Series", "ts_rank", "ts_rank(x, d, constant=0)"),
+            ("Time Series", "ts_corr", "ts_corr(x, y, d)"),
+            ("Cross Sectional", "rank", "rank(x, rate=2)"),
+            ("Cross Sectional", "zscore", "zscore(x)"),
+            ("Arithmetic", "abs", "abs(x)"),
+            ("Arithmetic", "sign", "sign(x)"),
+            ("Arithmetic", "add", "add(x, y, filter=false)"),
+            ("Arithmetic", "subtract", "subtract(x, y, filter=false)"),
+            ("Arithmetic", "max", "max(x, y, ..)"),
+            ("Arithmetic", "min", "min(x, y, ..)"),
+        ];
+        let mut catalog = OperatorCatalog::default();
+        for (category, name, def) in defs {
+            catalog
+                .by_category
+                .entry(category.to_string())
+                .or_default()
+                .push(OperatorInfo {
+                    name: name.to_string(),
+                    category: category.to_string(),
+                    definition: Some(def.to_string()),
+                    ..Default::default()
+                });
+        }
+        GenePool::new(&catalog, HashMap::new())
+    }
+
+    /// 前序遍历中每个调用的 (名称, 位置参数个数, 命名参数)
+    fn calls(program: &Program) -> Vec<(String, usize, Vec<String>)> {
+        let mut out = Vec::new();
+        program.visit(&mut |e| {
+            if let ExprKind::Call { name, args } = &e.kind {
+                let keys = args.iter().filter_map(|a| a.name.clone()).collect();
+                out.push((
+                    name.clone(),
+                    args.iter().filter(|a| a.name.is_none()).count(),
+                    keys,
+                ));
+            }
+        });
+        out
+    }
+
+    /// 前序遍历中每个字面量参数的 (所在调用, 位置下标或参数名, 值)
+    fn literal_args(program: &Program) -> Vec<(String, String, f64)> {
+        let mut out = Vec::new();
+        program.visit(&mut |e| {
+            if let ExprKind::Call { name, args } = &e.kind {
+                let mut positional = 0usize;
+                for a in args {
+                    let slot = match &a.name {
+                        Some(k) => k.clone(),
+                        None => {
+                            positional += 1;
+                            positional.to_string()
+                        }
+                    };
+                    if let ExprKind::Number { value, .. } = &a.value.kind {
+                        out.push((name.clone(), slot, *value));
+                    }
+                }
+            }
+        });
+        out
+    }
+
+    #[test]
+    fn operator_swap_keeps_category_and_arity() {
+        let pool = pool();
+        // 没有字面量窗口与可替换字段，只会发生运算符替换
+        let parent =
+            "rank(add(abs(close), max(open, high, low)), rate=0) - ts_rank(volume, n, constant=1)";
+        let before = calls(&parse(parent).unwrap());
+        for seed in 0..200 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let child = pool.mutate(parent, &mut rng).expect("有可替换的运算符");
+            assert_eq!(child.variation, Variation::Operator);
+            let after = calls(&parse(&child.expression).unwrap());
+            assert_eq!(after.len(), before.len());
+            let changed: Vec<_> = before
+                .iter()
+                .zip(&after)
+                .filter(|(b, a)| b.0 != a.0)
+                .collect();
+            assert_eq!(changed.len(), 1, "{}", child.expression);
+            let (old, new) = changed[0];
+            assert_eq!(pool.op_category.get(&old.0), pool.op_category.get(&new.0));
+            assert_eq!((old.1, &old.2), (new.1, &new.2));
+            let sig = pool.signatures.get(&new.0).unwrap();
+            assert!(
+                sig.positional.len() == new.1 || (sig.variadic && new.1 >= sig.positional.len()),
+                "{}",
+                child.expression
+            );
+            assert!(
+                new.2.iter().all(|k| sig.accepts_keyword(k)),
+                "{}",
+                child.expression
+            );
+        }
+    }
+
+    #[test]
+    fn lookback_change_only_touches_window_slots() {
+        let src = "ts_rank(ts_mean(close, 20), 10, constant=5) + ts_corr(close, volume, 60) \
+                   + winsorize(close, std=4) + hump(close, 30) + decay(close, days=15) \
+                   + ts_sum(close, lookback=5) + ts_mean(close, 2.5)";
+        let allowed = [
+            ("ts_mean", "2"),
+            ("ts_rank", "2"),
+            ("decay", "days"),
+            ("ts_sum", "lookback"),
+        ];
+        let parent = parse(src).unwrap();
+        let before = literal_args(&parent);
+        let mut touched = std::collections::HashSet::new();
+        for seed in 0..200 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut p = parent.clone();
+            assert!(change_lookback(&mut p, &mut rng));
+            let after = literal_args(&p);
+            let changed: Vec<_> = before.iter().zip(&after).filter(|(b, a)| b != a).collect();
+            assert_eq!(changed.len(), 1, "{}", render(&p));
+            let (old, new) = changed[0];
+            assert_eq!((&old.0, &old.1), (&new.0, &new.1));
+            assert!(
+                allowed.contains(&(old.0.as_str(), old.1.as_str())),
+                "{}",
+                render(&p)
+            );
+            assert_ne!(old.2, 2.5, "非整数窗口不应被改写");
+            assert!(LOOKBACKS.contains(&(new.2 as i64)) && new.2 != old.2);
+            touched.insert((old.0.clone(), old.1.clone()));
+        }
+        assert_eq!(touched.len(), allowed.len());
+    }
+
+    #[test]
+    fn crossover_never_imports_donor_locals() {
+        let pool = pool();
+        let a = "m = ts_mean(close, 20); rank(m) - ts_sum(open, 5)";
+        let b = "v = ts_sum(volume, 10); zscore(v) + ts_corr(returns, abs(high), 5)";
+        let b_locals = parse(b).unwrap().assigned_names();
+        let mut produced = 0;
+        for seed in 0..200 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let Some(child) = pool.crossover(a, b, &mut rng) else {
+                continue;
+            };
+            produced += 1;
+            assert_eq!(child.variation, Variation::Crossover);
+            assert_eq!(child.parents, vec![a.to_string(), b.to_string()]);
+            let program = parse(&child.expression).unwrap();
+            let mut idents = Vec::new();
+            program.visit(&mut |e| {
+                if let ExprKind::Ident(n) = &e.kind {
+                    idents.push(n.clone());
+                }
+            });
+            assert!(
+                !idents.iter().any(|n| b_locals.contains(n)),
+                "{}",
+                child.expression
+            );
+            // 受体中作为参数的局部变量不是可替换位置
+            assert!(child.expression.contains("rank(m)"), "{}", child.expression);
+        }
+        assert!(produced > 0);
+    }
+}
@@ -0,0 +1,4 @@
+pub mod engine;
+pub mod service;
+
+pub use service::{EvolveConfig, EvolveService};
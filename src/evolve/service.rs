@@ -0,0 +1,238 @@
+use crate::evolve::engine::{Child, GenePool};
+use crate::expr::checker::SignatureTable;
+use crate::expr::complexity::Complexity;
+use crate::generate::context::GenerateContextProvider;
+use crate::generate::parser::validate_for_scope;
+use crate::generate::ComplexityLimits;
+use crate::storage::repository::{
+    AlphaDefinition, AlphaRepository, BacktestRepository, DataFieldRepository, LineageRepository,
+    Provenance, ProvenanceRepository,
+};
+use crate::AppEvent;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use sea_orm::DatabaseConnection;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+#[derive(Clone, Debug)]
+pub struct EvolveConfig {
+    pub batch_size: usize,
+    pub interval_sec: u64,
+    pub region: String,
+    pub universe: String,
+    pub delay: i32,
+    pub parent_pool: u64,
+    pub crossover_rate: f64,
+    pub complexity_limits: ComplexityLimits,
+}
+
+impl EvolveConfig {
+    /// 父代池大小 EVOLVE_PARENTS（默认 20），交叉概率 EVOLVE_CROSSOVER_RATE（默认 0.3）
+    pub fn from_env(
+        batch_size: usize,
+        interval_sec: u64,
+        region: Option<String>,
+        universe: Option<String>,
+        delay: Option<i32>,
+    ) -> Self {
+        let parent_pool = std::env::var("EVOLVE_PARENTS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .unwrap_or(20)
+            .max(1);
+        let crossover_rate = std::env::var("EVOLVE_CROSSOVER_RATE")
+            .ok()
+            .and_then(|s| s.parse::<f64>().ok())
+            .unwrap_or(0.3)
+            .clamp(0.0, 1.0);
+        Self {
+            batch_size: batch_size.max(1),
+            interval_sec,
+            region: region.unwrap_or_else(|| "CHN".to_string()),
+            universe: universe.unwrap_or_else(|| "TOP2000U".to_string()),
+            delay: delay.unwrap_or(1),
+            parent_pool,
+            crossover_rate,
+            complexity_limits: ComplexityLimits::from_env(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct EvolveResult {
+    pub parents: usize,
+    pub candidates: usize,
+    pub queued: usize,
+    pub rejected: usize,
+}
+
+pub struct EvolveService {
+    db: Arc<DatabaseConnection>,
+    evt_tx: mpsc::UnboundedSender<AppEvent>,
+    ctx: Arc<dyn GenerateContextProvider>,
+}
+
+impl EvolveService {
+    pub fn new(
+        db: Arc<DatabaseConnection>,
+        evt_tx: mpsc::UnboundedSender<AppEvent>,
+        ctx: Arc<dyn GenerateContextProvider>,
+    ) -> Self {
+        Self { db, evt_tx, ctx }
+    }
+
+    pub async fn run_loop(&self, cfg: EvolveConfig) {
+        loop {
+            match self.evolve_once(&cfg).await {
+                Ok(res) => {
+                    let _ = self.evt_tx.send(AppEvent::Log(format!(
+                        "进化完成: 父代 {}, 候选 {}, 入队 {}, 拒绝 {}",
+                        res.parents, res.candidates, res.queued, res.rejected
+                    )));
+                }
+                Err(e) => {
+                    let _ = self
+                        .evt_tx
+                        .send(AppEvent::Error(format!("进化出错: {}", e)));
+                }
+            }
+            tokio::time::sleep(tokio::time::Duration::from_secs(cfg.interval_sec.max(1))).await;
+        }
+    }
+
+    /// 一轮进化：选取父代 -> 变异/交叉 -> 去重与校验 -> 入库、记录谱系、入队
+    pub async fn evolve_once(&self, cfg: &EvolveConfig) -> Result<EvolveResult, anyhow::Error> {
+        let db = self.db.as_ref();
+        let parents: Vec<String> =
+            AlphaRepository::top_by_fitness(db, &cfg.region, &cfg.universe, cfg.parent_pool)
+                .await?
+                .into_iter()
+                .map(|m| m.expression)
+                .collect();
+        if parents.is_empty() {
+            return Err(anyhow::anyhow!(
+                "没有可用的父代：{} / {} 下尚无已完成且有 fitness 的 Alpha",
+                cfg.region,
+                cfg.universe
+            ));
+        }
+        let catalog = self.ctx.get_operator_catalog().await?;
+        let signatures = SignatureTable::from_catalog(&catalog);
+        let fields =
+            DataFieldRepository::fields_by_dataset(db, &cfg.region, &cfg.universe, cfg.delay)
+                .await?;
+        let pool = GenePool::new(&catalog, fields);
+
+        let children = breed(&pool, &parents, cfg);
+        let hashes: Vec<String> = children
+            .iter()
+            .map(|c| crate::expr::canonical_hash(&c.expression))
+            .collect();
+        let existing = AlphaRepository::existing_canonical_hashes(db, hashes.clone()).await?;
+
+        let mut res = EvolveResult {
+            parents: parents.len(),
+            candidates: children.len(),
+            ..Default::default()
+        };
+        let provenance = Provenance {
+            batch_id: Some(Provenance::new_batch_id()),
+            ..Provenance::source("evolve").with_scope(&cfg.region, &cfg.universe)
+        };
+        for (child, hash) in children.iter().zip(hashes) {
+            if res.queued >= cfg.batch_size {
+                break;
+            }
+            if existing.contains(&hash) {
+                continue;
+            }
+            let check = match Complexity::of(&child.expression) {
+                Some(c) => cfg.complexity_limits.check(&c).map_err(|e| e.to_string()),
+                None => Err("无法解析".to_string()),
+            };
+            let check = match check {
+                Ok(()) => validate_for_scope(
+                    db,
+                    &child.expression,
+                    &signatures,
+                    &cfg.region,
+                    &cfg.universe,
+                    cfg.delay,
+                )
+                .await
+                .map_err(|r| r.message()),
+                Err(e) => Err(e),
+            };
+            if let Err(reason) = check {
+                res.rejected += 1;
+                log::debug!("进化子代被拒绝: {} => {}", child.expression, reason);
+                continue;
+            }
+
+            AlphaRepository::insert_or_ignore_alpha(
+                db,
+                AlphaDefinition::with_defaults(
+                    child.expression.clone(),
+                    cfg.region.clone(),
+                    cfg.universe.clone(),
+                    cfg.delay,
+                ),
+            )
+            .await?;
+            LineageRepository::record(
+                db,
+                &child.expression,
+                &child.parents,
+                child.variation.as_str(),
+            )
+            .await?;
+            ProvenanceRepository::record(db, &provenance, std::slice::from_ref(&child.expression))
+                .await?;
+            if BacktestRepository::create_job(
+                db,
+                child.expression.clone(),
+                cfg.region.clone(),
+                cfg.universe.clone(),
+                cfg.delay,
+            )
+            .await?
+            .is_some()
+            {
+                res.queued += 1;
+            }
+        }
+        Ok(res)
+    }
+}
+
+/// 按交叉概率交替产生子代，批内与父代按规范化哈希去重；候选数为批大小的数倍以抵消校验淘汰
+fn breed(pool: &GenePool, parents: &[String], cfg: &EvolveConfig) -> Vec<Child> {
+    let mut rng = rand::thread_rng();
+    let mut seen: HashSet<String> = parents
+        .iter()
+        .map(|p| crate::expr::canonical_hash(p))
+        .collect();
+    let want = cfg.batch_size * 3;
+    let mut out = Vec::new();
+    for _ in 0..cfg.batch_size * 20 {
+        if out.len() >= want {
+            break;
+        }
+        let child = if parents.len() >= 2 && rng.gen_bool(cfg.crossover_rate) {
+            let pair: Vec<&String> = parents.choose_multiple(&mut rng, 2).collect();
+            pool.crossover(pair[0], pair[1], &mut rng)
+        } else {
+            parents
+                .choose(&mut rng)
+                .and_then(|p| pool.mutate(p, &mut rng))
+        };
+        if let Some(c) = child {
+            if seen.insert(crate::expr::canonical_hash(&c.expression)) {
+                out.push(c);
+            }
+        }
+    }
+    out
+}
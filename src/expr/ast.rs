@@ -0,0 +1,265 @@
+//! FASTEXPR 语法树。
+
+use crate::expr::lexer::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+impl UnaryOp {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            UnaryOp::Neg => "-",
+            UnaryOp::Not => "!",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    And,
+    Or,
+}
+
+impl BinaryOp {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Pow => "^",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "!=",
+            BinaryOp::And => "&&",
+            BinaryOp::Or => "||",
+        }
+    }
+
+    /// 绑定优先级，数值越大越紧
+    pub fn precedence(&self) -> u8 {
+        match self {
+            BinaryOp::Or => 1,
+            BinaryOp::And => 2,
+            BinaryOp::Eq | BinaryOp::Ne => 3,
+            BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => 4,
+            BinaryOp::Add | BinaryOp::Sub => 5,
+            BinaryOp::Mul | BinaryOp::Div => 6,
+            BinaryOp::Pow => 7,
+        }
+    }
+
+    pub fn is_right_assoc(&self) -> bool {
+        matches!(self, BinaryOp::Pow)
+    }
+
+    /// 交换两侧操作数结果不变
+    pub fn is_commutative(&self) -> bool {
+        matches!(
+            self,
+            BinaryOp::Add
+                | BinaryOp::Mul
+                | BinaryOp::Eq
+                | BinaryOp::Ne
+                | BinaryOp::And
+                | BinaryOp::Or
+        )
+    }
+
+    /// 可交换且可结合：连续链可以整体展开排序
+    pub fn is_associative(&self) -> bool {
+        matches!(
+            self,
+            BinaryOp::Add | BinaryOp::Mul | BinaryOp::And | BinaryOp::Or
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    Number {
+        value: f64,
+        raw: String,
+    },
+    Str(String),
+    Ident(String),
+    Call {
+        name: String,
+        args: Vec<Arg>,
+    },
+    Unary {
+        op: UnaryOp,
+        operand: Box<Expr>,
+    },
+    Binary {
+        op: BinaryOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    Ternary {
+        cond: Box<Expr>,
+        then_branch: Box<Expr>,
+        else_branch: Box<Expr>,
+    },
+}
+
+/// 函数调用参数；命名参数形如 `lower=0.1`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arg {
+    pub name: Option<String>,
+    pub value: Expr,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+    Assign {
+        name: String,
+        value: Expr,
+        span: Span,
+    },
+    Expr(Expr),
+}
+
+/// 多语句表达式：`a = ...; b = ...; 结果表达式`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    pub statements: Vec<Stmt>,
+}
+
+impl Expr {
+    /// 前序遍历所有子表达式（含自身）
+    pub fn visit<'a>(&'a self, f: &mut impl FnMut(&'a Expr)) {
+        f(self);
+        match &self.kind {
+            ExprKind::Call { args, .. } => {
+                for a in args {
+                    a.value.visit(f);
+                }
+            }
+            ExprKind::Unary { operand, .. } => operand.visit(f),
+            ExprKind::Binary { lhs, rhs, .. } => {
+                lhs.visit(f);
+                rhs.visit(f);
+            }
+            ExprKind::Ternary {
+                cond,
+                then_branch,
+                else_branch,
+            } => {
+                cond.visit(f);
+                then_branch.visit(f);
+                else_branch.visit(f);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Expr {
+    /// 前序遍历所有子表达式（可修改）
+    pub fn visit_mut(&mut self, f: &mut impl FnMut(&mut Expr)) {
+        f(self);
+        match &mut self.kind {
+            ExprKind::Call { args, .. } => {
+                for a in args {
+                    a.value.visit_mut(f);
+                }
+            }
+            ExprKind::Unary { operand, .. } => operand.visit_mut(f),
+            ExprKind::Binary { lhs, rhs, .. } => {
+                lhs.visit_mut(f);
+                rhs.visit_mut(f);
+            }
+            ExprKind::Ternary {
+                cond,
+                then_branch,
+                else_branch,
+            } => {
+                cond.visit_mut(f);
+                then_branch.visit_mut(f);
+                else_branch.visit_mut(f);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Program {
+    pub fn visit_mut(&mut self, f: &mut impl FnMut(&mut Expr)) {
+        for st in &mut self.statements {
+            match st {
+                Stmt::Assign { value, .. } => value.visit_mut(f),
+                Stmt::Expr(e) => e.visit_mut(f),
+            }
+        }
+    }
+
+    pub fn visit<'a>(&'a self, f: &mut impl FnMut(&'a Expr)) {
+        for st in &self.statements {
+            match st {
+                Stmt::Assign { value, .. } => value.visit(f),
+                Stmt::Expr(e) => e.visit(f),
+            }
+        }
+    }
+
+    /// 语句中赋值的局部变量名
+    pub fn assigned_names(&self) -> Vec<String> {
+        self.statements
+            .iter()
+            .filter_map(|st| match st {
+                Stmt::Assign { name, .. } => Some(name.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// 按出现顺序列出所有函数调用名（可能重复）
+    pub fn operators(&self) -> Vec<String> {
+        let mut out = Vec::new();
+        self.visit(&mut |e| {
+            if let ExprKind::Call { name, .. } = &e.kind {
+                out.push(name.clone());
+            }
+        });
+        out
+    }
+
+    /// 按出现顺序列出引用的标识符（不含函数名、命名参数名和局部变量）
+    pub fn identifiers(&self) -> Vec<String> {
+        let locals = self.assigned_names();
+        let mut out = Vec::new();
+        self.visit(&mut |e| {
+            if let ExprKind::Ident(name) = &e.kind {
+                if !locals.contains(name) {
+                    out.push(name.clone());
+                }
+            }
+        });
+        out
+    }
+}
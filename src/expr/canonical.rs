@@ -0,0 +1,286 @@
+//! 表达式规范化：用于判断两个 FASTEXPR 表达式是否“实质相同”。
+//!
+//! 规则：
+//! - 忽略空白与多余括号，参数统一为 `a, b` 形式
+//! - 数值字面量统一（`10.0`/`10`/`1e1` -> `10`，`.5` -> `0.5`）
+//! - 命名参数按名称排序并置于位置参数之后
+//! - 可交换运算符（add/multiply/max/min/and/or）的位置参数排序
+//! - 可交换的中缀运算（`+ * == != && ||`）两侧排序，`+ * && ||` 连续链整体排序
+//!
+//! 无法解析的表达式退化为按 token 拼接，保证不同输入不会被误判为相同。
+//!
+//! 结构骨架在规范化基础上再把字段统一为 `x`、数值统一为 `#`，用于把 Alpha 归入“家族”。
+
+use crate::expr::ast::{BinaryOp, Expr, ExprKind, Program, Stmt};
+use crate::expr::lexer::{tokenize, TokenKind};
+use crate::expr::parser::parse;
+
+/// 位置参数可任意交换顺序的运算符
+const COMMUTATIVE_OPS: [&str; 6] = ["add", "multiply", "max", "min", "and", "or"];
+
+/// 返回表达式的规范化形式
+pub fn canonicalize(expr: &str) -> String {
+    match parse(expr) {
+        Ok(program) => render_program(&program),
+        Err(_) => fallback(expr),
+    }
+}
+
+/// 规范化形式的稳定哈希（FNV-1a 64 位，十六进制），用于持久化去重
+pub fn canonical_hash(expr: &str) -> String {
+    format!("{:016x}", fnv1a64(canonicalize(expr).as_bytes()))
+}
+
+/// 结构骨架：字段（非局部变量、非关键字）替换为 `x`，数值替换为 `#`；无法解析时返回 None
+pub fn skeleton(expr: &str) -> Option<String> {
+    let mut program = parse(expr).ok()?;
+    let locals = program.assigned_names();
+    program.visit_mut(&mut |e| {
+        let abstracted = match &e.kind {
+            ExprKind::Ident(n) if !locals.contains(n) && !crate::expr::is_keyword(n) => {
+                Some(ExprKind::Ident("x".to_string()))
+            }
+            ExprKind::Number { .. } => Some(ExprKind::Number {
+                value: 0.0,
+                raw: "#".to_string(),
+            }),
+            _ => None,
+        };
+        if let Some(kind) = abstracted {
+            e.kind = kind;
+        }
+    });
+    Some(render_program(&program))
+}
+
+/// 家族 id：结构骨架的稳定哈希
+pub fn family_id(expr: &str) -> Option<String> {
+    skeleton(expr).map(|s| format!("{:016x}", fnv1a64(s.as_bytes())))
+}
+
+fn render_program(program: &Program) -> String {
+    program
+        .statements
+        .iter()
+        .map(|st| match st {
+            Stmt::Assign { name, value, .. } => format!("{}={}", name, render(value, 0)),
+            Stmt::Expr(e) => render(e, 0),
+        })
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+pub(crate) fn fnv1a64(bytes: &[u8]) -> u64 {
+    let mut h: u64 = 0xcbf29ce484222325;
+    for b in bytes {
+        h ^= *b as u64;
+        h = h.wrapping_mul(0x100000001b3);
+    }
+    h
+}
+
+pub(crate) fn normalize_number(raw: &str) -> String {
+    match raw.parse::<f64>() {
+        Ok(v) if v.is_finite() => {
+            if v.fract() == 0.0 && v.abs() < 1e15 {
+                format!("{}", v as i64)
+            } else {
+                format!("{}", v)
+            }
+        }
+        _ => raw.to_string(),
+    }
+}
+
+/// 解析失败时：能分词则按 token 拼接，否则压缩空白；加前缀与合法形式区分
+fn fallback(expr: &str) -> String {
+    let body = match tokenize(expr) {
+        Ok(tokens) => tokens
+            .iter()
+            .filter_map(|t| match &t.kind {
+                TokenKind::Eof => None,
+                TokenKind::Ident(s) | TokenKind::Str(s) => Some(s.clone()),
+                TokenKind::Number { raw, .. } => Some(normalize_number(raw)),
+                other => Some(other.symbol().to_string()),
+            })
+            .collect::<Vec<_>>()
+            .join(" "),
+        Err(_) => expr.split_whitespace().collect::<Vec<_>>().join(" "),
+    };
+    format!("?{}", body)
+}
+
+/// 按优先级渲染：子表达式优先级低于 `min_prec` 时加括号
+fn render(e: &Expr, min_prec: u8) -> String {
+    match &e.kind {
+        ExprKind::Number { raw, .. } => normalize_number(raw),
+        ExprKind::Str(s) | ExprKind::Ident(s) => s.clone(),
+        ExprKind::Call { name, args } => {
+            let mut positional: Vec<String> = Vec::new();
+            let mut named: Vec<(String, String)> = Vec::new();
+            for a in args {
+                let v = render(&a.value, 0);
+                match &a.name {
+                    Some(n) => named.push((n.clone(), format!("{}={}", n, v))),
+                    None => positional.push(v),
+                }
+            }
+            if COMMUTATIVE_OPS.contains(&name.to_ascii_lowercase().as_str()) {
+                positional.sort();
+            }
+            named.sort_by(|a, b| a.0.cmp(&b.0));
+            let all: Vec<String> = positional
+                .into_iter()
+                .chain(named.into_iter().map(|(_, s)| s))
+                .collect();
+            format!("{}({})", name, all.join(", "))
+        }
+        ExprKind::Unary { op, operand } => {
+            // 一元运算的操作数优先级需高于乘除，与解析规则一致
+            let s = format!(
+                "{}{}",
+                op.symbol(),
+                render(operand, BinaryOp::Pow.precedence())
+            );
+            wrap(s, BinaryOp::Pow.precedence(), min_prec)
+        }
+        ExprKind::Binary { op, lhs, rhs } => {
+            let prec = op.precedence();
+            let s = if op.is_associative() {
+                let mut parts = Vec::new();
+                flatten(e, *op, &mut parts);
+                let mut rendered: Vec<String> = parts.iter().map(|p| render(p, prec + 1)).collect();
+                rendered.sort();
+                rendered.join(op.symbol())
+            } else {
+                let (lp, rp) = if op.is_right_assoc() {
+                    (prec + 1, prec)
+                } else {
+                    (prec, prec + 1)
+                };
+                let mut l = render(lhs, lp);
+                let mut r = render(rhs, rp);
+                if op.is_commutative() && r < l {
+                    // 交换后两侧都按右操作数的要求加括号
+                    l = render(lhs, prec + 1);
+                    r = render(rhs, prec + 1);
+                    std::mem::swap(&mut l, &mut r);
+                }
+                format!("{}{}{}", l, op.symbol(), r)
+            };
+            wrap(s, prec, min_prec)
+        }
+        ExprKind::Ternary {
+            cond,
+            then_branch,
+            else_branch,
+        } => {
+            let s = format!(
+                "{}?{}:{}",
+                render(cond, 1),
+                render(then_branch, 0),
+                render(else_branch, 0)
+            );
+            wrap(s, 0, min_prec)
+        }
+    }
+}
+
+fn wrap(s: String, prec: u8, min_prec: u8) -> String {
+    if prec < min_prec {
+        format!("({})", s)
+    } else {
+        s
+    }
+}
+
+/// 展开同一可结合运算的连续链：a+(b+c) -> [a, b, c]
+fn flatten<'a>(e: &'a Expr, op: BinaryOp, out: &mut Vec<&'a Expr>) {
+    match &e.kind {
+        ExprKind::Binary { op: o, lhs, rhs } if *o == op => {
+            flatten(lhs, op, out);
+            flatten(rhs, op, out);
+        }
+        _ => out.push(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn same(a: &str, b: &str) {
+        assert_eq!(canonicalize(a), canonicalize(b), "{} vs {}", a, b);
+        assert_eq!(canonical_hash(a), canonical_hash(b), "{} vs {}", a, b);
+    }
+
+    fn differ(a: &str, b: &str) {
+        assert_ne!(canonical_hash(a), canonical_hash(b), "{} vs {}", a, b);
+    }
+
+    #[test]
+    fn whitespace_parens_and_numbers() {
+        same("rank( close )", "rank(close)");
+        same("((a + b))", "a+b");
+        same("ts_mean(x, 10.0)", "ts_mean(x, 1e1)");
+        same("x * .5", "x * 0.5");
+        differ("ts_mean(x, 10)", "ts_mean(x, 20)");
+    }
+
+    #[test]
+    fn commutative_rewrites_hash_equal() {
+        same("a + b", "b + a");
+        same("a * b * c", "c * (b * a)");
+        same("a + (b + c)", "(c + a) + b");
+        same("x == 1", "1 == x");
+        same("p && q || r", "r || q && p");
+        same("add(a, b)", "add(b, a)");
+        same("max(rank(x), y)", "max(y, rank(x))");
+        same(
+            "ts_rank(close, 20, rettype=1, lag=2)",
+            "ts_rank(close, 20, lag=2, rettype=1)",
+        );
+    }
+
+    #[test]
+    fn non_commutative_forms_stay_distinct() {
+        differ("a - b", "b - a");
+        differ("a / b", "b / a");
+        differ("a ^ b", "b ^ a");
+        differ("a < b", "b < a");
+        differ("ts_corr(a, b, 5)", "ts_corr(b, a, 5)");
+        differ("-(a + b)", "-a + b");
+        differ("(a - b) - c", "a - (b - c)");
+        // 交换乘法两侧后仍保留子表达式的括号
+        same("(a + b) * c", "c * (b + a)");
+        differ("(a + b) * c", "a + b * c");
+    }
+
+    #[test]
+    fn assignments_and_fallback() {
+        same("t = a + b; rank(t)", "t=b+a;rank(t)");
+        differ("t = a; rank(t)", "u = a; rank(u)");
+        // 无法解析的表达式不会与合法表达式混同
+        assert!(canonicalize("rank(close").starts_with('?'));
+        differ("rank(close", "rank(close)");
+        same("rank( close", "rank(close");
+    }
+
+    #[test]
+    fn family_abstracts_fields_and_numbers() {
+        assert_eq!(
+            skeleton("ts_rank(close, 20)").as_deref(),
+            Some("ts_rank(x, #)")
+        );
+        assert_eq!(
+            family_id("ts_rank(close, 20)"),
+            family_id("ts_rank(volume, 5)")
+        );
+        assert_ne!(family_id("ts_rank(close, 20)"), family_id("rank(close)"));
+        assert_eq!(
+            skeleton("t = rank(close); group_rank(t, industry)").as_deref(),
+            Some("t=rank(x);group_rank(t, industry)")
+        );
+        assert_eq!(family_id("rank(close"), None);
+    }
+}
@@ -0,0 +1,402 @@
+//! 基于运算符目录的调用检查：未知运算符、位置/命名参数个数、回看窗口取整、关键字名。
+//!
+//! 签名来自目录中每个运算符的 `definition`，例如：
+//! - `ts_mean(x, d)`
+//! - `winsorize(x, std=4)`
+//! - `add(x, y, filter = false), x + y`（只取与名称匹配的函数形式）
+//! - `max(x, y, ..)`（`..` 表示可变参数）
+
+use crate::expr::ast::{Expr, ExprKind, Program};
+use crate::expr::parser::ParseError;
+use crate::generate::context::OperatorCatalog;
+use std::collections::HashMap;
+
+/// 视为回看窗口（需为正整数）的参数名
+pub(crate) const LOOKBACK_PARAMS: [&str; 3] = ["d", "lookback", "days"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    pub positional: Vec<String>,
+    pub keywords: Vec<String>,
+    pub variadic: bool,
+}
+
+impl Signature {
+    /// 从 definition 中找到 `name(...)` 形式并解析参数表；找不到则返回 None
+    pub fn parse(name: &str, definition: &str) -> Option<Self> {
+        let open = find_call(definition, name)?;
+        let inner = balanced_inner(&definition[open..])?;
+        let mut sig = Signature {
+            positional: Vec::new(),
+            keywords: Vec::new(),
+            variadic: false,
+        };
+        for part in split_top_level(inner) {
+            let p = part.trim();
+            if p.is_empty() {
+                continue;
+            }
+            if p.contains("..") {
+                sig.variadic = true;
+            } else if let Some((k, _)) = p.split_once('=') {
+                sig.keywords.push(k.trim().to_string());
+            } else {
+                sig.positional.push(p.to_string());
+            }
+        }
+        Some(sig)
+    }
+
+    /// 作为关键字传入时可用的名称：命名参数 + 形如标识符的位置参数名
+    pub fn accepts_keyword(&self, key: &str) -> bool {
+        self.keywords.iter().any(|k| k == key) || self.positional.iter().any(|p| p == key)
+    }
+}
+
+/// 运算符名 -> 签名（目录里有但 definition 无法解析的记为 None，只做存在性检查）
+#[derive(Debug, Clone, Default)]
+pub struct SignatureTable {
+    by_name: HashMap<String, Option<Signature>>,
+}
+
+impl SignatureTable {
+    pub fn from_catalog(catalog: &OperatorCatalog) -> Self {
+        let mut by_name = HashMap::new();
+        for ops in catalog.by_category.values() {
+            for op in ops {
+                let sig = op
+                    .definition
+                    .as_deref()
+                    .and_then(|d| Signature::parse(&op.name, d));
+                by_name.insert(op.name.clone(), sig);
+            }
+        }
+        Self { by_name }
+    }
+
+    /// 目录为空（未登录/拉取失败）时不做任何检查
+    pub fn is_empty(&self) -> bool {
+        self.by_name.is_empty()
+    }
+
+    /// 运算符的签名（目录中没有或 definition 无法解析时为 None）
+    pub fn get(&self, name: &str) -> Option<&Signature> {
+        self.lookup(name).and_then(|s| s.as_ref())
+    }
+
+    /// 目录中是否有该运算符
+    pub fn contains(&self, name: &str) -> bool {
+        self.lookup(name).is_some()
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Option<Signature>> {
+        self.by_name
+            .get(name)
+            .or_else(|| self.by_name.get(&name.to_ascii_lowercase()))
+    }
+}
+
+/// 检查表达式中的每个调用，返回全部诊断（按出现顺序）
+pub fn check(program: &Program, table: &SignatureTable) -> Vec<ParseError> {
+    let mut out = Vec::new();
+    if table.is_empty() {
+        return out;
+    }
+    program.visit(&mut |e| {
+        if let ExprKind::Call { name, args } = &e.kind {
+            check_call(e, name, args, table, &mut out);
+        }
+    });
+    out
+}
+
+fn check_call(
+    call: &Expr,
+    name: &str,
+    args: &[crate::expr::ast::Arg],
+    table: &SignatureTable,
+    out: &mut Vec<ParseError>,
+) {
+    let Some(entry) = table.lookup(name) else {
+        out.push(ParseError::new(
+            "unknown_operator",
+            format!("未知运算符 `{}`", name),
+            call.span,
+        ));
+        return;
+    };
+    let Some(sig) = entry else {
+        return;
+    };
+
+    let positional: Vec<&crate::expr::ast::Arg> =
+        args.iter().filter(|a| a.name.is_none()).collect();
+    let mut filled = positional.len().min(sig.positional.len());
+
+    if !sig.variadic && positional.len() > sig.positional.len() {
+        let extra = positional[sig.positional.len()];
+        out.push(ParseError::new(
+            "arity",
+            format!(
+                "`{}` 最多接受 {} 个位置参数，实际 {} 个（签名: {}）",
+                name,
+                sig.positional.len(),
+                positional.len(),
+                describe_sig(name, sig)
+            ),
+            extra.span,
+        ));
+    }
+
+    for a in args.iter().filter(|a| a.name.is_some()) {
+        let key = a.name.as_deref().unwrap_or_default();
+        if !sig.accepts_keyword(key) {
+            out.push(ParseError::new(
+                "unknown_keyword",
+                format!(
+                    "`{}` 不接受参数 `{}`（签名: {}）",
+                    name,
+                    key,
+                    describe_sig(name, sig)
+                ),
+                a.span,
+            ));
+            continue;
+        }
+        if let Some(idx) = sig.positional.iter().position(|p| p == key) {
+            if idx < positional.len() {
+                out.push(ParseError::new(
+                    "duplicate_argument",
+                    format!("`{}` 的参数 `{}` 重复传入", name, key),
+                    a.span,
+                ));
+            } else {
+                filled += 1;
+            }
+        }
+    }
+
+    if filled < sig.positional.len() {
+        out.push(ParseError::new(
+            "arity",
+            format!(
+                "`{}` 需要 {} 个位置参数，实际 {} 个（签名: {}）",
+                name,
+                sig.positional.len(),
+                filled,
+                describe_sig(name, sig)
+            ),
+            call.span,
+        ));
+    }
+
+    // 回看窗口：按位置或按名称传入的字面量都必须是正整数
+    let mut lookbacks: Vec<&Expr> = Vec::new();
+    for (i, a) in positional.iter().enumerate() {
+        if sig
+            .positional
+            .get(i)
+            .is_some_and(|p| LOOKBACK_PARAMS.contains(&p.as_str()))
+        {
+            lookbacks.push(&a.value);
+        }
+    }
+    for a in args {
+        if a.name
+            .as_deref()
+            .is_some_and(|k| LOOKBACK_PARAMS.contains(&k))
+        {
+            lookbacks.push(&a.value);
+        }
+    }
+    for v in lookbacks {
+        if let ExprKind::Number { value, raw } = &v.kind {
+            if value.fract() != 0.0 || *value < 1.0 {
+                out.push(ParseError::new(
+                    "bad_lookback",
+                    format!("`{}` 的回看窗口必须是正整数，实际为 `{}`", name, raw),
+                    v.span,
+                ));
+            }
+        }
+    }
+}
+
+fn describe_sig(name: &str, sig: &Signature) -> String {
+    let mut parts: Vec<String> = sig.positional.clone();
+    parts.extend(sig.keywords.iter().map(|k| format!("{}=…", k)));
+    if sig.variadic {
+        parts.push("..".to_string());
+    }
+    format!("{}({})", name, parts.join(", "))
+}
+
+/// 找到 `name(` 的位置（返回 `(` 的下标），要求名称前不是标识符字符
+fn find_call(def: &str, name: &str) -> Option<usize> {
+    let bytes = def.as_bytes();
+    let mut from = 0usize;
+    while let Some(rel) = def[from..].find(name) {
+        let start = from + rel;
+        let mut end = start + name.len();
+        let boundary_ok = start == 0 || {
+            let b = bytes[start - 1];
+            !(b.is_ascii_alphanumeric() || b == b'_')
+        };
+        while end < bytes.len() && bytes[end] == b' ' {
+            end += 1;
+        }
+        if boundary_ok && bytes.get(end) == Some(&b'(') {
+            return Some(end);
+        }
+        from = start + name.len();
+    }
+    None
+}
+
+/// 给定以 `(` 开头的字符串，返回配对括号内的内容
+fn balanced_inner(s: &str) -> Option<&str> {
+    let mut depth = 0i32;
+    let mut quote: Option<char> = None;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(&s[1..i]);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// 按顶层逗号切分（忽略括号与引号内的逗号）
+fn split_top_level(s: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut depth = 0i32;
+    let mut quote: Option<char> = None;
+    let mut start = 0usize;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                out.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    out.push(&s[start..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::parser::parse;
+    use crate::generate::context::OperatorInfo;
+
+    fn table() -> SignatureTable {
+        let defs = [
+            ("ts_mean", "ts_mean(x, d)"),
+            ("winsorize", "winsorize(x, std=4)"),
+            ("add", "add(x, y, filter = false), x + y"),
+            ("max", "max(x, y, ..)"),
+            ("rank", "rank(x, rate=2)"),
+            ("if_else", "x ? y : z"),
+        ];
+        let mut catalog = OperatorCatalog::default();
+        let ops = defs
+            .iter()
+            .map(|(name, def)| OperatorInfo {
+                name: name.to_string(),
+                definition: Some(def.to_string()),
+                ..Default::default()
+            })
+            .collect();
+        catalog.by_category.insert("Test".to_string(), ops);
+        SignatureTable::from_catalog(&catalog)
+    }
+
+    fn codes(src: &str) -> Vec<&'static str> {
+        let program = parse(src).unwrap();
+        check(&program, &table()).iter().map(|e| e.code).collect()
+    }
+
+    #[test]
+    fn signature_from_definition() {
+        let sig = Signature::parse("add", "add(x, y, filter = false), x + y").unwrap();
+        assert_eq!(sig.positional, vec!["x", "y"]);
+        assert_eq!(sig.keywords, vec!["filter"]);
+        assert!(!sig.variadic);
+
+        let sig = Signature::parse("max", "max(x, y, ..)").unwrap();
+        assert_eq!(sig.positional, vec!["x", "y"]);
+        assert!(sig.variadic);
+
+        let sig = Signature::parse("ts_mean", "ts_mean (x, d)").unwrap();
+        assert!(sig.accepts_keyword("d"));
+        assert!(!sig.accepts_keyword("std"));
+
+        // 名称只作为更长标识符的一部分出现时不算
+        assert_eq!(Signature::parse("mean", "ts_mean(x, d)"), None);
+        assert_eq!(Signature::parse("if_else", "x ? y : z"), None);
+    }
+
+    #[test]
+    fn valid_calls_pass() {
+        for src in [
+            "ts_mean(close, 20)",
+            "ts_mean(close, d=20)",
+            "winsorize(close, std=4)",
+            "add(close, open, filter=true)",
+            "max(close, open, high, low)",
+            "rank(ts_mean(close, 5))",
+            "TS_MEAN(close, 5)",
+            "if_else(close > 0, close, open)",
+        ] {
+            assert!(codes(src).is_empty(), "{}: {:?}", src, codes(src));
+        }
+    }
+
+    #[test]
+    fn unknown_operator_and_keyword() {
+        assert_eq!(codes("rank(foo(close))"), vec!["unknown_operator"]);
+        assert_eq!(codes("winsorize(close, limit=4)"), vec!["unknown_keyword"]);
+    }
+
+    #[test]
+    fn duplicate_keyword() {
+        assert_eq!(codes("ts_mean(close, 20, d=5)"), vec!["duplicate_argument"]);
+    }
+
+    #[test]
+    fn too_few_and_too_many_args() {
+        assert_eq!(codes("ts_mean(close)"), vec!["arity"]);
+        assert_eq!(codes("ts_mean(close, 20, 3)"), vec!["arity"]);
+        assert_eq!(codes("max(close)"), vec!["arity"]);
+        assert_eq!(codes("add(close, filter=true)"), vec!["arity"]);
+    }
+
+    #[test]
+    fn non_positive_lookback() {
+        assert_eq!(codes("ts_mean(close, 0)"), vec!["bad_lookback"]);
+        assert_eq!(codes("ts_mean(close, 2.5)"), vec!["bad_lookback"]);
+        assert_eq!(codes("ts_mean(close, d=0)"), vec!["bad_lookback"]);
+    }
+
+    #[test]
+    fn empty_catalog_skips_checks() {
+        let program = parse("foo(close, 0)").unwrap();
+        assert!(check(&program, &SignatureTable::default()).is_empty());
+    }
+}
@@ -0,0 +1,190 @@
+//! 表达式结构复杂度：运算符个数、嵌套深度、不同字段数、不同运算符类别数、最大回看窗口。
+//!
+//! 运算符个数与平台的 operatorCount 口径一致：函数调用与中缀/一元/三元运算都计入。
+//! 类别按内置规则归类（`ts_` 时序、`group_` 分组、`vec_` 向量、常见截面/逻辑/算术函数），
+//! 不依赖运算符目录，保证离线入库与生成过滤使用同一口径。
+
+use crate::expr::ast::{BinaryOp, Expr, ExprKind, Stmt};
+use crate::expr::checker::LOOKBACK_PARAMS;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// 可用于筛选的复杂度指标名
+pub const COMPLEXITY_KEYS: [&str; 5] = ["operators", "depth", "fields", "categories", "lookback"];
+
+const CROSS_SECTIONAL: [&str; 8] = [
+    "rank",
+    "zscore",
+    "scale",
+    "normalize",
+    "quantile",
+    "winsorize",
+    "rank_by_side",
+    "regression_neut",
+];
+const LOGICAL: [&str; 12] = [
+    "if_else",
+    "and",
+    "or",
+    "not",
+    "is_nan",
+    "less",
+    "greater",
+    "equal",
+    "not_equal",
+    "less_equal",
+    "greater_equal",
+    "trade_when",
+];
+const ARITHMETIC: [&str; 17] = [
+    "add",
+    "subtract",
+    "multiply",
+    "divide",
+    "abs",
+    "log",
+    "sqrt",
+    "sign",
+    "power",
+    "signed_power",
+    "inverse",
+    "reverse",
+    "max",
+    "min",
+    "exp",
+    "densify",
+    "s_log_1p",
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Complexity {
+    pub operators: i32,
+    pub depth: i32,
+    pub fields: i32,
+    pub categories: i32,
+    /// 字面量回看窗口中的最大值；没有时序运算时为 None
+    pub lookback: Option<i32>,
+}
+
+impl Complexity {
+    /// 计算表达式复杂度；解析失败时返回 None
+    pub fn of(expr: &str) -> Option<Self> {
+        let program = crate::expr::parse(expr).ok()?;
+        let locals = program.assigned_names();
+        let mut c = Complexity::default();
+        let mut fields: HashSet<String> = HashSet::new();
+        let mut categories: HashSet<&'static str> = HashSet::new();
+        program.visit(&mut |e| match &e.kind {
+            ExprKind::Call { name, args } => {
+                c.operators += 1;
+                categories.insert(category(name));
+                if let Some(d) = call_lookback(name, args) {
+                    c.lookback = Some(c.lookback.map_or(d, |m| m.max(d)));
+                }
+            }
+            ExprKind::Binary { op, .. } => {
+                c.operators += 1;
+                categories.insert(binary_category(*op));
+            }
+            ExprKind::Unary { .. } => {
+                c.operators += 1;
+                categories.insert("Arithmetic");
+            }
+            ExprKind::Ternary { .. } => {
+                c.operators += 1;
+                categories.insert("Logical");
+            }
+            ExprKind::Ident(name) if !locals.contains(name) && !crate::expr::is_keyword(name) => {
+                fields.insert(name.clone());
+            }
+            _ => {}
+        });
+        for st in &program.statements {
+            let e = match st {
+                Stmt::Assign { value, .. } => value,
+                Stmt::Expr(e) => e,
+            };
+            c.depth = c.depth.max(depth(e));
+        }
+        c.fields = fields.len() as i32;
+        c.categories = categories.len() as i32;
+        Some(c)
+    }
+
+    /// 按 COMPLEXITY_KEYS 中的名称取值
+    pub fn get(&self, key: &str) -> Option<f64> {
+        match key {
+            "operators" => Some(self.operators as f64),
+            "depth" => Some(self.depth as f64),
+            "fields" => Some(self.fields as f64),
+            "categories" => Some(self.categories as f64),
+            "lookback" => self.lookback.map(|v| v as f64),
+            _ => None,
+        }
+    }
+}
+
+/// 运算节点的最大嵌套层数（叶子为 0）
+fn depth(e: &Expr) -> i32 {
+    match &e.kind {
+        ExprKind::Call { args, .. } => 1 + args.iter().map(|a| depth(&a.value)).max().unwrap_or(0),
+        ExprKind::Unary { operand, .. } => 1 + depth(operand),
+        ExprKind::Binary { lhs, rhs, .. } => 1 + depth(lhs).max(depth(rhs)),
+        ExprKind::Ternary {
+            cond,
+            then_branch,
+            else_branch,
+        } => 1 + depth(cond).max(depth(then_branch)).max(depth(else_branch)),
+        _ => 0,
+    }
+}
+
+fn category(name: &str) -> &'static str {
+    let n = name.to_ascii_lowercase();
+    if n.starts_with("ts_") {
+        "Time Series"
+    } else if n.starts_with("group_") {
+        "Group"
+    } else if n.starts_with("vec_") {
+        "Vector"
+    } else if CROSS_SECTIONAL.contains(&n.as_str()) {
+        "Cross Sectional"
+    } else if LOGICAL.contains(&n.as_str()) {
+        "Logical"
+    } else if ARITHMETIC.contains(&n.as_str()) {
+        "Arithmetic"
+    } else {
+        "Other"
+    }
+}
+
+fn binary_category(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Pow => {
+            "Arithmetic"
+        }
+        _ => "Logical",
+    }
+}
+
+/// 时序运算的回看窗口：命名参数 d/lookback/days，或 `ts_*` 的第二个位置参数
+fn call_lookback(name: &str, args: &[crate::expr::ast::Arg]) -> Option<i32> {
+    let named = args.iter().find(|a| {
+        a.name
+            .as_deref()
+            .is_some_and(|k| LOOKBACK_PARAMS.contains(&k))
+    });
+    let arg = match named {
+        Some(a) => a,
+        None if name.to_ascii_lowercase().starts_with("ts_") => {
+            args.iter().filter(|a| a.name.is_none()).nth(1)?
+        }
+        None => return None,
+    };
+    match &arg.value.kind {
+        ExprKind::Number { value, .. } if value.fract() == 0.0 && *value >= 1.0 => {
+            Some(*value as i32)
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,266 @@
+//! 表达式格式化：单行紧凑形式与按宽度折行的缩进树形式，仅用于详情页展示。
+//!
+//! 与 `canonical` 不同，这里保留原始的参数顺序与数值写法，只统一空白和多余括号；
+//! 入库与提交始终使用清洗后的原文。
+
+use crate::expr::ast::{Arg, BinaryOp, Expr, ExprKind, Program, Stmt};
+use crate::expr::parser::parse;
+
+const INDENT: &str = "  ";
+
+/// 单行紧凑形式；无法解析时压缩空白后原样返回
+pub fn compact(expr: &str) -> String {
+    match parse(expr) {
+        Ok(program) => render(&program),
+        Err(_) => expr.split_whitespace().collect::<Vec<_>>().join(" "),
+    }
+}
+
+/// 将语法树渲染为单行紧凑形式
+pub fn render(program: &Program) -> String {
+    program
+        .statements
+        .iter()
+        .map(stmt_flat)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// 缩进树形式：单行放得下（含缩进不超过 `width`）的子树保持一行，否则逐层展开
+pub fn pretty(expr: &str, width: usize) -> String {
+    let Ok(program) = parse(expr) else {
+        return compact(expr);
+    };
+    let width = width.max(20);
+    program
+        .statements
+        .iter()
+        .map(|st| match st {
+            Stmt::Assign { name, value, .. } => {
+                let prefix = format!("{} = ", name);
+                let flat = format!("{}{}", prefix, flat(value, 0));
+                if flat.chars().count() <= width {
+                    flat
+                } else {
+                    format!("{}{}", prefix, tree(value, 0, 0, width))
+                }
+            }
+            Stmt::Expr(e) => tree(e, 0, 0, width),
+        })
+        .collect::<Vec<_>>()
+        .join(";\n")
+}
+
+fn stmt_flat(st: &Stmt) -> String {
+    match st {
+        Stmt::Assign { name, value, .. } => format!("{} = {}", name, flat(value, 0)),
+        Stmt::Expr(e) => flat(e, 0),
+    }
+}
+
+fn arg_flat(a: &Arg) -> String {
+    match &a.name {
+        Some(n) => format!("{}={}", n, flat(&a.value, 0)),
+        None => flat(&a.value, 0),
+    }
+}
+
+/// 单行渲染：子表达式优先级低于 `min_prec` 时加括号
+fn flat(e: &Expr, min_prec: u8) -> String {
+    match &e.kind {
+        ExprKind::Number { raw, .. } => raw.clone(),
+        ExprKind::Str(s) | ExprKind::Ident(s) => s.clone(),
+        ExprKind::Call { name, args } => format!(
+            "{}({})",
+            name,
+            args.iter().map(arg_flat).collect::<Vec<_>>().join(", ")
+        ),
+        ExprKind::Unary { op, operand } => wrap(
+            format!(
+                "{}{}",
+                op.symbol(),
+                flat(operand, BinaryOp::Pow.precedence())
+            ),
+            BinaryOp::Pow.precedence(),
+            min_prec,
+        ),
+        ExprKind::Binary { op, lhs, rhs } => {
+            let (lp, rp) = operand_precs(*op);
+            wrap(
+                format!("{} {} {}", flat(lhs, lp), op.symbol(), flat(rhs, rp)),
+                op.precedence(),
+                min_prec,
+            )
+        }
+        ExprKind::Ternary {
+            cond,
+            then_branch,
+            else_branch,
+        } => wrap(
+            format!(
+                "{} ? {} : {}",
+                flat(cond, 1),
+                flat(then_branch, 0),
+                flat(else_branch, 0)
+            ),
+            0,
+            min_prec,
+        ),
+    }
+}
+
+/// 折行渲染；`indent` 为当前层级，返回串的首行不含缩进（由调用方放置）
+fn tree(e: &Expr, min_prec: u8, indent: usize, width: usize) -> String {
+    let one_line = flat(e, min_prec);
+    if indent * INDENT.len() + one_line.chars().count() <= width {
+        return one_line;
+    }
+    let pad = INDENT.repeat(indent);
+    let inner = INDENT.repeat(indent + 1);
+    match &e.kind {
+        ExprKind::Call { name, args } if !args.is_empty() => {
+            let parts: Vec<String> = args
+                .iter()
+                .map(|a| {
+                    let v = tree(&a.value, 0, indent + 1, width);
+                    match &a.name {
+                        Some(n) => format!("{}{}={}", inner, n, v),
+                        None => format!("{}{}", inner, v),
+                    }
+                })
+                .collect();
+            format!("{}(\n{}\n{})", name, parts.join(",\n"), pad)
+        }
+        ExprKind::Binary { op, lhs, rhs } => {
+            let (lp, rp) = operand_precs(*op);
+            let needs_paren = op.precedence() < min_prec;
+            let (lvl, open, close) = if needs_paren {
+                (indent + 1, format!("(\n{}", inner), format!("\n{})", pad))
+            } else {
+                (indent, String::new(), String::new())
+            };
+            let lvl_pad = INDENT.repeat(lvl);
+            format!(
+                "{}{}\n{}{} {}{}",
+                open,
+                tree(lhs, lp, lvl, width),
+                lvl_pad,
+                op.symbol(),
+                tree(rhs, rp, lvl, width),
+                close
+            )
+        }
+        ExprKind::Ternary {
+            cond,
+            then_branch,
+            else_branch,
+        } => {
+            let (lvl, open, close) = if min_prec > 0 {
+                (indent + 1, format!("(\n{}", inner), format!("\n{})", pad))
+            } else {
+                (indent, String::new(), String::new())
+            };
+            let branch_pad = INDENT.repeat(lvl + 1);
+            format!(
+                "{}{}\n{}? {}\n{}: {}{}",
+                open,
+                tree(cond, 1, lvl, width),
+                branch_pad,
+                tree(then_branch, 0, lvl + 1, width),
+                branch_pad,
+                tree(else_branch, 0, lvl + 1, width),
+                close
+            )
+        }
+        ExprKind::Unary { op, operand } => {
+            let s = format!(
+                "{}{}",
+                op.symbol(),
+                tree(operand, BinaryOp::Pow.precedence(), indent, width)
+            );
+            wrap(s, BinaryOp::Pow.precedence(), min_prec)
+        }
+        _ => one_line,
+    }
+}
+
+fn operand_precs(op: BinaryOp) -> (u8, u8) {
+    let prec = op.precedence();
+    if op.is_right_assoc() {
+        (prec + 1, prec)
+    } else {
+        (prec, prec + 1)
+    }
+}
+
+fn wrap(s: String, prec: u8, min_prec: u8) -> String {
+    if prec < min_prec {
+        format!("({})", s)
+    } else {
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::canonical_hash;
+
+    const CASES: [&str; 9] = [
+        "rank(ts_mean(close, 20))",
+        "winsorize(ts_zscore(close - open, d=60), std=4)",
+        "close > open ? rank(volume) : -rank(returns)",
+        "(a > b ? a : b) > 0 ? 1 : (c ? d : e)",
+        "-(close - open) / -ts_std_dev(close, 10)",
+        "-x ^ 2 + (-x) ^ 2 - 2 ^ -x",
+        "a - (b - c) - (d + e) * f / (g / h)",
+        "m = ts_delta(close, 5); s = ts_std_dev(m, 20); group_neutralize(m / s, subindustry)",
+        "trade_when(volume > adv20, -ts_corr(rank(close), rank(volume), lookback=10), -1)",
+    ];
+
+    #[test]
+    fn compact_round_trips_to_same_hash() {
+        for src in CASES {
+            let c = compact(src);
+            assert_eq!(canonical_hash(&c), canonical_hash(src), "{} -> {}", src, c);
+            assert_eq!(compact(&c), c, "not idempotent: {}", src);
+        }
+    }
+
+    #[test]
+    fn pretty_round_trips_at_any_width() {
+        for src in CASES {
+            for width in [20, 40, 80, 200] {
+                let p = pretty(src, width);
+                assert_eq!(
+                    canonical_hash(&p),
+                    canonical_hash(src),
+                    "{} @{}:\n{}",
+                    src,
+                    width,
+                    p
+                );
+                assert_eq!(compact(&p), compact(src), "{} @{}:\n{}", src, width, p);
+            }
+        }
+    }
+
+    #[test]
+    fn compact_normalizes_whitespace_and_redundant_parens() {
+        assert_eq!(compact("rank(  (close) )"), "rank(close)");
+        assert_eq!(compact("((a + b)) * c"), "(a + b) * c");
+        assert_eq!(compact("ts_mean(x,d = 5)"), "ts_mean(x, d=5)");
+        assert_eq!(compact("a - (b + c)"), "a - (b + c)");
+        // 无法解析时只压缩空白
+        assert_eq!(compact("rank(close  ,"), "rank(close ,");
+    }
+
+    #[test]
+    fn pretty_breaks_long_calls_and_keeps_short_ones() {
+        let src = "group_neutralize(ts_rank(ts_delta(close, 5), 20), subindustry)";
+        assert_eq!(pretty(src, 200), src);
+        let p = pretty(src, 30);
+        assert!(p.lines().count() > 1, "{}", p);
+        assert!(p.lines().all(|l| l.chars().count() <= 30), "{}", p);
+    }
+}
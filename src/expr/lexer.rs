@@ -0,0 +1,255 @@
+//! FASTEXPR 词法分析：把源码切成带位置（字节偏移）的 token。
+
+use crate::expr::parser::ParseError;
+
+/// 源码中的字节区间 [start, end)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// 覆盖两个区间的最小区间
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    Ident(String),
+    Number { value: f64, raw: String },
+    Str(String), // 含引号的原文
+    LParen,
+    RParen,
+    Comma,
+    Semicolon,
+    Question,
+    Colon,
+    Assign,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Caret,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    EqEq,
+    Ne,
+    AndAnd,
+    OrOr,
+    Bang,
+    Eof,
+}
+
+impl TokenKind {
+    /// 用于错误信息的简短描述
+    pub fn describe(&self) -> String {
+        match self {
+            TokenKind::Ident(s) => format!("标识符 `{}`", s),
+            TokenKind::Number { raw, .. } => format!("数字 `{}`", raw),
+            TokenKind::Str(s) => format!("字符串 {}", s),
+            TokenKind::Eof => "表达式结尾".to_string(),
+            other => format!("`{}`", other.symbol()),
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            TokenKind::LParen => "(",
+            TokenKind::RParen => ")",
+            TokenKind::Comma => ",",
+            TokenKind::Semicolon => ";",
+            TokenKind::Question => "?",
+            TokenKind::Colon => ":",
+            TokenKind::Assign => "=",
+            TokenKind::Plus => "+",
+            TokenKind::Minus => "-",
+            TokenKind::Star => "*",
+            TokenKind::Slash => "/",
+            TokenKind::Caret => "^",
+            TokenKind::Lt => "<",
+            TokenKind::Le => "<=",
+            TokenKind::Gt => ">",
+            TokenKind::Ge => ">=",
+            TokenKind::EqEq => "==",
+            TokenKind::Ne => "!=",
+            TokenKind::AndAnd => "&&",
+            TokenKind::OrOr => "||",
+            TokenKind::Bang => "!",
+            _ => "",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+/// 词法分析；结果总以 Eof 结尾
+pub fn tokenize(src: &str) -> Result<Vec<Token>, ParseError> {
+    let bytes = src.as_bytes();
+    let mut out = Vec::new();
+    let mut i = 0usize;
+    while i < bytes.len() {
+        let c = bytes[i];
+        if c.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        let kind = if c.is_ascii_alphabetic() || c == b'_' {
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                i += 1;
+            }
+            TokenKind::Ident(src[start..i].to_string())
+        } else if c.is_ascii_digit()
+            || (c == b'.' && bytes.get(i + 1).is_some_and(|n| n.is_ascii_digit()))
+        {
+            while i < bytes.len() && (bytes[i].is_ascii_digit() || bytes[i] == b'.') {
+                i += 1;
+            }
+            if i < bytes.len() && (bytes[i] == b'e' || bytes[i] == b'E') {
+                let mut j = i + 1;
+                if j < bytes.len() && (bytes[j] == b'+' || bytes[j] == b'-') {
+                    j += 1;
+                }
+                if j < bytes.len() && bytes[j].is_ascii_digit() {
+                    i = j;
+                    while i < bytes.len() && bytes[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            let raw = &src[start..i];
+            let value = raw.parse::<f64>().map_err(|_| {
+                ParseError::new(
+                    "invalid_number",
+                    format!("非法数字 `{}`", raw),
+                    Span::new(start, i),
+                )
+            })?;
+            TokenKind::Number {
+                value,
+                raw: raw.to_string(),
+            }
+        } else if c == b'"' || c == b'\'' {
+            i += 1;
+            while i < bytes.len() && bytes[i] != c {
+                i += 1;
+            }
+            if i >= bytes.len() {
+                return Err(ParseError::new(
+                    "unterminated_string",
+                    "字符串缺少结束引号",
+                    Span::new(start, bytes.len()),
+                ));
+            }
+            i += 1;
+            TokenKind::Str(src[start..i].to_string())
+        } else {
+            let next = bytes.get(i + 1).copied();
+            let (kind, len) = match (c, next) {
+                (b'<', Some(b'=')) => (TokenKind::Le, 2),
+                (b'>', Some(b'=')) => (TokenKind::Ge, 2),
+                (b'=', Some(b'=')) => (TokenKind::EqEq, 2),
+                (b'!', Some(b'=')) => (TokenKind::Ne, 2),
+                (b'&', Some(b'&')) => (TokenKind::AndAnd, 2),
+                (b'|', Some(b'|')) => (TokenKind::OrOr, 2),
+                (b'(', _) => (TokenKind::LParen, 1),
+                (b')', _) => (TokenKind::RParen, 1),
+                (b',', _) => (TokenKind::Comma, 1),
+                (b';', _) => (TokenKind::Semicolon, 1),
+                (b'?', _) => (TokenKind::Question, 1),
+                (b':', _) => (TokenKind::Colon, 1),
+                (b'=', _) => (TokenKind::Assign, 1),
+                (b'+', _) => (TokenKind::Plus, 1),
+                (b'-', _) => (TokenKind::Minus, 1),
+                (b'*', _) => (TokenKind::Star, 1),
+                (b'/', _) => (TokenKind::Slash, 1),
+                (b'^', _) => (TokenKind::Caret, 1),
+                (b'<', _) => (TokenKind::Lt, 1),
+                (b'>', _) => (TokenKind::Gt, 1),
+                (b'!', _) => (TokenKind::Bang, 1),
+                _ => {
+                    let ch = src[start..].chars().next().unwrap_or('?');
+                    return Err(ParseError::new(
+                        "invalid_char",
+                        format!("非法字符 `{}`", ch),
+                        Span::new(start, start + ch.len_utf8()),
+                    ));
+                }
+            };
+            i += len;
+            kind
+        };
+        out.push(Token {
+            kind,
+            span: Span::new(start, i),
+        });
+    }
+    out.push(Token {
+        kind: TokenKind::Eof,
+        span: Span::new(bytes.len(), bytes.len()),
+    });
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(src: &str) -> Vec<TokenKind> {
+        tokenize(src).unwrap().into_iter().map(|t| t.kind).collect()
+    }
+
+    #[test]
+    fn two_char_operators_and_spans() {
+        let tokens = tokenize("a<=b != c").unwrap();
+        let spans: Vec<(usize, usize)> =
+            tokens.iter().map(|t| (t.span.start, t.span.end)).collect();
+        assert_eq!(spans, vec![(0, 1), (1, 3), (3, 4), (5, 7), (8, 9), (9, 9)]);
+        assert_eq!(tokens[1].kind, TokenKind::Le);
+        assert_eq!(tokens[3].kind, TokenKind::Ne);
+        assert_eq!(tokens.last().unwrap().kind, TokenKind::Eof);
+    }
+
+    #[test]
+    fn numbers_keep_raw_text() {
+        let k = kinds("10 .5 1e-3 2.0");
+        let nums: Vec<(f64, &str)> = k
+            .iter()
+            .filter_map(|t| match t {
+                TokenKind::Number { value, raw } => Some((*value, raw.as_str())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            nums,
+            vec![(10.0, "10"), (0.5, ".5"), (0.001, "1e-3"), (2.0, "2.0")]
+        );
+        assert_eq!(tokenize("1.2.3").unwrap_err().code, "invalid_number");
+    }
+
+    #[test]
+    fn strings_and_invalid_chars() {
+        assert_eq!(kinds("'USA'")[0], TokenKind::Str("'USA'".to_string()));
+        let e = tokenize("f(\"abc").unwrap_err();
+        assert_eq!(e.code, "unterminated_string");
+        assert_eq!(e.span, Span::new(2, 6));
+        let e = tokenize("a # b").unwrap_err();
+        assert_eq!(e.code, "invalid_char");
+        assert_eq!(e.span, Span::new(2, 3));
+        let e = tokenize("a ＋ b").unwrap_err();
+        assert_eq!(e.span, Span::new(2, 5));
+    }
+}
@@ -0,0 +1,70 @@
+pub mod ast;
+pub mod canonical;
+pub mod checker;
+pub mod complexity;
+pub mod format;
+pub mod lexer;
+pub mod parser;
+pub mod similarity;
+
+pub use canonical::canonical_hash;
+pub use parser::parse;
+
+use lexer::{tokenize, TokenKind};
+
+/// 语言内置的常量与分组名，出现在表达式中时不视为数据字段
+pub const KEYWORDS: [&str; 12] = [
+    "true",
+    "false",
+    "nan",
+    "NaN",
+    "inf",
+    "market",
+    "sector",
+    "industry",
+    "subindustry",
+    "country",
+    "exchange",
+    "currency",
+];
+
+/// 是否为内置关键字（大小写不敏感）
+pub fn is_keyword(name: &str) -> bool {
+    KEYWORDS.iter().any(|k| k.eq_ignore_ascii_case(name))
+}
+
+/// 表达式中调用的运算符（按出现顺序，可能重复）。
+/// 解析失败时退化为 token 扫描：标识符后紧跟 `(` 即视为调用。
+pub fn operators(expr: &str) -> Vec<String> {
+    if let Ok(program) = parse(expr) {
+        return program.operators();
+    }
+    let Ok(tokens) = tokenize(expr) else {
+        return Vec::new();
+    };
+    tokens
+        .windows(2)
+        .filter_map(|w| match (&w[0].kind, &w[1].kind) {
+            (TokenKind::Ident(name), TokenKind::LParen) => Some(name.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// 表达式中引用的标识符（数据字段候选，不含函数名、命名参数名与局部变量）。
+/// 解析失败时退化为按字母数字切分的全部单词，由调用方再与字段表比对。
+pub fn identifiers(expr: &str) -> Vec<String> {
+    if let Ok(program) = parse(expr) {
+        return program.identifiers();
+    }
+    let mut out = Vec::new();
+    let mut cur = String::new();
+    for ch in expr.chars().chain(std::iter::once(' ')) {
+        if ch.is_ascii_alphanumeric() || ch == '_' {
+            cur.push(ch);
+        } else if !cur.is_empty() {
+            out.push(std::mem::take(&mut cur));
+        }
+    }
+    out
+}
@@ -0,0 +1,505 @@
+//! FASTEXPR 语法分析（Pratt 解析）。
+//!
+//! 优先级由低到高：`?:` < `||` < `&&` < `== !=` < `< <= > >=` < `+ -` < `* /` < `^` < 一元 `- ! +`
+
+use crate::expr::ast::{Arg, BinaryOp, Expr, ExprKind, Program, Stmt, UnaryOp};
+use crate::expr::lexer::{tokenize, Span, Token, TokenKind};
+use std::fmt;
+
+/// 带位置的解析/检查错误；`code` 为稳定的原因码，便于统计与映射提示文案
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub code: &'static str,
+    pub message: String,
+    pub span: Span,
+}
+
+impl ParseError {
+    pub fn new(code: &'static str, message: impl Into<String>, span: Span) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            span,
+        }
+    }
+
+    /// 出错位置附近的原文，用 ⟨here⟩ 标出位置
+    pub fn location(&self, src: &str) -> String {
+        let start = floor_char_boundary(src, self.span.start.saturating_sub(12));
+        let end = floor_char_boundary(src, (self.span.end + 12).min(src.len()));
+        let at = floor_char_boundary(src, self.span.start.min(src.len()));
+        format!(
+            "第 {} 字节: `{}⟨here⟩{}`",
+            self.span.start,
+            &src[start..at],
+            &src[at..end]
+        )
+    }
+
+    /// 单行描述：错误信息 + 出错位置
+    pub fn describe(&self, src: &str) -> String {
+        format!("{}（{}）", self.message, self.location(src))
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} [{}..{}]",
+            self.message, self.span.start, self.span.end
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn floor_char_boundary(s: &str, mut i: usize) -> usize {
+    while i > 0 && !s.is_char_boundary(i) {
+        i -= 1;
+    }
+    i
+}
+
+/// 解析完整的（可能含多条语句的）表达式
+pub fn parse(src: &str) -> Result<Program, ParseError> {
+    let tokens = tokenize(src)?;
+    let mut p = Parser { tokens, pos: 0 };
+    p.parse_program()
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos.min(self.tokens.len() - 1)]
+    }
+
+    fn peek_kind_at(&self, offset: usize) -> &TokenKind {
+        let i = (self.pos + offset).min(self.tokens.len() - 1);
+        &self.tokens[i].kind
+    }
+
+    fn bump(&mut self) -> Token {
+        let t = self.peek().clone();
+        if self.pos < self.tokens.len() - 1 {
+            self.pos += 1;
+        }
+        t
+    }
+
+    fn eat(&mut self, kind: &TokenKind) -> Option<Token> {
+        if &self.peek().kind == kind {
+            Some(self.bump())
+        } else {
+            None
+        }
+    }
+
+    /// 针对当前 token 生成“意外 token”错误，尽量给出具体原因码
+    fn unexpected(&self, expected: &str) -> ParseError {
+        let tok = self.peek();
+        let prev = self.pos.checked_sub(1).map(|i| &self.tokens[i].kind);
+        let (code, message) = match (&tok.kind, prev) {
+            (TokenKind::LParen, Some(TokenKind::RParen)) => (
+                "unexpected_right_paren",
+                "右括号后紧跟左括号（形如 ...)(...）".to_string(),
+            ),
+            (TokenKind::RParen, _) => ("unbalanced_parens", "多余的右括号".to_string()),
+            (TokenKind::Eof, _) => (
+                "unexpected_eof",
+                format!("表达式意外结束，期望{}", expected),
+            ),
+            (other, _) => (
+                "unexpected_token",
+                format!("意外的{}，期望{}", other.describe(), expected),
+            ),
+        };
+        ParseError::new(code, message, tok.span)
+    }
+
+    fn parse_program(&mut self) -> Result<Program, ParseError> {
+        let mut statements = Vec::new();
+        loop {
+            while self.eat(&TokenKind::Semicolon).is_some() {}
+            if self.peek().kind == TokenKind::Eof {
+                break;
+            }
+            statements.push(self.parse_statement()?);
+            match self.peek().kind {
+                TokenKind::Semicolon | TokenKind::Eof => {}
+                _ => return Err(self.unexpected(" `;` 或表达式结尾")),
+            }
+        }
+        match statements.last() {
+            None => Err(ParseError::new(
+                "empty_expression",
+                "表达式为空",
+                self.peek().span,
+            )),
+            Some(Stmt::Assign { span, .. }) => Err(ParseError::new(
+                "missing_result",
+                "最后一条语句必须是表达式而不是赋值",
+                *span,
+            )),
+            Some(Stmt::Expr(_)) => Ok(Program { statements }),
+        }
+    }
+
+    fn parse_statement(&mut self) -> Result<Stmt, ParseError> {
+        if let (TokenKind::Ident(name), TokenKind::Assign) =
+            (self.peek().kind.clone(), self.peek_kind_at(1))
+        {
+            let start = self.bump().span;
+            self.bump();
+            let value = self.parse_expr()?;
+            let span = start.to(value.span);
+            return Ok(Stmt::Assign { name, value, span });
+        }
+        Ok(Stmt::Expr(self.parse_expr()?))
+    }
+
+    fn parse_expr(&mut self) -> Result<Expr, ParseError> {
+        let cond = self.parse_binary(1)?;
+        if self.eat(&TokenKind::Question).is_none() {
+            return Ok(cond);
+        }
+        let then_branch = self.parse_expr()?;
+        if self.eat(&TokenKind::Colon).is_none() {
+            return Err(self.unexpected(" `:`"));
+        }
+        let else_branch = self.parse_expr()?;
+        let span = cond.span.to(else_branch.span);
+        Ok(Expr {
+            kind: ExprKind::Ternary {
+                cond: Box::new(cond),
+                then_branch: Box::new(then_branch),
+                else_branch: Box::new(else_branch),
+            },
+            span,
+        })
+    }
+
+    fn peek_binary_op(&self) -> Option<BinaryOp> {
+        Some(match self.peek().kind {
+            TokenKind::Plus => BinaryOp::Add,
+            TokenKind::Minus => BinaryOp::Sub,
+            TokenKind::Star => BinaryOp::Mul,
+            TokenKind::Slash => BinaryOp::Div,
+            TokenKind::Caret => BinaryOp::Pow,
+            TokenKind::Lt => BinaryOp::Lt,
+            TokenKind::Le => BinaryOp::Le,
+            TokenKind::Gt => BinaryOp::Gt,
+            TokenKind::Ge => BinaryOp::Ge,
+            TokenKind::EqEq => BinaryOp::Eq,
+            TokenKind::Ne => BinaryOp::Ne,
+            TokenKind::AndAnd => BinaryOp::And,
+            TokenKind::OrOr => BinaryOp::Or,
+            _ => return None,
+        })
+    }
+
+    fn parse_binary(&mut self, min_prec: u8) -> Result<Expr, ParseError> {
+        let mut lhs = self.parse_unary()?;
+        while let Some(op) = self.peek_binary_op() {
+            let prec = op.precedence();
+            if prec < min_prec {
+                break;
+            }
+            self.bump();
+            let next_min = if op.is_right_assoc() { prec } else { prec + 1 };
+            let rhs = self.parse_binary(next_min)?;
+            let span = lhs.span.to(rhs.span);
+            lhs = Expr {
+                kind: ExprKind::Binary {
+                    op,
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                },
+                span,
+            };
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ParseError> {
+        let op = match self.peek().kind {
+            TokenKind::Minus => Some(UnaryOp::Neg),
+            TokenKind::Bang => Some(UnaryOp::Not),
+            TokenKind::Plus => None,
+            _ => return self.parse_primary(),
+        };
+        let start = self.bump().span;
+        // 一元运算绑定比 `^` 松：-x^2 == -(x^2)
+        let operand = self.parse_binary(BinaryOp::Pow.precedence())?;
+        let Some(op) = op else {
+            return Ok(operand);
+        };
+        let span = start.to(operand.span);
+        Ok(Expr {
+            kind: ExprKind::Unary {
+                op,
+                operand: Box::new(operand),
+            },
+            span,
+        })
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ParseError> {
+        let tok = self.peek().clone();
+        match tok.kind {
+            TokenKind::Number { value, raw } => {
+                self.bump();
+                Ok(Expr {
+                    kind: ExprKind::Number { value, raw },
+                    span: tok.span,
+                })
+            }
+            TokenKind::Str(s) => {
+                self.bump();
+                Ok(Expr {
+                    kind: ExprKind::Str(s),
+                    span: tok.span,
+                })
+            }
+            TokenKind::Ident(name) => {
+                self.bump();
+                if self.peek().kind == TokenKind::LParen {
+                    self.bump();
+                    let (args, close) = self.parse_args(tok.span)?;
+                    return Ok(Expr {
+                        kind: ExprKind::Call { name, args },
+                        span: tok.span.to(close),
+                    });
+                }
+                Ok(Expr {
+                    kind: ExprKind::Ident(name),
+                    span: tok.span,
+                })
+            }
+            TokenKind::LParen => {
+                self.bump();
+                let mut inner = self.parse_expr()?;
+                match self.eat(&TokenKind::RParen) {
+                    Some(close) => {
+                        inner.span = tok.span.to(close.span);
+                        Ok(inner)
+                    }
+                    None if self.peek().kind == TokenKind::Eof => {
+                        Err(ParseError::new("unbalanced_parens", "括号未闭合", tok.span))
+                    }
+                    None => Err(self.unexpected(" `)`")),
+                }
+            }
+            TokenKind::RParen | TokenKind::Comma => Err(ParseError::new(
+                "missing_operand",
+                format!("{} 前缺少操作数", tok.kind.describe()),
+                tok.span,
+            )),
+            _ => Err(self.unexpected("操作数")),
+        }
+    }
+
+    /// 解析 `(` 之后的参数列表，返回参数和右括号位置
+    fn parse_args(&mut self, open: Span) -> Result<(Vec<Arg>, Span), ParseError> {
+        let mut args = Vec::new();
+        if let Some(close) = self.eat(&TokenKind::RParen) {
+            return Ok((args, close.span));
+        }
+        loop {
+            let named = match (self.peek().kind.clone(), self.peek_kind_at(1)) {
+                (TokenKind::Ident(n), TokenKind::Assign) => {
+                    let start = self.bump().span;
+                    self.bump();
+                    Some((n, start))
+                }
+                _ => None,
+            };
+            let value = self.parse_expr()?;
+            let (name, span) = match named {
+                Some((n, start)) => (Some(n), start.to(value.span)),
+                None => (None, value.span),
+            };
+            args.push(Arg { name, value, span });
+
+            match self.peek().kind {
+                TokenKind::Comma => {
+                    let comma = self.bump();
+                    if self.peek().kind == TokenKind::RParen {
+                        return Err(ParseError::new(
+                            "trailing_comma",
+                            "参数列表存在拖尾逗号（形如 ...,)）",
+                            comma.span,
+                        ));
+                    }
+                }
+                TokenKind::RParen => {
+                    let close = self.bump();
+                    return Ok((args, close.span));
+                }
+                TokenKind::Eof => {
+                    return Err(ParseError::new(
+                        "unbalanced_parens",
+                        "函数调用的括号未闭合",
+                        open,
+                    ))
+                }
+                _ => return Err(self.unexpected(" `,` 或 `)`")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 以全括号前缀形式输出，便于断言结合方式
+    fn sexp(e: &Expr) -> String {
+        match &e.kind {
+            ExprKind::Number { raw, .. } => raw.clone(),
+            ExprKind::Str(s) => s.clone(),
+            ExprKind::Ident(n) => n.clone(),
+            ExprKind::Call { name, args } => {
+                let args: Vec<String> = args
+                    .iter()
+                    .map(|a| match &a.name {
+                        Some(n) => format!("{}={}", n, sexp(&a.value)),
+                        None => sexp(&a.value),
+                    })
+                    .collect();
+                format!("{}({})", name, args.join(" "))
+            }
+            ExprKind::Unary { op, operand } => format!("({} {})", op.symbol(), sexp(operand)),
+            ExprKind::Binary { op, lhs, rhs } => {
+                format!("({} {} {})", op.symbol(), sexp(lhs), sexp(rhs))
+            }
+            ExprKind::Ternary {
+                cond,
+                then_branch,
+                else_branch,
+            } => format!(
+                "(? {} {} {})",
+                sexp(cond),
+                sexp(then_branch),
+                sexp(else_branch)
+            ),
+        }
+    }
+
+    fn last(src: &str) -> String {
+        let program = parse(src).unwrap();
+        match program.statements.last().unwrap() {
+            Stmt::Expr(e) => sexp(e),
+            Stmt::Assign { .. } => unreachable!(),
+        }
+    }
+
+    fn err_code(src: &str) -> &'static str {
+        parse(src).unwrap_err().code
+    }
+
+    #[test]
+    fn binary_precedence_and_associativity() {
+        assert_eq!(last("a + b * c"), "(+ a (* b c))");
+        assert_eq!(last("a - b - c"), "(- (- a b) c)");
+        assert_eq!(last("a ^ b ^ c"), "(^ a (^ b c))");
+        assert_eq!(last("a < b && c || d"), "(|| (&& (< a b) c) d)");
+        assert_eq!(last("a == b + 1"), "(== a (+ b 1))");
+        assert_eq!(last("(a + b) * c"), "(* (+ a b) c)");
+    }
+
+    #[test]
+    fn unary_minus_binds_looser_than_pow() {
+        assert_eq!(last("-x ^ 2"), "(- (^ x 2))");
+        assert_eq!(last("-a * b"), "(* (- a) b)");
+        assert_eq!(last("a - -b"), "(- a (- b))");
+        assert_eq!(last("+a"), "a");
+        assert_eq!(last("!a && b"), "(&& (! a) b)");
+    }
+
+    #[test]
+    fn ternary_is_lowest_and_right_nested() {
+        assert_eq!(last("a > 0 ? b + 1 : c"), "(? (> a 0) (+ b 1) c)");
+        assert_eq!(last("a ? b : c ? d : e"), "(? a b (? c d e))");
+        assert_eq!(err_code("a ? b"), "unexpected_eof");
+    }
+
+    #[test]
+    fn calls_with_named_args() {
+        assert_eq!(
+            last("ts_rank(close, 20, rettype=1)"),
+            "ts_rank(close 20 rettype=1)"
+        );
+        assert_eq!(
+            last("group_neutralize(rank(x), densify(industry))"),
+            "group_neutralize(rank(x) densify(industry))"
+        );
+        assert_eq!(last("f()"), "f()");
+    }
+
+    #[test]
+    fn statements_and_assignments() {
+        let program = parse("a = rank(close); b = a * 2;; b - a").unwrap();
+        assert_eq!(program.statements.len(), 3);
+        assert!(matches!(&program.statements[0], Stmt::Assign { name, .. } if name == "a"));
+        assert!(matches!(&program.statements[1], Stmt::Assign { name, .. } if name == "b"));
+        assert_eq!(program.assigned_names(), vec!["a", "b"]);
+        assert_eq!(last("a = 1; a;"), "a");
+        assert_eq!(err_code("a = 1"), "missing_result");
+        assert_eq!(err_code(";;"), "empty_expression");
+        assert_eq!(err_code(""), "empty_expression");
+    }
+
+    #[test]
+    fn error_codes_and_spans() {
+        let e = parse("rank(close").unwrap_err();
+        assert_eq!(e.code, "unbalanced_parens");
+        assert_eq!(e.span, Span::new(0, 4));
+
+        let e = parse("(a + b").unwrap_err();
+        assert_eq!(e.code, "unbalanced_parens");
+        assert_eq!(e.span, Span::new(0, 1));
+
+        let e = parse("rank(x))").unwrap_err();
+        assert_eq!(e.code, "unbalanced_parens");
+        assert_eq!(e.span, Span::new(7, 8));
+
+        let e = parse("f(a, )").unwrap_err();
+        assert_eq!(e.code, "trailing_comma");
+        assert_eq!(e.span, Span::new(3, 4));
+
+        let e = parse("f(a)(b)").unwrap_err();
+        assert_eq!(e.code, "unexpected_right_paren");
+        assert_eq!(e.span, Span::new(4, 5));
+
+        assert_eq!(err_code("f(, a)"), "missing_operand");
+        assert_eq!(err_code("a b"), "unexpected_token");
+        assert_eq!(err_code("a +"), "unexpected_eof");
+    }
+
+    #[test]
+    fn expr_spans_cover_source() {
+        let src = "rank(a) + ts_mean(b, 5)";
+        let program = parse(src).unwrap();
+        let Stmt::Expr(e) = &program.statements[0] else {
+            panic!("expected expression");
+        };
+        assert_eq!(e.span, Span::new(0, src.len()));
+        let ExprKind::Binary { lhs, rhs, .. } = &e.kind else {
+            panic!("expected binary");
+        };
+        assert_eq!(&src[lhs.span.start..lhs.span.end], "rank(a)");
+        assert_eq!(&src[rhs.span.start..rhs.span.end], "ts_mean(b, 5)");
+    }
+
+    #[test]
+    fn location_marks_error_position() {
+        let src = "rank(close, )";
+        let e = parse(src).unwrap_err();
+        assert_eq!(e.location(src), "第 10 字节: `rank(close⟨here⟩, )`");
+    }
+}
@@ -0,0 +1,124 @@
+//! 表达式结构相似度：语法树节点标签与父子/祖孙路径组成的 shingle 集合之间的 Jaccard 系数。
+//!
+//! 数值字面量统一记为 `#`，因此只差回看窗口或常数的表达式相似度为 1；
+//! 字段与运算符保留名称，替换一个字段只会影响包含它的少数 shingle。
+
+use crate::expr::ast::{Expr, ExprKind, Program, Stmt};
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Shingles(HashSet<String>);
+
+impl Shingles {
+    /// 解析失败时返回 None
+    pub fn of(expr: &str) -> Option<Self> {
+        crate::expr::parse(expr)
+            .ok()
+            .map(|p| Self::from_program(&p))
+    }
+
+    pub fn from_program(program: &Program) -> Self {
+        let locals = program.assigned_names();
+        let mut set = HashSet::new();
+        for st in &program.statements {
+            match st {
+                Stmt::Assign { value, .. } => collect(value, None, &["="], &locals, &mut set),
+                Stmt::Expr(e) => collect(e, None, &[], &locals, &mut set),
+            }
+        }
+        Self(set)
+    }
+
+    pub fn jaccard(&self, other: &Shingles) -> f64 {
+        if self.0.is_empty() && other.0.is_empty() {
+            return 1.0;
+        }
+        let inter = self.0.intersection(&other.0).count();
+        let union = self.0.len() + other.0.len() - inter;
+        inter as f64 / union as f64
+    }
+}
+
+/// 与 `target` 结构最相似的前 `k` 条（相似度降序，排除与其规范化相同的表达式）
+pub fn most_similar(target: &str, pool: &[String], k: usize) -> Vec<(String, f64)> {
+    let Some(sh) = Shingles::of(target) else {
+        return Vec::new();
+    };
+    let own = crate::expr::canonical_hash(target);
+    let mut scored: Vec<(String, f64)> = pool
+        .iter()
+        .filter(|e| crate::expr::canonical_hash(e) != own)
+        .filter_map(|e| Shingles::of(e).map(|s| (e.clone(), sh.jaccard(&s))))
+        .filter(|(_, sim)| *sim > 0.0)
+        .collect();
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scored.truncate(k);
+    scored
+}
+
+fn label(e: &Expr, locals: &[String]) -> String {
+    match &e.kind {
+        ExprKind::Number { .. } => "#".to_string(),
+        ExprKind::Str(s) => format!("\"{}\"", s),
+        ExprKind::Ident(n) if locals.contains(n) => "$".to_string(),
+        ExprKind::Ident(n) => n.to_ascii_lowercase(),
+        ExprKind::Call { name, .. } => format!("{}()", name.to_ascii_lowercase()),
+        ExprKind::Unary { op, .. } => format!("u{}", op.symbol()),
+        ExprKind::Binary { op, .. } => op.symbol().to_string(),
+        ExprKind::Ternary { .. } => "?:".to_string(),
+    }
+}
+
+/// `keyword` 为该节点作为命名参数时的参数名；`ancestors` 为最近的两层祖先标签
+fn collect(
+    e: &Expr,
+    keyword: Option<&str>,
+    ancestors: &[&str],
+    locals: &[String],
+    out: &mut HashSet<String>,
+) {
+    let own = match keyword {
+        Some(k) => format!("{}={}", k, label(e, locals)),
+        None => label(e, locals),
+    };
+    out.insert(own.clone());
+    if let Some(parent) = ancestors.last() {
+        out.insert(format!("{}>{}", parent, own));
+        if ancestors.len() >= 2 {
+            out.insert(format!(
+                "{}>{}>{}",
+                ancestors[ancestors.len() - 2],
+                parent,
+                own
+            ));
+        }
+    }
+    let path: Vec<&str> = ancestors
+        .last()
+        .into_iter()
+        .copied()
+        .chain(std::iter::once(own.as_str()))
+        .collect();
+    match &e.kind {
+        ExprKind::Call { args, .. } => {
+            for a in args {
+                collect(&a.value, a.name.as_deref(), &path, locals, out);
+            }
+        }
+        ExprKind::Unary { operand, .. } => collect(operand, None, &path, locals, out),
+        ExprKind::Binary { lhs, rhs, .. } => {
+            collect(lhs, None, &path, locals, out);
+            collect(rhs, None, &path, locals, out);
+        }
+        ExprKind::Ternary {
+            cond,
+            then_branch,
+            else_branch,
+        } => {
+            collect(cond, None, &path, locals, out);
+            collect(then_branch, None, &path, locals, out);
+            collect(else_branch, None, &path, locals, out);
+        }
+        _ => {}
+    }
+}
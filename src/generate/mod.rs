@@ -3,5 +3,9 @@ pub mod field_sync;
 pub mod parser;
 pub mod prompt;
 pub mod service;
+pub mod template;
 
-pub use service::{GenerateConfig, GenerateResult, GeneratorService};
+pub use service::{
+    ComplexityLimits, FeedbackConfig, GenerateConfig, GenerateResult, GeneratorService,
+    NearDuplicateFilter, RepairConfig,
+};
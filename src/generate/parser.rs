@@ -1,107 +1,159 @@
+use crate::expr::ast::ExprKind;
+use crate::expr::checker::SignatureTable;
+use crate::expr::lexer::{tokenize, TokenKind};
+use crate::storage::repository::data_field_repo::EventOpValidationErr;
+use crate::storage::repository::DataFieldRepository;
 use regex::Regex;
+use sea_orm::DatabaseConnection;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
 
 pub struct ParsedResult {
     pub exprs: Vec<String>,
     pub total_lines: usize,
     pub rejected_examples: Vec<String>,
+    /// 值得交回 LLM 修复的被拒候选：(表达式, 原因)；不含过短/无括号的说明性文字
+    pub repairable: Vec<(String, String)>,
+    /// 结构化输出中模型给出的假设，按入库表达式索引；行模式下为空
+    pub hypotheses: HashMap<String, String>,
 }
 
-pub fn validate_prequeue(expr: &str) -> Result<(), String> {
-    let s = expr.trim();
-    {
-        let bytes = s.as_bytes();
-        let mut i = 0usize;
-        while i + 1 < bytes.len() {
-            if bytes[i] == b')' {
-                let mut j = i + 1;
-                while j < bytes.len() && bytes[j].is_ascii_whitespace() {
-                    j += 1;
-                }
-                if j < bytes.len() && bytes[j] == b'(' {
-                    return Err("unexpected_right_paren".to_string());
-                }
-            }
-            i += 1;
+/// 入队前校验失败的原因；`code` 为稳定原因码，`message()` 给出统一的提示文案
+#[derive(Debug, Clone)]
+pub struct PrequeueRejection {
+    pub code: &'static str,
+    pub detail: Option<String>,
+}
+
+impl PrequeueRejection {
+    pub fn message(&self) -> String {
+        let base = match self.code {
+            "unexpected_right_paren" => "预提交校验失败：存在意外右括号（形如 ...)(...）",
+            "trailing_comma" => "预提交校验失败：存在拖尾逗号（形如 ...,)）",
+            "winsorize_arity" => "预提交校验失败：winsorize 仅接受 1 个输入参数",
+            "unbalanced_parens" => "预提交校验失败：括号不匹配",
+            "unknown_operator" => "预提交校验失败：存在未知运算符",
+            "arity" => "预提交校验失败：参数个数与运算符签名不符",
+            "unknown_keyword" => "预提交校验失败：存在签名中没有的命名参数",
+            "duplicate_argument" => "预提交校验失败：参数重复传入",
+            "bad_lookback" => "预提交校验失败：回看窗口必须是正整数",
+            "unknown_field" => "预提交校验失败：存在目标范围内不可用的字段",
+            "event_operator" => "预提交校验失败：事件字段与不兼容运算符组合",
+            "field_lookup" => "预提交校验失败：读取字段目录出错",
+            _ => "预提交校验失败：表达式语法错误",
+        };
+        match &self.detail {
+            Some(d) => format!("{}：{}", base, d),
+            None => base.to_string(),
         }
     }
-    {
-        let bytes = s.as_bytes();
-        let mut depth = 0i32;
-        let mut i = 0usize;
-        while i < bytes.len() {
-            let ch = bytes[i];
-            if ch == b'(' {
-                depth += 1;
-            } else if ch == b')' {
-                let mut k = i;
-                while k > 0 && bytes[k - 1].is_ascii_whitespace() {
-                    k -= 1;
-                }
-                if k > 0 && bytes[k - 1] == b',' {
-                    return Err("trailing_comma".to_string());
-                }
-                depth -= 1;
+}
+
+pub fn validate_prequeue(expr: &str) -> Result<(), PrequeueRejection> {
+    let s = expr.trim();
+    let program = crate::expr::parse(s).map_err(|e| {
+        // 已有专门文案的原因码只补充位置，其余附带完整解析错误
+        let detail = match e.code {
+            "unexpected_right_paren" | "trailing_comma" => e.location(s),
+            _ => e.describe(s),
+        };
+        PrequeueRejection {
+            code: e.code,
+            detail: Some(detail),
+        }
+    })?;
+
+    let mut winsorize_bad = false;
+    program.visit(&mut |e| {
+        if let ExprKind::Call { name, args } = &e.kind {
+            if name.eq_ignore_ascii_case("winsorize")
+                && args.iter().filter(|a| a.name.is_none()).count() > 1
+            {
+                winsorize_bad = true;
             }
-            i += 1;
         }
+    });
+    if winsorize_bad {
+        return Err(PrequeueRejection {
+            code: "winsorize_arity",
+            detail: None,
+        });
+    }
+    Ok(())
+}
+
+/// 在 `validate_prequeue` 基础上按运算符目录签名检查每个调用；目录为空时等价于前者
+pub fn validate_with_catalog(expr: &str, table: &SignatureTable) -> Result<(), PrequeueRejection> {
+    validate_prequeue(expr)?;
+    let s = expr.trim();
+    let Ok(program) = crate::expr::parse(s) else {
+        return Ok(());
+    };
+    let diags = crate::expr::checker::check(&program, table);
+    let Some(first) = diags.first() else {
+        return Ok(());
+    };
+    let detail = diags
+        .iter()
+        .take(3)
+        .map(|d| d.describe(s))
+        .collect::<Vec<_>>()
+        .join("；");
+    Err(PrequeueRejection {
+        code: first.code,
+        detail: Some(detail),
+    })
+}
+
+/// 完整的入队前校验：语法、运算符签名、目标 region/universe/delay 下的字段存在性、事件字段运算符兼容性
+pub async fn validate_for_scope(
+    db: &DatabaseConnection,
+    expr: &str,
+    table: &SignatureTable,
+    region: &str,
+    universe: &str,
+    delay: i32,
+) -> Result<(), PrequeueRejection> {
+    validate_with_catalog(expr, table)?;
+    let s = expr.trim();
+    // 查询出错时拒绝而不是当作字段都存在，避免未校验的表达式入队
+    let unknown: Vec<String> =
+        DataFieldRepository::unknown_fields_in_scope(db, s, region, universe, delay)
+            .await
+            .map_err(|e| PrequeueRejection {
+                code: "field_lookup",
+                detail: Some(e.to_string()),
+            })?
+            .into_iter()
+            .filter(|name| !table.contains(name))
+            .collect();
+    if !unknown.is_empty() {
+        return Err(PrequeueRejection {
+            code: "unknown_field",
+            detail: Some(format!(
+                "{}/{}/D{} 下未找到 {}",
+                region,
+                universe,
+                delay,
+                unknown.join(", ")
+            )),
+        });
     }
+    if let Err(EventOpValidationErr::Incompatible) =
+        DataFieldRepository::validate_event_operator_compatibility(
+            db,
+            s,
+            Some(region),
+            Some(universe),
+            Some(delay),
+        )
+        .await
     {
-        let lower = s.to_ascii_lowercase();
-        let mut pos = 0usize;
-        loop {
-            if let Some(idx) = lower[pos..].find("winsorize(") {
-                let start = pos + idx + "winsorize(".len();
-                let bytes = s.as_bytes();
-                let mut depth = 1i32;
-                let mut i = start;
-                let mut segs: Vec<(usize, usize)> = Vec::new();
-                let mut seg_start = start;
-                while i < bytes.len() && depth > 0 {
-                    let ch = bytes[i];
-                    if ch == b'(' {
-                        depth += 1;
-                    } else if ch == b')' {
-                        depth -= 1;
-                        if depth == 0 {
-                            segs.push((seg_start, i));
-                            break;
-                        }
-                    } else if ch == b',' && depth == 1 {
-                        segs.push((seg_start, i));
-                        seg_start = i + 1;
-                    }
-                    i += 1;
-                }
-                let mut positional = 0usize;
-                for (a, b) in segs {
-                    let seg = s[a..b].trim();
-                    if seg.is_empty() {
-                        continue;
-                    }
-                    let mut d = 0i32;
-                    let mut is_named = false;
-                    for ch in seg.chars() {
-                        if ch == '(' {
-                            d += 1;
-                        } else if ch == ')' {
-                            d -= 1;
-                        } else if ch == '=' && d == 0 {
-                            is_named = true;
-                            break;
-                        }
-                    }
-                    if !is_named {
-                        positional += 1;
-                    }
-                }
-                if positional > 1 {
-                    return Err("winsorize_arity".to_string());
-                }
-                pos = (i + 1).min(lower.len());
-            } else {
-                break;
-            }
-        }
+        return Err(PrequeueRejection {
+            code: "event_operator",
+            detail: None,
+        });
     }
     Ok(())
 }
@@ -114,9 +166,61 @@ pub fn sanitize_expression(expr: &str) -> String {
     s.trim().to_string()
 }
 
+/// 流式输出按行累积：只保留已完整收到的行，中途中断时末尾半行不会被当成表达式；
+/// `<think>` 块内的行属于思考过程，不计数也不保留
+#[derive(Debug, Default)]
+pub struct StreamLines {
+    complete: String,
+    pending: String,
+    exprs: usize,
+    in_think: bool,
+}
+
+impl StreamLines {
+    /// 追加一段增量，返回本次新完成的 `ALPHA_EXPR:` 行数
+    pub fn push(&mut self, delta: &str) -> usize {
+        self.pending.push_str(delta);
+        let mut added = 0;
+        while let Some(pos) = self.pending.find('\n') {
+            let mut line: String = self.pending.drain(..=pos).collect();
+            if line.contains("<think>") {
+                self.in_think = true;
+            }
+            if let Some(i) = line.find("</think>") {
+                if !self.in_think {
+                    // 只有结束标签：此前收到的都是思考过程
+                    self.complete.clear();
+                    added = 0;
+                    self.exprs = 0;
+                }
+                self.in_think = false;
+                line = line[i + "</think>".len()..].to_string();
+            }
+            if self.in_think {
+                continue;
+            }
+            if line.trim_start().starts_with("ALPHA_EXPR:") {
+                added += 1;
+            }
+            self.complete.push_str(&line);
+        }
+        self.exprs += added;
+        added
+    }
+
+    pub fn exprs(&self) -> usize {
+        self.exprs
+    }
+
+    pub fn complete_text(&self) -> &str {
+        &self.complete
+    }
+}
+
 pub fn parse_alpha_exprs(text: &str) -> ParsedResult {
     let mut out = Vec::new();
     let mut rejected = Vec::new();
+    let mut repairable = Vec::new();
     let mut total = 0usize;
 
     for line in text.lines() {
@@ -131,47 +235,203 @@ pub fn parse_alpha_exprs(text: &str) -> ParsedResult {
             line
         };
         let expr = sanitize_expression(expr_raw);
+        if screen(&expr, &mut rejected, &mut repairable) {
+            out.push(expr);
+        }
+    }
 
-        if expr.len() < 8 {
-            if rejected.len() < 5 {
-                rejected.push(format!("too_short: {expr}"));
-            }
-            continue;
+    ParsedResult {
+        exprs: out,
+        total_lines: total,
+        rejected_examples: rejected,
+        repairable,
+        hypotheses: HashMap::new(),
+    }
+}
+
+/// 基本筛查（长度、括号、禁用运算符）；未通过时记录示例与可修复原因并返回 false
+fn screen(expr: &str, rejected: &mut Vec<String>, repairable: &mut Vec<(String, String)>) -> bool {
+    if expr.len() < 8 {
+        if rejected.len() < 5 {
+            rejected.push(format!("too_short: {expr}"));
         }
-        if !expr.contains('(') || !expr.contains(')') {
-            if rejected.len() < 5 {
-                rejected.push(format!("no_parens: {expr}"));
-            }
-            continue;
+        return false;
+    }
+    if !expr.contains('(') || !expr.contains(')') {
+        if rejected.len() < 5 {
+            rejected.push(format!("no_parens: {expr}"));
+        }
+        return false;
+    }
+    if !paren_balanced(expr) {
+        if rejected.len() < 5 {
+            rejected.push(format!("bad_parens: {expr}"));
+        }
+        repairable.push((expr.to_string(), "括号不匹配".to_string()));
+        return false;
+    }
+    if expr.to_ascii_lowercase().contains("reduce_") {
+        if rejected.len() < 5 {
+            rejected.push(format!("banned_op: {expr}"));
         }
-        if !paren_balanced(&expr) {
-            if rejected.len() < 5 {
-                rejected.push(format!("bad_parens: {expr}"));
+        repairable.push((expr.to_string(), "禁止使用 reduce_* 运算符".to_string()));
+        return false;
+    }
+    true
+}
+
+/// 结构化输出中的一条 Alpha；模型返回的 `fields` 等其余键忽略
+#[derive(Debug, Clone, Deserialize)]
+pub struct JsonAlpha {
+    pub expression: String,
+    #[serde(default)]
+    pub hypothesis: Option<String>,
+}
+
+/// 结构化输出模式的 `response_format`：`{"alphas": [{expression, hypothesis, fields}]}`
+pub fn alpha_response_format() -> Value {
+    serde_json::json!({
+        "type": "json_schema",
+        "json_schema": {
+            "name": "alphas",
+            "strict": true,
+            "schema": {
+                "type": "object",
+                "properties": {
+                    "alphas": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "expression": {"type": "string"},
+                                "hypothesis": {"type": "string"},
+                                "fields": {"type": "array", "items": {"type": "string"}}
+                            },
+                            "required": ["expression", "hypothesis", "fields"],
+                            "additionalProperties": false
+                        }
+                    }
+                },
+                "required": ["alphas"],
+                "additionalProperties": false
             }
+        }
+    })
+}
+
+/// 从模型输出中提取结构化 Alpha：先按完整 JSON（可在 ``` 代码块内）解析，
+/// 失败时（夹杂说明文字、流式截断）逐个提取已完整闭合的 `{...}` 对象
+pub fn extract_json_alphas(text: &str) -> Vec<JsonAlpha> {
+    let body = fenced_block(text).unwrap_or(text).trim();
+    if let Ok(v) = serde_json::from_str::<Value>(body) {
+        let items = alpha_items(v);
+        if !items.is_empty() {
+            return items;
+        }
+    }
+    closed_objects(body)
+        .into_iter()
+        .filter_map(|s| serde_json::from_str::<JsonAlpha>(s).ok())
+        .collect()
+}
+
+/// 结构化输出解析，提取不到任何条目时退回 `ALPHA_EXPR:` 行解析
+pub fn parse_structured_exprs(text: &str) -> ParsedResult {
+    let items = extract_json_alphas(text);
+    if items.is_empty() {
+        return parse_alpha_exprs(text);
+    }
+    let mut out = Vec::new();
+    let mut rejected = Vec::new();
+    let mut repairable = Vec::new();
+    let mut hypotheses = HashMap::new();
+    let total = items.len();
+    for item in items {
+        let expr = sanitize_expression(&item.expression);
+        if !screen(&expr, &mut rejected, &mut repairable) {
             continue;
         }
-        if expr.to_ascii_lowercase().contains("reduce_") {
-            if rejected.len() < 5 {
-                rejected.push(format!("banned_op: {expr}"));
+        if let Some(h) = item.hypothesis.map(|h| h.trim().to_string()) {
+            if !h.is_empty() {
+                hypotheses.insert(expr.clone(), h);
             }
-            continue;
         }
-        out.push(expr.to_string());
+        out.push(expr);
     }
-
     ParsedResult {
         exprs: out,
         total_lines: total,
         rejected_examples: rejected,
+        repairable,
+        hypotheses,
+    }
+}
+
+/// 第一个 ``` 代码块的内容；缺少结束标记（截断）时取到末尾
+fn fenced_block(text: &str) -> Option<&str> {
+    let start = text.find("```")?;
+    let rest = &text[start + 3..];
+    let rest = &rest[rest.find('\n').map_or(rest.len(), |i| i + 1)..];
+    Some(rest.find("```").map_or(rest, |end| &rest[..end]))
+}
+
+fn alpha_items(v: Value) -> Vec<JsonAlpha> {
+    let list = match v {
+        Value::Array(list) => list,
+        Value::Object(mut obj) => {
+            match obj.remove("alphas").or_else(|| obj.remove("expressions")) {
+                Some(Value::Array(list)) => list,
+                _ if obj.contains_key("expression") => vec![Value::Object(obj)],
+                _ => Vec::new(),
+            }
+        }
+        _ => Vec::new(),
+    };
+    list.into_iter()
+        .filter_map(|x| serde_json::from_value(x).ok())
+        .collect()
+}
+
+/// 所有已闭合的 `{...}` 片段（含嵌套，内层在前）；忽略字符串内的括号
+fn closed_objects(s: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut starts = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' => starts.push(i),
+            '}' => {
+                if let Some(start) = starts.pop() {
+                    out.push(&s[start..=i]);
+                }
+            }
+            _ => {}
+        }
     }
+    out
 }
 
+/// 括号是否配对（忽略字符串内的括号；无法分词视为不配对）
 fn paren_balanced(s: &str) -> bool {
+    let Ok(tokens) = tokenize(s) else {
+        return false;
+    };
     let mut depth = 0i32;
-    for ch in s.chars() {
-        match ch {
-            '(' => depth += 1,
-            ')' => {
+    for t in tokens {
+        match t.kind {
+            TokenKind::LParen => depth += 1,
+            TokenKind::RParen => {
                 depth -= 1;
                 if depth < 0 {
                     return false;
@@ -184,37 +444,65 @@ fn paren_balanced(s: &str) -> bool {
 }
 
 pub fn extract_operators(expr: &str) -> Vec<String> {
-    let s = expr.trim();
-    let bytes = s.as_bytes();
-    let mut ops = Vec::new();
-    let mut i = 0usize;
-    while i < bytes.len() {
-        if is_ident_start(bytes[i]) {
-            let start = i;
-            i += 1;
-            while i < bytes.len() && is_ident_continue(bytes[i]) {
-                i += 1;
-            }
-            let end = i;
-            while i < bytes.len() && bytes[i].is_ascii_whitespace() {
-                i += 1;
-            }
-            if i < bytes.len() && bytes[i] == b'(' {
-                let name = &s[start..end];
-                if name != "ALPHA_EXPR" {
-                    ops.push(name.to_string());
-                }
-            }
-        } else {
-            i += 1;
-        }
-    }
-    ops
+    crate::expr::operators(expr.trim())
+        .into_iter()
+        .filter(|name| name != "ALPHA_EXPR")
+        .collect()
 }
 
-fn is_ident_start(b: u8) -> bool {
-    (b'A'..=b'Z').contains(&b) || (b'a'..=b'z').contains(&b) || b == b'_'
-}
-fn is_ident_continue(b: u8) -> bool {
-    is_ident_start(b) || (b'0'..=b'9').contains(&b)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 逐段推入并返回 (各次新增行数, 完整文本)
+    fn feed(deltas: &[&str]) -> (Vec<usize>, StreamLines) {
+        let mut lines = StreamLines::default();
+        let added = deltas.iter().map(|d| lines.push(d)).collect();
+        (added, lines)
+    }
+
+    #[test]
+    fn keeps_only_complete_lines_across_split_deltas() {
+        let (added, lines) = feed(&[
+            "ALPHA_EX",
+            "PR: rank(close)\nALPHA_EXPR: ts_mean(",
+            "open, 5)\n",
+            "ALPHA_EXPR: zscore(vol",
+        ]);
+        assert_eq!(added, vec![0, 1, 1, 0]);
+        assert_eq!(lines.exprs(), 2);
+        assert_eq!(
+            lines.complete_text(),
+            "ALPHA_EXPR: rank(close)\nALPHA_EXPR: ts_mean(open, 5)\n"
+        );
+    }
+
+    #[test]
+    fn counts_only_alpha_expr_lines() {
+        let (_, lines) = feed(&["思路如下：\n  ALPHA_EXPR: rank(close)\n说明 ALPHA_EXPR: x\n"]);
+        assert_eq!(lines.exprs(), 1);
+        assert!(lines.complete_text().starts_with("思路如下"));
+    }
+
+    #[test]
+    fn skips_think_block() {
+        let (added, lines) = feed(&[
+            "<think>\nALPHA_EXPR: draft(",
+            "close)\n</think>\nALPHA_EXPR: rank(close)\n",
+        ]);
+        assert_eq!(added, vec![0, 1]);
+        assert_eq!(lines.complete_text(), "\nALPHA_EXPR: rank(close)\n");
+    }
+
+    #[test]
+    fn close_tag_only_discards_earlier_text() {
+        // 部分模型不输出开始标签，结束标签之前的都是思考过程
+        let (added, lines) = feed(&[
+            "ALPHA_EXPR: draft(close)\n",
+            "再想想\n</think>ALPHA_EXPR: rank(open)\n",
+        ]);
+        assert_eq!(added, vec![1, 1]);
+        assert_eq!(lines.exprs(), 1);
+        assert_eq!(lines.complete_text(), "ALPHA_EXPR: rank(open)\n");
+    }
 }
@@ -1,13 +1,84 @@
 use crate::generate::context::OperatorCatalog;
 use regex::Regex;
 
+/// 提示模板版本，修改提示文案时递增，随来源信息记录
+pub const PROMPT_VERSION: &str = "4";
+
 pub struct PromptBuilder {
     operators: OperatorCatalog,
+    feedback: PromptFeedback,
+    json_output: bool,
+}
+
+/// 回测结果反馈：近期表现最好的 Alpha 与有代表性的失败，作为 few-shot 示例写入提示
+#[derive(Clone, Debug, Default)]
+pub struct PromptFeedback {
+    pub best: Vec<BestExample>,
+    pub failures: Vec<FailureExample>,
+}
+
+#[derive(Clone, Debug)]
+pub struct BestExample {
+    pub expression: String,
+    pub sharpe: Option<f64>,
+    pub fitness: Option<f64>,
+    pub turnover: Option<f64>,
+}
+
+#[derive(Clone, Debug)]
+pub struct FailureExample {
+    pub expression: String,
+    pub reason: String,
 }
 
 impl PromptBuilder {
     pub fn new(operators: OperatorCatalog) -> Self {
-        Self { operators }
+        Self {
+            operators,
+            feedback: PromptFeedback::default(),
+            json_output: false,
+        }
+    }
+
+    /// 要求按 JSON 数组输出（见 `parser::ALPHA_JSON_SCHEMA`），而不是 `ALPHA_EXPR:` 行
+    pub fn with_json_output(mut self, json_output: bool) -> Self {
+        self.json_output = json_output;
+        self
+    }
+
+    pub fn with_feedback(mut self, feedback: PromptFeedback) -> Self {
+        self.feedback = feedback;
+        self
+    }
+
+    fn push_feedback(&self, lines: &mut Vec<String>) {
+        let fmt = |v: Option<f64>| v.map_or("-".to_string(), |x| format!("{:.2}", x));
+        if !self.feedback.best.is_empty() {
+            lines.push(
+                "Recent best alphas (learn from their structure; do NOT repeat them verbatim):"
+                    .to_string(),
+            );
+            lines.extend(self.feedback.best.iter().map(|b| {
+                format!(
+                    "- {}  [sharpe={}, fitness={}, turnover={}]",
+                    b.expression,
+                    fmt(b.sharpe),
+                    fmt(b.fitness),
+                    fmt(b.turnover)
+                )
+            }));
+            lines.push("".to_string());
+        }
+        if !self.feedback.failures.is_empty() {
+            lines.push("Recent failures (avoid these mistakes):".to_string());
+            lines.extend(
+                self.feedback
+                    .failures
+                    .iter()
+                    .map(|f| format!("- {}  => {}", f.expression, f.reason)),
+            );
+            lines.push("".to_string());
+        }
     }
 
     pub fn build(&self, n: usize) -> String {
@@ -34,6 +105,8 @@ impl PromptBuilder {
         lines.push("ALPHA_EXPR:group_zscore(ts_mean([FIELD], 10), [GROUP_FIELD])".to_string());
         lines.push("".to_string());
 
+        self.push_feedback(&mut lines);
+
         if !self.operators.by_category.is_empty() {
             lines.push("Operators (compact hints):".to_string());
             for (cat, list) in &self.operators.by_category {
@@ -140,15 +213,24 @@ impl PromptBuilder {
         lines.push(format!(
             "Generate {n} unique alpha factor expressions for WorldQuant BRAIN FASTEXPR."
         ));
-        lines.push("Return ONLY the expressions, one per line.".to_string());
-        lines.push(
-            "Each line MUST start with 'ALPHA_EXPR:' followed by the expression.".to_string(),
-        );
-        lines.push("No markdown, no explanations.".to_string());
-        lines.push("Do NOT include any curly braces {} or annotations.".to_string());
-        lines.push(
-            "Do NOT append trailing markers like {CR}, {…}, comments or metadata.".to_string(),
-        );
+        if self.json_output {
+            lines.push("Return ONLY a JSON object: {\"alphas\": [ ... ]}.".to_string());
+            lines.push(
+                "Each item: {\"expression\": FASTEXPR string, \"hypothesis\": one sentence on the economic idea, \"fields\": [field IDs used]}."
+                    .to_string(),
+            );
+            lines.push("Do NOT put annotations or comments inside \"expression\".".to_string());
+        } else {
+            lines.push("Return ONLY the expressions, one per line.".to_string());
+            lines.push(
+                "Each line MUST start with 'ALPHA_EXPR:' followed by the expression.".to_string(),
+            );
+            lines.push("No markdown, no explanations.".to_string());
+            lines.push("Do NOT include any curly braces {} or annotations.".to_string());
+            lines.push(
+                "Do NOT append trailing markers like {CR}, {…}, comments or metadata.".to_string(),
+            );
+        }
         lines.push("".to_string());
 
         if region.is_some() || universe.is_some() || delay.is_some() {
@@ -201,12 +283,21 @@ impl PromptBuilder {
         }
 
         lines.push("Example format (use provided fields; avoid placeholders):".to_string());
-        lines.push("ALPHA_EXPR:ts_rank(FIELD_ID_HERE, 20)".to_string());
-        lines.push(
-            "ALPHA_EXPR:group_zscore(ts_mean(FIELD_ID_HERE, 10), GROUP_FIELD_ID)".to_string(),
-        );
+        if self.json_output {
+            lines.push(
+                "{\"alphas\": [{\"expression\": \"ts_rank(FIELD_ID_HERE, 20)\", \"hypothesis\": \"Recent strength persists.\", \"fields\": [\"FIELD_ID_HERE\"]}]}"
+                    .to_string(),
+            );
+        } else {
+            lines.push("ALPHA_EXPR:ts_rank(FIELD_ID_HERE, 20)".to_string());
+            lines.push(
+                "ALPHA_EXPR:group_zscore(ts_mean(FIELD_ID_HERE, 10), GROUP_FIELD_ID)".to_string(),
+            );
+        }
         lines.push("".to_string());
 
+        self.push_feedback(&mut lines);
+
         if !self.operators.by_category.is_empty() {
             lines.push("Operators (compact hints):".to_string());
             for (cat, list) in &self.operators.by_category {
@@ -269,6 +360,39 @@ impl PromptBuilder {
 
         lines.join("\n")
     }
+
+    /// 修复轮提示：逐条给出被拒表达式与校验原因，要求输出修正后的版本
+    pub fn build_repair(
+        &self,
+        rejected: &[(String, String)],
+        fields: &[String],
+        incompatible_ops: &[String],
+    ) -> String {
+        let mut lines = vec![
+            "The following WorldQuant BRAIN FASTEXPR expressions were rejected by validation."
+                .to_string(),
+            "Fix each one so it passes validation while keeping its original idea.".to_string(),
+            "Output exactly one line per fixed expression, prefixed with 'ALPHA_EXPR: '. Skip any that cannot be fixed. No explanations."
+                .to_string(),
+            "".to_string(),
+        ];
+        for (i, (expr, reason)) in rejected.iter().enumerate() {
+            lines.push(format!("{}. {}", i + 1, expr));
+            lines.push(format!("   reason: {}", reason));
+        }
+        lines.push("".to_string());
+        if !fields.is_empty() {
+            lines.push(format!("Allowed data fields: {}", fields.join(", ")));
+        }
+        if !incompatible_ops.is_empty() {
+            lines.push(format!(
+                "Do NOT apply these operators to event fields: {}",
+                incompatible_ops.join(", ")
+            ));
+        }
+        lines.push("Do NOT use reduce_* operators. Keep parentheses balanced.".to_string());
+        lines.join("\n")
+    }
 }
 
 fn is_banned(name: &str) -> bool {
@@ -1,14 +1,25 @@
 use crate::ai::{ChatRequest, LlmError, LlmProvider};
+use crate::expr::checker::SignatureTable;
+use crate::expr::complexity::Complexity;
+use crate::expr::similarity::Shingles;
 use crate::generate::context::GenerateContextProvider;
-use crate::generate::parser::{parse_alpha_exprs, validate_prequeue};
-use crate::generate::prompt::PromptBuilder;
+use crate::generate::parser::{
+    alpha_response_format, parse_alpha_exprs, parse_structured_exprs, validate_for_scope,
+    StreamLines,
+};
+use crate::generate::prompt::{
+    BestExample, FailureExample, PromptBuilder, PromptFeedback, PROMPT_VERSION,
+};
 use crate::session::WQBSession;
 use crate::storage::repository::DataFieldRepository;
-use crate::storage::repository::{AlphaDefinition, AlphaRepository, BacktestRepository};
+use crate::storage::repository::{
+    AlphaDefinition, AlphaRepository, BacktestRepository, LlmUsageRepository, NewUsage, Provenance,
+    ProvenanceRepository, ReasoningRepository,
+};
 use crate::AppEvent;
 use sea_orm::DatabaseConnection;
-use std::collections::HashSet;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 use tokio::sync::mpsc;
 
 #[derive(Clone, Debug)]
@@ -22,6 +33,268 @@ pub struct GenerateConfig {
     pub delay: Option<i32>,
     pub field_sample_size: usize,
     pub auto_backtest: bool,
+    pub complexity_limits: ComplexityLimits,
+    pub near_duplicate: NearDuplicateFilter,
+    pub feedback: FeedbackConfig,
+    pub repair: RepairConfig,
+}
+
+/// 修复轮（环境变量）：GEN_REPAIR=1 开启（默认关闭），GEN_REPAIR_MAX 单轮最多交回修复的条数（默认 10）。
+/// 开启后入库前即做完整校验，未通过的候选连同原因交回 LLM 修正，修正版通过校验才入库
+#[derive(Clone, Debug)]
+pub struct RepairConfig {
+    pub enabled: bool,
+    pub max: usize,
+}
+
+impl RepairConfig {
+    pub fn from_env() -> Self {
+        let enabled = std::env::var("GEN_REPAIR")
+            .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+            .unwrap_or(false);
+        let max = std::env::var("GEN_REPAIR_MAX")
+            .ok()
+            .and_then(|s| s.trim().parse::<usize>().ok())
+            .unwrap_or(10);
+        Self {
+            enabled: enabled && max > 0,
+            max,
+        }
+    }
+}
+
+type CachedFeedback = (i64, (Option<String>, Option<String>), PromptFeedback);
+
+/// 提示中的回测反馈（环境变量）：GEN_FEEDBACK_BEST（最好 Alpha 条数，默认 0 即关闭）、
+/// GEN_FEEDBACK_FAILURES（失败示例条数，默认 0）、GEN_FEEDBACK_DAYS（只取最近多少天，默认 14）、
+/// GEN_FEEDBACK_REFRESH（反馈缓存的刷新间隔秒数，默认 300）
+#[derive(Clone, Debug)]
+pub struct FeedbackConfig {
+    pub best: u64,
+    pub failures: usize,
+    pub days: i64,
+    pub refresh_sec: i64,
+    /// 最近一次加载的 (时间戳, 范围, 反馈)，同一配置的各批次与各 worker 共享
+    cache: Arc<Mutex<Option<CachedFeedback>>>,
+}
+
+impl FeedbackConfig {
+    pub fn from_env() -> Self {
+        let read = |k: &str| {
+            std::env::var(k)
+                .ok()
+                .and_then(|s| s.trim().parse::<i64>().ok())
+        };
+        Self {
+            best: read("GEN_FEEDBACK_BEST").unwrap_or(0).max(0) as u64,
+            failures: read("GEN_FEEDBACK_FAILURES").unwrap_or(0).max(0) as usize,
+            days: read("GEN_FEEDBACK_DAYS").filter(|d| *d > 0).unwrap_or(14),
+            refresh_sec: read("GEN_FEEDBACK_REFRESH").unwrap_or(300).max(0),
+            cache: Arc::default(),
+        }
+    }
+
+    /// 按配置从 alphas / backtest_jobs 取反馈示例；最好 Alpha 限定在目标范围内。
+    /// 距上次加载不足 `refresh_sec` 秒时直接返回缓存
+    pub async fn load(
+        &self,
+        db: &DatabaseConnection,
+        region: Option<&str>,
+        universe: Option<&str>,
+    ) -> Result<PromptFeedback, sea_orm::DbErr> {
+        let now = chrono::Utc::now().timestamp();
+        let scope = (region.map(str::to_string), universe.map(str::to_string));
+        let cached = self.cache.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        if let Some((_, _, feedback)) =
+            cached.filter(|(at, s, _)| *s == scope && now - at < self.refresh_sec)
+        {
+            return Ok(feedback);
+        }
+        let since = now - self.days * 86_400;
+        let mut feedback = PromptFeedback::default();
+        if self.best > 0 {
+            feedback.best = AlphaRepository::best_recent(db, region, universe, since, self.best)
+                .await?
+                .into_iter()
+                .map(|a| BestExample {
+                    expression: a.expression,
+                    sharpe: a.is_sharpe,
+                    fitness: a.is_fitness,
+                    turnover: a.is_turnover,
+                })
+                .collect();
+        }
+        if self.failures > 0 {
+            feedback.failures =
+                BacktestRepository::representative_failures(db, since, self.failures)
+                    .await?
+                    .into_iter()
+                    .map(|(expression, reason)| FailureExample { expression, reason })
+                    .collect();
+        }
+        *self.cache.lock().unwrap_or_else(|e| e.into_inner()) =
+            Some((now, scope, feedback.clone()));
+        Ok(feedback)
+    }
+}
+
+/// 出错请求的 (供应商, 模型)：经供应商链时取出错的那一项，否则按 LLM_PROVIDER 与请求的模型
+fn error_origin(e: &LlmError, model: &str) -> (String, String) {
+    match e.origin() {
+        Some((p, m)) => (p.to_string(), m.to_string()),
+        None => (
+            std::env::var("LLM_PROVIDER").unwrap_or_else(|_| "openrouter".to_string()),
+            model.to_string(),
+        ),
+    }
+}
+
+/// 鉴权失败时按供应商配置（providers.json 或内置预设）提示应设置的 key 环境变量
+fn unauthorized_hint(provider: &str) -> String {
+    let vars = crate::ai::openai_compat::ProviderSpec::load(provider)
+        .map(|spec| spec.key_vars())
+        .unwrap_or_default();
+    if vars.is_empty() {
+        format!(
+            "AI 未授权：请检查供应商 {} 的 key 配置（providers.json 中的 key_env/keys_env）",
+            provider
+        )
+    } else {
+        format!(
+            "AI 未授权：请在 .env 设置 {}（供应商 {}），或在 LLM_PROVIDER/LLM_CHAIN 中换用其他供应商",
+            vars.join(" 或 "),
+            provider
+        )
+    }
+}
+
+/// 生成结果的结构复杂度接受规则（环境变量，未设置则不限制）：
+/// GEN_MAX_OPERATORS / GEN_MAX_DEPTH / GEN_MAX_LOOKBACK / GEN_MIN_FIELDS / GEN_MIN_CATEGORIES
+#[derive(Clone, Debug, Default)]
+pub struct ComplexityLimits {
+    pub max_operators: Option<i32>,
+    pub max_depth: Option<i32>,
+    pub max_lookback: Option<i32>,
+    pub min_fields: Option<i32>,
+    pub min_categories: Option<i32>,
+}
+
+impl ComplexityLimits {
+    pub fn from_env() -> Self {
+        let read = |k: &str| {
+            std::env::var(k)
+                .ok()
+                .and_then(|s| s.trim().parse::<i32>().ok())
+        };
+        Self {
+            max_operators: read("GEN_MAX_OPERATORS"),
+            max_depth: read("GEN_MAX_DEPTH"),
+            max_lookback: read("GEN_MAX_LOOKBACK"),
+            min_fields: read("GEN_MIN_FIELDS"),
+            min_categories: read("GEN_MIN_CATEGORIES"),
+        }
+    }
+
+    /// 不满足时返回第一条违反的规则
+    pub fn check(&self, c: &Complexity) -> Result<(), String> {
+        if let Some(m) = self.max_operators.filter(|m| c.operators > *m) {
+            return Err(format!("运算符个数 {} > {}", c.operators, m));
+        }
+        if let Some(m) = self.max_depth.filter(|m| c.depth > *m) {
+            return Err(format!("嵌套深度 {} > {}", c.depth, m));
+        }
+        if let (Some(m), Some(lb)) = (self.max_lookback, c.lookback) {
+            if lb > m {
+                return Err(format!("回看窗口 {} > {}", lb, m));
+            }
+        }
+        if let Some(m) = self.min_fields.filter(|m| c.fields < *m) {
+            return Err(format!("字段数 {} < {}", c.fields, m));
+        }
+        if let Some(m) = self.min_categories.filter(|m| c.categories < *m) {
+            return Err(format!("运算符类别数 {} < {}", c.categories, m));
+        }
+        Ok(())
+    }
+}
+
+/// 近似重复过滤：与批内已接受或同范围最近入库的 Alpha 结构相似度达到阈值即丢弃。
+/// GEN_SIMILARITY_THRESHOLD（默认关闭，取 (0, 1] 内的值开启，如 0.9）、GEN_SIMILARITY_POOL（比对的已有条数，默认 5000）
+#[derive(Clone, Debug)]
+pub struct NearDuplicateFilter {
+    pub threshold: Option<f64>,
+    pub pool: u64,
+    /// 比对池中表达式的 shingle（无法解析为 None），同一配置的各批次与各 worker 共享
+    cache: Arc<Mutex<HashMap<String, Option<Shingles>>>>,
+}
+
+impl NearDuplicateFilter {
+    pub fn new(threshold: Option<f64>, pool: u64) -> Self {
+        Self {
+            threshold,
+            pool,
+            cache: Arc::default(),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let threshold = std::env::var("GEN_SIMILARITY_THRESHOLD")
+            .ok()
+            .and_then(|s| s.trim().parse::<f64>().ok())
+            .filter(|t| *t > 0.0 && *t <= 1.0);
+        let pool = std::env::var("GEN_SIMILARITY_POOL")
+            .ok()
+            .and_then(|s| s.trim().parse::<u64>().ok())
+            .unwrap_or(5000);
+        Self::new(threshold, pool)
+    }
+
+    /// 返回 (保留的候选, 被丢弃的 (候选, 最相似的表达式, 相似度))；无法解析的候选原样保留，交给后续校验
+    pub fn apply(
+        &self,
+        candidates: Vec<String>,
+        existing: &[String],
+    ) -> (Vec<String>, Vec<(String, String, f64)>) {
+        let Some(threshold) = self.threshold else {
+            return (candidates, Vec::new());
+        };
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        // 只解析新进入比对池的表达式；移出比对池的条目随之淘汰
+        let current: HashSet<&str> = existing.iter().map(String::as_str).collect();
+        cache.retain(|e, _| current.contains(e.as_str()));
+        for e in existing {
+            if !cache.contains_key(e) {
+                cache.insert(e.clone(), Shingles::of(e));
+            }
+        }
+        let pool: Vec<(&str, &Shingles)> = existing
+            .iter()
+            .filter_map(|e| cache.get(e)?.as_ref().map(|s| (e.as_str(), s)))
+            .collect();
+        let mut kept_shingles: Vec<(String, Shingles)> = Vec::new();
+        let mut kept = Vec::new();
+        let mut dropped = Vec::new();
+        for c in candidates {
+            let Some(sh) = Shingles::of(&c) else {
+                kept.push(c);
+                continue;
+            };
+            let best = pool
+                .iter()
+                .copied()
+                .chain(kept_shingles.iter().map(|(e, s)| (e.as_str(), s)))
+                .map(|(e, s)| (e, sh.jaccard(s)))
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+            match best {
+                Some((e, sim)) if sim >= threshold => dropped.push((c, e.to_string(), sim)),
+                _ => {
+                    kept_shingles.push((c.clone(), sh));
+                    kept.push(c);
+                }
+            }
+        }
+        (kept, dropped)
+    }
 }
 
 #[derive(Clone, Debug, Default)]
@@ -31,6 +304,34 @@ pub struct GenerateResult {
     pub accepted: usize,
     pub inserted: usize,
     pub rejected_examples: Vec<String>,
+    /// 实际使用的 `供应商/模型`
+    pub provider: Option<String>,
+    /// 修复轮交回的条数与救回的条数（已计入 accepted）
+    pub repair_attempted: usize,
+    pub rescued: usize,
+}
+
+impl GenerateResult {
+    pub fn summary(&self) -> String {
+        let via = self
+            .provider
+            .as_deref()
+            .map(|p| format!(" [{}]", p))
+            .unwrap_or_default();
+        let repair = if self.repair_attempted > 0 {
+            format!(", 修复 {}/{}", self.rescued, self.repair_attempted)
+        } else {
+            String::new()
+        };
+        format!(
+            "候选 {}, 入库 {}, 拒绝 {}{}{}",
+            self.candidates,
+            self.inserted,
+            self.rejected_examples.len(),
+            repair,
+            via
+        )
+    }
 }
 
 pub struct GeneratorService<P: LlmProvider> {
@@ -39,6 +340,7 @@ pub struct GeneratorService<P: LlmProvider> {
     session: Arc<WQBSession>,
     evt_tx: mpsc::UnboundedSender<AppEvent>,
     ctx: Arc<dyn GenerateContextProvider>,
+    worker_index: Option<usize>,
 }
 
 impl<P: LlmProvider + Clone + Send + Sync + 'static> GeneratorService<P> {
@@ -55,20 +357,24 @@ impl<P: LlmProvider + Clone + Send + Sync + 'static> GeneratorService<P> {
             session,
             evt_tx,
             ctx,
+            worker_index: None,
         }
     }
 
+    /// 多 worker 并行时记录到来源信息中
+    pub fn with_worker(mut self, worker_index: usize) -> Self {
+        self.worker_index = Some(worker_index);
+        self
+    }
+
     pub async fn run_loop(&self, cfg: GenerateConfig) {
         let mut backoff: u64 = 0;
         loop {
             match self.generate_once(&cfg).await {
                 Ok(res) => {
-                    let _ = self.evt_tx.send(AppEvent::Log(format!(
-                        "生成完成: 候选 {}, 入库 {}, 拒绝 {}",
-                        res.candidates,
-                        res.inserted,
-                        res.rejected_examples.len()
-                    )));
+                    let _ = self
+                        .evt_tx
+                        .send(AppEvent::Log(format!("生成完成: {}", res.summary())));
                     backoff = 0;
                 }
                 Err(e) => {
@@ -94,7 +400,19 @@ impl<P: LlmProvider + Clone + Send + Sync + 'static> GeneratorService<P> {
         cfg: &GenerateConfig,
     ) -> Result<GenerateResult, anyhow::Error> {
         let operators = self.ctx.get_operator_catalog().await?;
-        let pb = PromptBuilder::new(operators);
+        let signatures = SignatureTable::from_catalog(&operators);
+        let feedback = cfg
+            .feedback
+            .load(
+                self.db.as_ref(),
+                cfg.region.as_deref(),
+                cfg.universe.as_deref(),
+            )
+            .await?;
+        let json_output = crate::ai::json_output_enabled();
+        let pb = PromptBuilder::new(operators)
+            .with_feedback(feedback)
+            .with_json_output(json_output);
         let (non_event_fields, event_fields) = DataFieldRepository::sample_weighted_fields_grouped(
             self.db.as_ref(),
             cfg.region.clone(),
@@ -128,6 +446,7 @@ impl<P: LlmProvider + Clone + Send + Sync + 'static> GeneratorService<P> {
             cfg.delay,
             &incompatible_ops,
         );
+        let prompt_hash = Provenance::hash_prompt(&prompt);
 
         let req = ChatRequest {
             model: cfg.model.clone(),
@@ -135,35 +454,130 @@ impl<P: LlmProvider + Clone + Send + Sync + 'static> GeneratorService<P> {
             user: prompt,
             temperature: 0.7,
             max_tokens: 2048,
+            response_format: json_output.then(alpha_response_format),
         };
 
-        let resp = match self.provider.chat(req).await {
+        // LLM_STREAM：边收边按行累积，慢模型超时也能保留已完整收到的表达式
+        let stream_lines = std::sync::Mutex::new(StreamLines::default());
+        let started = std::time::Instant::now();
+        let result = if crate::ai::stream_enabled() {
+            let evt_tx = self.evt_tx.clone();
+            let on_delta = |delta: &str| {
+                let mut lines = stream_lines.lock().unwrap();
+                if lines.push(delta) > 0 && lines.exprs() % 10 == 0 {
+                    let _ = evt_tx.send(AppEvent::Log(format!(
+                        "⏳ 流式生成中：已收到 {} 条表达式",
+                        lines.exprs()
+                    )));
+                }
+            };
+            self.provider.chat_stream(req, &on_delta).await
+        } else {
+            self.provider.chat(req).await
+        };
+        let latency_ms = started.elapsed().as_millis() as u64;
+        if let Err(e) = &result {
+            let (provider, model) = error_origin(e, &cfg.model);
+            self.record_usage(NewUsage {
+                provider,
+                model,
+                latency_ms,
+                error: Some(e.to_string()),
+                ..Default::default()
+            })
+            .await;
+        }
+        let resp = match result {
             Ok(r) => r,
-            Err(LlmError::Unauthorized) => {
-                let provider =
-                    std::env::var("LLM_PROVIDER").unwrap_or_else(|_| "openrouter".to_string());
-                let msg = match provider.to_ascii_lowercase().as_str() {
-                    "cerebras" => "AI 未授权：请在 .env 设置 CEREBRAS_API_KEY（可选 CEREBRAS_BASE_URL），或切换 LLM_PROVIDER=openrouter 并设置 OPENROUTER_API_KEY",
-                    "xirang" => "AI 未授权：请在 .env 设置 XIRANG_APP_KEY（可选 XIRANG_BASE_URL=https://wishub-x6.ctyun.cn/v1），或切换 LLM_PROVIDER=openrouter 并设置 OPENROUTER_API_KEY",
-                    _ => "AI 未授权：请在 .env 设置 OPENROUTER_API_KEY（可选 OPENROUTER_BASE_URL），或切换 LLM_PROVIDER=cerebras 并设置 CEREBRAS_API_KEY",
-                };
-                return Err(anyhow::anyhow!(msg));
+            Err(e) if matches!(e.kind(), LlmError::Unauthorized) => {
+                let (provider, _) = error_origin(&e, &cfg.model);
+                return Err(anyhow::anyhow!(unauthorized_hint(&provider)));
             }
             Err(e) => return Err(anyhow::anyhow!(e.to_string())),
         };
-        let parsed = parse_alpha_exprs(&resp.text);
+        let provider = resp
+            .provider
+            .as_ref()
+            .map(|p| format!("{}/{}", p, resp.model.as_deref().unwrap_or(&cfg.model)));
+        let usage_model = resp.model.clone().unwrap_or_else(|| cfg.model.clone());
+        let usage_provider = resp.provider.clone().unwrap_or_else(|| {
+            std::env::var("LLM_PROVIDER").unwrap_or_else(|_| "openrouter".to_string())
+        });
+        let mut provenance = Provenance {
+            source: "generate".to_string(),
+            provider: Some(usage_provider.clone()),
+            model: Some(usage_model.clone()),
+            prompt_hash: Some(prompt_hash),
+            prompt_version: Some(PROMPT_VERSION.to_string()),
+            batch_id: Some(Provenance::new_batch_id()),
+            worker_index: self.worker_index,
+            sampled_fields: non_event_fields
+                .iter()
+                .chain(event_fields.iter())
+                .cloned()
+                .collect(),
+            ..Default::default()
+        };
+        let usage = resp.usage.unwrap_or_default();
+        let cost = resp
+            .usage
+            .and_then(|u| crate::ai::pricing::estimate_cost(&usage_model, &u));
+        // 结构化输出截断时由 JSON 解析自行挑出已闭合的对象，不按行截取
+        let text = if resp.truncated && !json_output {
+            let lines = stream_lines.into_inner().unwrap();
+            let _ = self.evt_tx.send(AppEvent::Log(format!(
+                "⚠️ 流式响应中断，保留已完整收到的 {} 条表达式",
+                lines.exprs()
+            )));
+            lines.complete_text().to_string()
+        } else {
+            resp.text
+        };
+        let parsed = if json_output {
+            parse_structured_exprs(&text)
+        } else {
+            parse_alpha_exprs(&text)
+        };
+        if resp.truncated && json_output {
+            let _ = self.evt_tx.send(AppEvent::Log(format!(
+                "⚠️ 流式响应中断，保留已完整收到的 {} 条表达式",
+                parsed.exprs.len()
+            )));
+        }
+        provenance.hypotheses = parsed.hypotheses.clone();
         let candidates_count = parsed.exprs.len();
 
+        // 按规范化哈希去重：空白/数值写法/可交换参数顺序不同的表达式视为同一个
         let mut seen = HashSet::new();
         let mut accepted = Vec::new();
+        let mut too_complex: Vec<String> = Vec::new();
         for e in &parsed.exprs {
             if accepted.len() >= cfg.max_insert {
                 break;
             }
-            if seen.insert(e.clone()) {
+            if let Some(Err(reason)) = Complexity::of(e).map(|c| cfg.complexity_limits.check(&c)) {
+                too_complex.push(format!("{} ({})", e, reason));
+                continue;
+            }
+            if seen.insert(crate::expr::canonical_hash(e)) {
                 accepted.push(e.clone());
             }
         }
+        if !too_complex.is_empty() {
+            let _ = self.evt_tx.send(AppEvent::Log(format!(
+                "复杂度规则过滤 {} 条，例: {}",
+                too_complex.len(),
+                too_complex[0]
+            )));
+        }
+        let existing = AlphaRepository::existing_canonical_hashes(
+            self.db.as_ref(),
+            seen.iter().cloned().collect(),
+        )
+        .await?;
+        if !existing.is_empty() {
+            accepted.retain(|e| !existing.contains(&crate::expr::canonical_hash(e)));
+        }
 
         let region = cfg.region.clone().unwrap_or_else(|| "CHN".to_string());
         let universe = cfg
@@ -172,53 +586,193 @@ impl<P: LlmProvider + Clone + Send + Sync + 'static> GeneratorService<P> {
             .unwrap_or_else(|| "TOP2000U".to_string());
         let delay = cfg.delay.unwrap_or(1);
 
+        // 结构近似重复：只差回看窗口或个别字段的克隆不再入库
+        if cfg.near_duplicate.threshold.is_some() && !accepted.is_empty() {
+            let recent = AlphaRepository::recent_expressions(
+                self.db.as_ref(),
+                Some(&region),
+                Some(&universe),
+                cfg.near_duplicate.pool,
+            )
+            .await?;
+            let (kept, dropped) = cfg.near_duplicate.apply(accepted, &recent);
+            accepted = kept;
+            if let Some((c, e, sim)) = dropped.first() {
+                let _ = self.evt_tx.send(AppEvent::Log(format!(
+                    "近似重复过滤 {} 条，例: {} ≈ {} (相似度 {:.2})",
+                    dropped.len(),
+                    c,
+                    e,
+                    sim
+                )));
+            }
+        }
+
+        // 入库前按目标范围校验（运算符签名、字段与事件兼容性），未通过的不入库
+        let mut rejected = parsed.repairable.clone();
+        let mut valid = Vec::with_capacity(accepted.len());
+        for e in accepted {
+            match validate_for_scope(self.db.as_ref(), &e, &signatures, &region, &universe, delay)
+                .await
+            {
+                Ok(()) => valid.push(e),
+                Err(reason) => {
+                    let msg = reason.message();
+                    let _ = self
+                        .evt_tx
+                        .send(AppEvent::Log(format!("跳过入库：{} => {}", e, msg)));
+                    rejected.push((e, msg));
+                }
+            }
+        }
+        accepted = valid;
+
+        // 修复轮：被拒的候选连同校验原因交回 LLM，修正版重新校验后并入
+        let mut rescued: Vec<String> = Vec::new();
+        let mut repair_attempted = 0usize;
+        if cfg.repair.enabled {
+            rejected.truncate(cfg.repair.max);
+            if !rejected.is_empty() {
+                repair_attempted = rejected.len();
+                let fields: Vec<String> = non_event_fields
+                    .iter()
+                    .chain(event_fields.iter())
+                    .cloned()
+                    .collect();
+                let prompt = pb.build_repair(&rejected, &fields, &incompatible_ops);
+                let (repaired, mut repair_usage) = self.request_repair(cfg, prompt).await;
+                for e in repaired {
+                    if accepted.len() + rescued.len() >= cfg.max_insert {
+                        break;
+                    }
+                    if let Some(Err(_)) =
+                        Complexity::of(&e).map(|c| cfg.complexity_limits.check(&c))
+                    {
+                        continue;
+                    }
+                    let hash = crate::expr::canonical_hash(&e);
+                    if !seen.insert(hash.clone()) {
+                        continue;
+                    }
+                    if !AlphaRepository::existing_canonical_hashes(self.db.as_ref(), vec![hash])
+                        .await?
+                        .is_empty()
+                    {
+                        continue;
+                    }
+                    if validate_for_scope(
+                        self.db.as_ref(),
+                        &e,
+                        &signatures,
+                        &region,
+                        &universe,
+                        delay,
+                    )
+                    .await
+                    .is_ok()
+                    {
+                        rescued.push(e);
+                    }
+                }
+                repair_usage.repair_attempted = repair_attempted;
+                repair_usage.repair_rescued = rescued.len();
+                repair_usage.expressions = rescued.clone();
+                self.record_usage(repair_usage).await;
+                let _ = self.evt_tx.send(AppEvent::Log(format!(
+                    "🔧 修复轮：交回 {} 条，救回 {} 条",
+                    repair_attempted,
+                    rescued.len()
+                )));
+            }
+        }
+
         let defs: Vec<AlphaDefinition> = accepted
             .iter()
-            .map(|expression| AlphaDefinition {
-                expression: expression.clone(),
-                region: region.clone(),
-                universe: universe.clone(),
-                language: "FASTEXPR".to_string(),
-                delay,
-                decay: 10,
-                neutralization: "INDUSTRY".to_string(),
-                operator_count: 0,
+            .chain(rescued.iter())
+            .map(|expression| {
+                AlphaDefinition::with_defaults(
+                    expression.clone(),
+                    region.clone(),
+                    universe.clone(),
+                    delay,
+                )
             })
             .collect();
 
-        let _ = AlphaRepository::insert_batch(self.db.as_ref(), defs).await?;
-        if cfg.auto_backtest {
-            let mut queued = 0usize;
-            for expression in &accepted {
-                if let Err(reason) = validate_prequeue(expression) {
-                    let msg = match reason.as_str() {
-                        "unexpected_right_paren" => {
-                            "预提交校验失败：存在意外右括号（形如 ...)(...）"
-                        }
-                        "trailing_comma" => "预提交校验失败：存在拖尾逗号（形如 ...,)）",
-                        "winsorize_arity" => "预提交校验失败：winsorize 仅接受 1 个输入参数",
-                        _ => "预提交校验失败：表达式不符合入队规则",
-                    };
-                    let _ = self.evt_tx.send(AppEvent::Log(format!(
-                        "跳过入队：{} => {}",
-                        expression, msg
-                    )));
-                    continue;
-                }
-                if let Err(
-                    crate::storage::repository::data_field_repo::EventOpValidationErr::Incompatible,
-                ) = DataFieldRepository::validate_event_operator_compatibility(
+        let inserted = AlphaRepository::insert_batch(self.db.as_ref(), defs).await?;
+        provenance.region = Some(region.clone());
+        provenance.universe = Some(universe.clone());
+        ProvenanceRepository::record(self.db.as_ref(), &provenance, &accepted).await?;
+        if !rescued.is_empty() {
+            let repair_provenance = Provenance {
+                source: "repair".to_string(),
+                ..provenance.clone()
+            };
+            ProvenanceRepository::record(self.db.as_ref(), &repair_provenance, &rescued).await?;
+        }
+        // 推理模型的思考过程只为有产出的批次存档，详情页按批次号查看
+        if let (Some(reasoning), Some(batch)) = (&resp.reasoning, &provenance.batch_id) {
+            if crate::ai::reasoning::archive_enabled()
+                && !(accepted.is_empty() && rescued.is_empty())
+            {
+                if let Err(e) = ReasoningRepository::archive(
                     self.db.as_ref(),
-                    expression,
-                    cfg.region.as_deref(),
-                    cfg.universe.as_deref(),
-                    cfg.delay,
+                    batch,
+                    provenance.provider.as_deref().unwrap_or_default(),
+                    provenance.model.as_deref().unwrap_or_default(),
+                    reasoning,
                 )
                 .await
                 {
+                    let _ = self
+                        .evt_tx
+                        .send(AppEvent::Log(format!("保存思考过程失败: {}", e)));
+                }
+            }
+        }
+        self.record_usage(NewUsage {
+            provider: usage_provider,
+            model: usage_model,
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            latency_ms,
+            cost,
+            success: true,
+            error: None,
+            expressions: accepted.clone(),
+            ..Default::default()
+        })
+        .await;
+        let rescued_count = rescued.len();
+        accepted.extend(rescued);
+        if cfg.auto_backtest {
+            // 本地面板筛查（LOCAL_EVAL_GATE）：明显无效的表达式不消耗回测额度
+            let local_rejects = if crate::panel::gate_enabled() {
+                let exprs = accepted.clone();
+                match tokio::task::spawn_blocking(move || crate::panel::screen(&exprs)).await {
+                    Ok(Ok(m)) => m,
+                    Ok(Err(e)) => {
+                        let _ = self
+                            .evt_tx
+                            .send(AppEvent::Log(format!("本地筛查跳过: {}", e)));
+                        Default::default()
+                    }
+                    Err(e) => {
+                        let _ = self
+                            .evt_tx
+                            .send(AppEvent::Log(format!("本地筛查跳过: {}", e)));
+                        Default::default()
+                    }
+                }
+            } else {
+                Default::default()
+            };
+            let mut queued = 0usize;
+            for expression in &accepted {
+                if let Some(reason) = local_rejects.get(expression) {
                     let _ = self.evt_tx.send(AppEvent::Log(format!(
-                        "跳过入队：{} => 预提交校验失败：事件字段与不兼容运算符组合",
-                        expression
+                        "跳过入队（本地评估）：{} => {}",
+                        expression, reason
                     )));
                     continue;
                 }
@@ -227,6 +781,7 @@ impl<P: LlmProvider + Clone + Send + Sync + 'static> GeneratorService<P> {
                     expression.clone(),
                     region.clone(),
                     universe.clone(),
+                    delay,
                 )
                 .await?
                 {
@@ -237,14 +792,86 @@ impl<P: LlmProvider + Clone + Send + Sync + 'static> GeneratorService<P> {
                 .evt_tx
                 .send(AppEvent::Log(format!("已自动加入回测队列: {}", queued)));
         }
-        let inserted = accepted.len();
-
         Ok(GenerateResult {
             total_lines: parsed.total_lines,
             candidates: candidates_count,
             accepted: accepted.len(),
             inserted,
             rejected_examples: parsed.rejected_examples,
+            provider,
+            repair_attempted,
+            rescued: rescued_count,
         })
     }
+
+    /// 发送修复请求，返回解析出的修正表达式与本次请求的用量；请求失败时表达式为空
+    async fn request_repair(
+        &self,
+        cfg: &GenerateConfig,
+        prompt: String,
+    ) -> (Vec<String>, NewUsage) {
+        let req = ChatRequest {
+            model: cfg.model.clone(),
+            system:
+                "You fix alpha expressions for WorldQuant BRAIN FASTEXPR. Output only expressions."
+                    .to_string(),
+            user: prompt,
+            temperature: 0.2,
+            max_tokens: 1024,
+            response_format: None,
+        };
+        let started = std::time::Instant::now();
+        let result = self.provider.chat(req).await;
+        let latency_ms = started.elapsed().as_millis() as u64;
+        match result {
+            Ok(resp) => {
+                let model = resp.model.clone().unwrap_or_else(|| cfg.model.clone());
+                let usage = resp.usage.unwrap_or_default();
+                let cost = resp
+                    .usage
+                    .and_then(|u| crate::ai::pricing::estimate_cost(&model, &u));
+                let provider = resp.provider.clone().unwrap_or_else(|| {
+                    std::env::var("LLM_PROVIDER").unwrap_or_else(|_| "openrouter".to_string())
+                });
+                (
+                    parse_alpha_exprs(&resp.text).exprs,
+                    NewUsage {
+                        provider,
+                        model,
+                        prompt_tokens: usage.prompt_tokens,
+                        completion_tokens: usage.completion_tokens,
+                        latency_ms,
+                        cost,
+                        success: true,
+                        ..Default::default()
+                    },
+                )
+            }
+            Err(e) => {
+                let _ = self
+                    .evt_tx
+                    .send(AppEvent::Log(format!("修复轮请求失败: {}", e)));
+                let (provider, model) = error_origin(&e, &cfg.model);
+                (
+                    Vec::new(),
+                    NewUsage {
+                        provider,
+                        model,
+                        latency_ms,
+                        error: Some(e.to_string()),
+                        ..Default::default()
+                    },
+                )
+            }
+        }
+    }
+
+    /// 用量记录失败不影响生成，只提示
+    async fn record_usage(&self, usage: NewUsage) {
+        if let Err(e) = LlmUsageRepository::record(self.db.as_ref(), usage).await {
+            let _ = self
+                .evt_tx
+                .send(AppEvent::Log(format!("记录 LLM 用量失败: {}", e)));
+        }
+    }
 }
@@ -0,0 +1,322 @@
+//! 表达式模板：带类型占位符的表达式，按字段表展开为具体表达式。
+//!
+//! 占位符：
+//! - `{field}` / `{field:<选择器>}`：目标范围内的字段，选择器匹配数据集/类别/子类别的 id 或名称、字段类型（不区分大小写）
+//! - `{window:5,10,20}`：逐个取给定数值
+//! - `{group}` / `{group:industry,sector}`：分组字段，默认 market/sector/industry/subindustry
+//!
+//! 每个占位符独立取值，结果为全部组合；组合数超过上限时随机抽样。规范化后相同的组合只保留一条。
+
+use crate::storage::entity::data_field::Model as DataFieldModel;
+use rand::Rng;
+use std::collections::HashSet;
+
+const DEFAULT_GROUPS: [&str; 4] = ["market", "sector", "industry", "subindustry"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Slot {
+    Field(Option<String>),
+    Window(Vec<String>),
+    Group(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    Slot(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    parts: Vec<Part>,
+    pub slots: Vec<Slot>,
+}
+
+impl Template {
+    pub fn parse(src: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut slots = Vec::new();
+        let mut rest = src;
+        while let Some(open) = rest.find('{') {
+            if open > 0 {
+                parts.push(Part::Text(rest[..open].to_string()));
+            }
+            let close = rest[open..]
+                .find('}')
+                .map(|i| open + i)
+                .ok_or_else(|| format!("占位符未闭合: {}", &rest[open..]))?;
+            slots.push(parse_slot(&rest[open + 1..close])?);
+            parts.push(Part::Slot(slots.len() - 1));
+            rest = &rest[close + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Text(rest.to_string()));
+        }
+        if slots.is_empty() {
+            return Err("模板中没有占位符".to_string());
+        }
+        Ok(Self { parts, slots })
+    }
+
+    /// 按字段表展开；`cap` 为最多返回条数，结果已按文本去重
+    pub fn expand(
+        &self,
+        fields: &[DataFieldModel],
+        cap: usize,
+        rng: &mut impl Rng,
+    ) -> Result<Vec<String>, String> {
+        let choices: Vec<Vec<String>> = self
+            .slots
+            .iter()
+            .map(|slot| slot_values(slot, fields))
+            .collect();
+        for (slot, values) in self.slots.iter().zip(&choices) {
+            if values.is_empty() {
+                return Err(format!(
+                    "占位符 {} 在目标范围内没有可用取值",
+                    describe(slot)
+                ));
+            }
+        }
+        let total = choices
+            .iter()
+            .try_fold(1usize, |acc, v| acc.checked_mul(v.len()))
+            .unwrap_or(usize::MAX);
+
+        let indices: Vec<usize> = if total <= cap {
+            (0..total).collect()
+        } else if total <= 1_000_000 {
+            rand::seq::index::sample(rng, total, cap).into_vec()
+        } else {
+            // 组合数过大时逐条随机抽取，重复的由下方去重吸收
+            (0..cap * 2).map(|_| rng.gen_range(0..total)).collect()
+        };
+
+        let mut seen = HashSet::new();
+        let mut out = Vec::new();
+        for mut idx in indices {
+            if out.len() >= cap {
+                break;
+            }
+            let mut picked: Vec<&str> = Vec::with_capacity(choices.len());
+            for values in &choices {
+                picked.push(&values[idx % values.len()]);
+                idx /= values.len();
+            }
+            let expr: String = self
+                .parts
+                .iter()
+                .map(|p| match p {
+                    Part::Text(t) => t.as_str(),
+                    Part::Slot(i) => picked[*i],
+                })
+                .collect();
+            // 按规范化哈希去重，上限统计的是实质不同的表达式
+            if seen.insert(crate::expr::canonical_hash(&expr)) {
+                out.push(expr);
+            }
+        }
+        Ok(out)
+    }
+}
+
+fn parse_slot(body: &str) -> Result<Slot, String> {
+    let (kind, arg) = match body.split_once(':') {
+        Some((k, a)) => (k.trim(), Some(a.trim())),
+        None => (body.trim(), None),
+    };
+    let list = |a: &str| -> Vec<String> {
+        a.split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect()
+    };
+    match kind.to_ascii_lowercase().as_str() {
+        "field" => Ok(Slot::Field(
+            arg.filter(|a| !a.is_empty()).map(str::to_string),
+        )),
+        "window" => {
+            let values = list(arg.unwrap_or_default());
+            if values.is_empty()
+                || values
+                    .iter()
+                    .any(|v| v.parse::<u32>().ok().filter(|n| *n > 0).is_none())
+            {
+                return Err(format!(
+                    "window 需要正整数列表，如 {{window:5,10,20}}: {{{}}}",
+                    body
+                ));
+            }
+            Ok(Slot::Window(values))
+        }
+        "group" => Ok(Slot::Group(match arg {
+            Some(a) if !a.is_empty() => list(a),
+            _ => DEFAULT_GROUPS.iter().map(|s| s.to_string()).collect(),
+        })),
+        _ => Err(format!("未知占位符类型: {{{}}}", body)),
+    }
+}
+
+fn slot_values(slot: &Slot, fields: &[DataFieldModel]) -> Vec<String> {
+    match slot {
+        Slot::Window(v) | Slot::Group(v) => v.clone(),
+        Slot::Field(selector) => {
+            let mut ids: Vec<String> = fields
+                .iter()
+                .filter(|f| selector.as_deref().is_none_or(|s| field_matches(f, s)))
+                .map(|f| f.field_id.clone())
+                .collect();
+            ids.sort();
+            ids.dedup();
+            ids
+        }
+    }
+}
+
+fn field_matches(f: &DataFieldModel, selector: &str) -> bool {
+    [
+        &f.dataset_id,
+        &f.dataset_name,
+        &f.category_id,
+        &f.category_name,
+        &f.subcategory_id,
+        &f.subcategory_name,
+        &f.field_type,
+    ]
+    .iter()
+    .any(|v| v.eq_ignore_ascii_case(selector))
+}
+
+fn describe(slot: &Slot) -> String {
+    match slot {
+        Slot::Field(Some(s)) => format!("{{field:{}}}", s),
+        Slot::Field(None) => "{field}".to_string(),
+        Slot::Window(v) => format!("{{window:{}}}", v.join(",")),
+        Slot::Group(v) => format!("{{group:{}}}", v.join(",")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn field(id: &str, dataset: &str, field_type: &str) -> DataFieldModel {
+        DataFieldModel {
+            field_id: id.to_string(),
+            description: String::new(),
+            dataset_id: dataset.to_string(),
+            dataset_name: format!("{} data", dataset),
+            category_id: "cat".to_string(),
+            category_name: "Category".to_string(),
+            subcategory_id: "sub".to_string(),
+            subcategory_name: "Subcategory".to_string(),
+            region: "CHN".to_string(),
+            delay: 1,
+            universe: "TOP2000U".to_string(),
+            field_type: field_type.to_string(),
+            date_coverage: 1.0,
+            coverage: 1.0,
+            user_count: 0,
+            alpha_count: 0,
+            pyramid_multiplier: 1.0,
+            themes: "[]".to_string(),
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    fn fields() -> Vec<DataFieldModel> {
+        vec![
+            field("close", "pv1", "MATRIX"),
+            field("assets", "fundamental6", "MATRIX"),
+            field("sales", "fundamental6", "MATRIX"),
+            field("news_count", "news12", "VECTOR"),
+        ]
+    }
+
+    fn expand_all(src: &str, cap: usize) -> Result<Vec<String>, String> {
+        let mut rng = StdRng::seed_from_u64(7);
+        Template::parse(src)?.expand(&fields(), cap, &mut rng)
+    }
+
+    #[test]
+    fn parse_slots() {
+        let tpl =
+            Template::parse("ts_mean({ field : fundamental6 }, {window:5, 20}) / {field}").unwrap();
+        assert_eq!(
+            tpl.slots,
+            vec![
+                Slot::Field(Some("fundamental6".to_string())),
+                Slot::Window(vec!["5".to_string(), "20".to_string()]),
+                Slot::Field(None),
+            ]
+        );
+        let tpl = Template::parse("group_rank(close, {group})").unwrap();
+        assert_eq!(
+            tpl.slots,
+            vec![Slot::Group(
+                DEFAULT_GROUPS.iter().map(|s| s.to_string()).collect()
+            )]
+        );
+    }
+
+    #[test]
+    fn parse_rejects_bad_templates() {
+        for src in [
+            "rank(close)",
+            "rank({field)",
+            "rank({foo})",
+            "ts_mean(close, {window:0,5})",
+            "ts_mean(close, {window:})",
+            "ts_mean(close, {window:a})",
+        ] {
+            assert!(Template::parse(src).is_err(), "{}", src);
+        }
+    }
+
+    #[test]
+    fn expand_full_product_with_selector() {
+        let out = expand_all(
+            "group_neutralize(ts_mean({field:FUNDAMENTAL6}, {window:5,20}), {group:industry,sector})",
+            100,
+        )
+        .unwrap();
+        assert_eq!(out.len(), 8);
+        assert!(out.contains(&"group_neutralize(ts_mean(sales, 20), sector)".to_string()));
+        assert!(out
+            .iter()
+            .all(|e| !e.contains("close") && !e.contains("news_count")));
+
+        // 选择器也匹配数据集名称与字段类型
+        assert_eq!(
+            expand_all("rank({field:news12 data})", 10).unwrap(),
+            vec!["rank(news_count)"]
+        );
+        assert_eq!(expand_all("rank({field:matrix})", 10).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn expand_dedups_by_canonical_hash() {
+        // a+b 与 b+a 规范化后相同
+        let out = expand_all("add({field:fundamental6}, {field:fundamental6})", 100).unwrap();
+        assert_eq!(out.len(), 3, "{:?}", out);
+    }
+
+    #[test]
+    fn expand_samples_up_to_cap() {
+        let src = "ts_rank({field}, {window:5,10,20,60})";
+        let all = expand_all(src, 100).unwrap();
+        assert_eq!(all.len(), 16);
+        let sampled = expand_all(src, 5).unwrap();
+        assert_eq!(sampled.len(), 5);
+        assert!(sampled.iter().all(|e| all.contains(e)));
+        assert_eq!(sampled, expand_all(src, 5).unwrap(), "同一种子结果应一致");
+    }
+
+    #[test]
+    fn expand_fails_when_slot_has_no_values() {
+        assert!(expand_all("rank({field:analyst4})", 10).is_err());
+    }
+}
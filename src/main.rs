@@ -3,7 +3,10 @@ mod app_service;
 mod app_state;
 mod backtest;
 mod commands;
+mod evolve;
+mod expr;
 mod generate;
+mod panel;
 mod session;
 mod storage;
 mod ui;
@@ -26,7 +29,8 @@ use crate::app_state::{App, AppEvent};
 use crate::commands::AppCommand;
 use crate::storage::entity::Alpha;
 use crate::storage::repository::{
-    AlphaDto, AlphaRepository, BacktestRepository, DataFieldRepository,
+    AlphaDto, AlphaRepository, BacktestRepository, DataFieldRepository, LineageRepository,
+    LlmUsageRepository, ProvenanceRepository, ReasoningRepository,
 };
 use crate::ui::draw;
 
@@ -100,6 +104,24 @@ async fn main() -> io::Result<()> {
     let db = match storage::establish_connection(&db_url).await {
         Ok(connection) => {
             session_info.push("✓ 数据库连接成功".to_string());
+            let alphas = AlphaRepository::backfill_canonical_hashes(&connection)
+                .await
+                .unwrap_or(0);
+            let jobs = BacktestRepository::backfill_canonical_hashes(&connection)
+                .await
+                .unwrap_or(0);
+            if alphas + jobs > 0 {
+                session_info.push(format!(
+                    "✓ 已补齐规范化哈希: Alpha {} 条, 回测任务 {} 条",
+                    alphas, jobs
+                ));
+            }
+            let complexity = AlphaRepository::backfill_complexity(&connection)
+                .await
+                .unwrap_or(0);
+            if complexity > 0 {
+                session_info.push(format!("✓ 已补齐复杂度指标: Alpha {} 条", complexity));
+            }
             Arc::new(connection)
         }
         Err(e) => {
@@ -163,9 +185,14 @@ async fn main() -> io::Result<()> {
     tokio::spawn(async move {
         use crate::ai::AnyProvider;
         use crate::backtest::BacktestService;
+        use crate::evolve::{EvolveConfig, EvolveService};
+        use crate::expr::checker::SignatureTable;
         use crate::generate::context::{ApiContextProvider, GenerateContextProvider};
         use crate::generate::field_sync::FieldSyncService;
-        use crate::generate::{GenerateConfig, GeneratorService};
+        use crate::generate::{
+            ComplexityLimits, FeedbackConfig, GenerateConfig, GeneratorService,
+            NearDuplicateFilter, RepairConfig,
+        };
 
         // 1. 初始化 BacktestService
         let backtest_service = session_bg.as_ref().map(|sess| BacktestService::new(
@@ -176,6 +203,8 @@ async fn main() -> io::Result<()> {
 
         // generate loop 控制
         let mut gen_loop: Vec<tokio::task::JoinHandle<()>> = Vec::new();
+        // evolve loop 控制
+        let mut evolve_loop: Option<tokio::task::JoinHandle<()>> = None;
 
         // 2. 执行恢复逻辑 + 启动常驻 workers
         if let Some(ref service) = backtest_service {
@@ -207,11 +236,41 @@ async fn main() -> io::Result<()> {
 
         while let Some(cmd) = cmd_rx.recv().await {
             match cmd {
-                AppCommand::Backtest { expr } => {
+                AppCommand::Backtest {
+                    expr,
+                    region,
+                    universe,
+                    delay,
+                } => {
                     if let Some(ref service) = backtest_service {
+                        let region = region.unwrap_or_else(|| "CHN".to_string());
+                        let universe = universe.unwrap_or_else(|| "TOP2000U".to_string());
+                        let delay = delay.unwrap_or(1);
                         let _ =
                             evt_tx_bg.send(AppEvent::Message(format!("收到回测请求: {}", expr)));
-                        match service.add_job(&expr).await {
+                        // 入队前按运算符目录签名与目标范围字段检查（目录/字段不可用时只做语法检查）
+                        let signatures = match ctx_provider.as_ref() {
+                            Some(ctx) => ctx
+                                .get_operator_catalog()
+                                .await
+                                .map(|c| SignatureTable::from_catalog(&c))
+                                .unwrap_or_default(),
+                            None => SignatureTable::default(),
+                        };
+                        if let Err(reason) = crate::generate::parser::validate_for_scope(
+                            db_bg.as_ref(),
+                            &expr,
+                            &signatures,
+                            &region,
+                            &universe,
+                            delay,
+                        )
+                        .await
+                        {
+                            let _ = evt_tx_bg.send(AppEvent::Error(reason.message()));
+                            continue;
+                        }
+                        match service.add_job(&expr, region, universe, delay).await {
                             Ok(Some(id)) => {
                                 let _ = evt_tx_bg.send(AppEvent::Message(format!(
                                     "已添加回测任务 [ID: {}]: {}",
@@ -350,6 +409,10 @@ async fn main() -> io::Result<()> {
                             delay,
                             field_sample_size: sample_size,
                             auto_backtest,
+                            complexity_limits: ComplexityLimits::from_env(),
+                            near_duplicate: NearDuplicateFilter::from_env(),
+                            feedback: FeedbackConfig::from_env(),
+                            repair: RepairConfig::from_env(),
                         };
                         for wi in 0..workers {
                             let provider = match AnyProvider::from_env_for_worker(wi) {
@@ -367,7 +430,8 @@ async fn main() -> io::Result<()> {
                                 sess.clone(),
                                 evt_tx_bg.clone(),
                                 ctx_provider.clone(),
-                            );
+                            )
+                            .with_worker(wi);
                             let cfg = config_clone.clone();
                             let handle = tokio::spawn(async move {
                                 generator.run_loop(cfg).await;
@@ -410,6 +474,10 @@ async fn main() -> io::Result<()> {
                             delay,
                             field_sample_size: sample_size,
                             auto_backtest,
+                            complexity_limits: ComplexityLimits::from_env(),
+                            near_duplicate: NearDuplicateFilter::from_env(),
+                            feedback: FeedbackConfig::from_env(),
+                            repair: RepairConfig::from_env(),
                         };
 
                         for wi in 0..workers {
@@ -428,7 +496,8 @@ async fn main() -> io::Result<()> {
                                 sess.clone(),
                                 evt_tx_bg.clone(),
                                 ctx_provider.clone(),
-                            );
+                            )
+                            .with_worker(wi);
                             let cfg = config.clone();
                             tokio::spawn({
                                 let tx = evt_tx_bg.clone();
@@ -440,10 +509,8 @@ async fn main() -> io::Result<()> {
                                     match generator.generate_once(&cfg).await {
                                         Ok(res) => {
                                             let _ = tx.send(AppEvent::Log(format!(
-                                                "单次生成完成: 候选 {}, 入库 {}, 拒绝 {}",
-                                                res.candidates,
-                                                res.inserted,
-                                                res.rejected_examples.len()
+                                                "单次生成完成: {}",
+                                                res.summary()
                                             )));
                                         }
                                         Err(e) => {
@@ -468,6 +535,111 @@ async fn main() -> io::Result<()> {
                         let _ = evt_tx_bg.send(AppEvent::Message("生成任务已停止".to_string()));
                     }
                 }
+                AppCommand::EvolveStart {
+                    batch,
+                    interval_sec,
+                    region,
+                    universe,
+                    delay,
+                } => {
+                    if let Some(ctx_provider) = ctx_provider.as_ref() {
+                        if let Some(h) = evolve_loop.take() {
+                            h.abort();
+                        }
+                        let cfg =
+                            EvolveConfig::from_env(batch, interval_sec, region, universe, delay);
+                        let service = EvolveService::new(
+                            db_bg.clone(),
+                            evt_tx_bg.clone(),
+                            ctx_provider.clone(),
+                        );
+                        let _ = evt_tx_bg.send(AppEvent::Message(format!(
+                            "开始进化任务... ({} / {} / D{}, 每轮 {} 条, 间隔 {}s)",
+                            cfg.region, cfg.universe, cfg.delay, cfg.batch_size, cfg.interval_sec
+                        )));
+                        evolve_loop = Some(tokio::spawn(async move {
+                            service.run_loop(cfg).await;
+                        }));
+                    } else {
+                        let _ = evt_tx_bg.send(AppEvent::Error("无法进化：未登录".to_string()));
+                    }
+                }
+                AppCommand::EvolveOnce {
+                    batch,
+                    region,
+                    universe,
+                    delay,
+                } => {
+                    if let Some(ctx_provider) = ctx_provider.as_ref() {
+                        let cfg = EvolveConfig::from_env(batch, 0, region, universe, delay);
+                        let service = EvolveService::new(
+                            db_bg.clone(),
+                            evt_tx_bg.clone(),
+                            ctx_provider.clone(),
+                        );
+                        let txc = evt_tx_bg.clone();
+                        let dbc = db_bg.clone();
+                        tokio::spawn(async move {
+                            match service.evolve_once(&cfg).await {
+                                Ok(res) => {
+                                    let _ = txc.send(AppEvent::Message(format!(
+                                        "进化完成: 父代 {}, 候选 {}, 入队 {}, 拒绝 {}",
+                                        res.parents, res.candidates, res.queued, res.rejected
+                                    )));
+                                }
+                                Err(e) => {
+                                    let _ = txc.send(AppEvent::Error(format!("进化出错: {}", e)));
+                                }
+                            }
+                            refresh_ui(&dbc, &txc).await;
+                        });
+                    } else {
+                        let _ = evt_tx_bg.send(AppEvent::Error("无法进化：未登录".to_string()));
+                    }
+                }
+                AppCommand::Template {
+                    template,
+                    cap,
+                    region,
+                    universe,
+                    delay,
+                } => {
+                    let signatures = match ctx_provider.as_ref() {
+                        Some(ctx) => ctx
+                            .get_operator_catalog()
+                            .await
+                            .map(|c| SignatureTable::from_catalog(&c))
+                            .unwrap_or_default(),
+                        None => SignatureTable::default(),
+                    };
+                    let _ = evt_tx_bg.send(AppEvent::Message(format!(
+                        "开始展开模板（上限 {}）: {}",
+                        cap, template
+                    )));
+                    let txc = evt_tx_bg.clone();
+                    let dbc = db_bg.clone();
+                    tokio::spawn(async move {
+                        let args = commands::template::TemplateArgs {
+                            template,
+                            cap,
+                            region,
+                            universe,
+                            delay,
+                        };
+                        if let Err(e) =
+                            commands::template::run(args, &dbc, &signatures, txc.clone()).await
+                        {
+                            let _ = txc.send(AppEvent::Error(format!("模板展开失败: {}", e)));
+                        }
+                        refresh_ui(&dbc, &txc).await;
+                    });
+                }
+                AppCommand::EvolveStop => {
+                    if let Some(h) = evolve_loop.take() {
+                        h.abort();
+                        let _ = evt_tx_bg.send(AppEvent::Message("进化任务已停止".to_string()));
+                    }
+                }
                 AppCommand::FieldsSync => {
                     if let Some(ref service) = field_sync_service {
                         if service.is_running() {
@@ -497,6 +669,45 @@ async fn main() -> io::Result<()> {
                         }
                     }
                 }
+                AppCommand::Cluster => {
+                    let dbc = db_bg.clone();
+                    let txc = evt_tx_bg.clone();
+                    tokio::spawn(async move {
+                        match AlphaRepository::assign_families(dbc.as_ref()).await {
+                            Ok((updated, families)) => {
+                                let _ = txc.send(AppEvent::Message(format!(
+                                    "✓ 聚类完成: {} 个家族，更新 {} 条",
+                                    families, updated
+                                )));
+                                if let Ok(rows) = AlphaRepository::family_stats(dbc.as_ref()).await
+                                {
+                                    let _ = txc.send(AppEvent::Families(rows));
+                                }
+                            }
+                            Err(e) => {
+                                let _ = txc.send(AppEvent::Error(format!("聚类失败: {}", e)));
+                            }
+                        }
+                    });
+                }
+                AppCommand::Families => match AlphaRepository::family_stats(db_bg.as_ref()).await {
+                    Ok(rows) => {
+                        let _ = evt_tx_bg.send(AppEvent::Families(rows));
+                    }
+                    Err(e) => {
+                        let _ = evt_tx_bg.send(AppEvent::Error(format!("家族查询失败: {}", e)));
+                    }
+                },
+                AppCommand::Usage { days } => {
+                    match LlmUsageRepository::daily_stats(db_bg.as_ref(), days).await {
+                        Ok(rows) => {
+                            let _ = evt_tx_bg.send(AppEvent::Usage(rows));
+                        }
+                        Err(e) => {
+                            let _ = evt_tx_bg.send(AppEvent::Error(format!("用量查询失败: {}", e)));
+                        }
+                    }
+                }
                 AppCommand::FieldSample {
                     region,
                     universe,
@@ -593,8 +804,24 @@ async fn main() -> io::Result<()> {
                 AppCommand::GetDetail { expr } => {
                     match Alpha::find_by_id(expr.clone()).one(db_bg.as_ref()).await {
                         Ok(Some(model)) => {
-                            let dto = AlphaDto::from(model);
-                            let _ = evt_tx_bg.send(AppEvent::Detail(dto));
+                            let mut dto = AlphaDto::from(model);
+                            dto.parents = LineageRepository::parents_of(db_bg.as_ref(), &expr)
+                                .await
+                                .unwrap_or_default();
+                            dto.provenance = ProvenanceRepository::of(db_bg.as_ref(), &expr)
+                                .await
+                                .ok()
+                                .flatten();
+                            if let Some(batch) =
+                                dto.provenance.as_ref().and_then(|p| p.batch_id.clone())
+                            {
+                                dto.reasoning =
+                                    ReasoningRepository::of_batch(db_bg.as_ref(), &batch)
+                                        .await
+                                        .ok()
+                                        .flatten();
+                            }
+                            let _ = evt_tx_bg.send(AppEvent::Detail(Box::new(dto)));
                         }
                         Ok(None) => {
                             let _ = evt_tx_bg.send(AppEvent::Error("未找到对应记录".to_string()));
@@ -604,6 +831,66 @@ async fn main() -> io::Result<()> {
                         }
                     }
                 }
+                AppCommand::Eval { expr } => {
+                    let txc = evt_tx_bg.clone();
+                    tokio::task::spawn_blocking(move || {
+                        let dir = crate::panel::panel_dir();
+                        let result = crate::panel::Panel::open(&dir)
+                            .and_then(|mut p| crate::panel::evaluate(&mut p, &expr));
+                        match result {
+                            Ok(r) => {
+                                let _ = txc.send(AppEvent::Message(format!(
+                                    "本地评估 ({} 天): Sharpe {:.2}, 年化收益 {:.2}%, 换手 {:.2}%, 覆盖率 {:.0}%, 最大回撤 {:.2}%",
+                                    r.days,
+                                    r.sharpe,
+                                    r.annual_return * 100.0,
+                                    r.turnover * 100.0,
+                                    r.coverage * 100.0,
+                                    r.max_drawdown * 100.0
+                                )));
+                                let problems = r.problems(crate::panel::min_coverage());
+                                if problems.is_empty() {
+                                    let _ =
+                                        txc.send(AppEvent::Message("✓ 未发现明显问题".to_string()));
+                                } else {
+                                    let _ = txc.send(AppEvent::Message(format!(
+                                        "⚠ {}",
+                                        problems.join("；")
+                                    )));
+                                }
+                            }
+                            Err(e) => {
+                                let _ = txc.send(AppEvent::Error(format!("✗ 本地评估失败: {}", e)));
+                            }
+                        }
+                    });
+                }
+                AppCommand::Similar { expr } => {
+                    let dbc = db_bg.clone();
+                    let txc = evt_tx_bg.clone();
+                    tokio::spawn(async move {
+                        let pool = NearDuplicateFilter::from_env().pool;
+                        match AlphaRepository::recent_expressions(dbc.as_ref(), None, None, pool)
+                            .await
+                        {
+                            Ok(all) => {
+                                let items = crate::expr::similarity::most_similar(&expr, &all, 10);
+                                let _ = txc.send(AppEvent::Message(format!(
+                                    "相似 Alpha: 比对 {} 条，最高相似度 {}",
+                                    all.len(),
+                                    items
+                                        .first()
+                                        .map(|(_, s)| format!("{:.2}", s))
+                                        .unwrap_or_else(|| "-".to_string())
+                                )));
+                                let _ = txc.send(AppEvent::Similar { expr, items });
+                            }
+                            Err(e) => {
+                                let _ = txc.send(AppEvent::Error(format!("查询失败: {}", e)));
+                            }
+                        }
+                    });
+                }
                 AppCommand::Catch { alpha_id } => {
                     if let Some(ref sess) = session_bg {
                         let dbc = db_bg.clone();
@@ -618,7 +905,7 @@ async fn main() -> io::Result<()> {
                     }
                 }
                 AppCommand::Help => {
-                    let _ = evt_tx_bg.send(AppEvent::Message("可用命令: backtest <expr> | backtest clear | backtest sanitize [limit] | alphas clear | fields sync | fields stats | fields sample [region] [universe] [delay] [n] | errors export [limit] [path] | generate once <n> [model] [region] [universe] [delay] [sample_size] [auto_backtest] | generate loop <n> <sec> [model] [region] [universe] [delay] [sample_size] [auto_backtest] | generate stop | __INTERNAL_GET_DETAIL__ <expr>".to_string()));
+                    let _ = evt_tx_bg.send(AppEvent::Message("可用命令: backtest [region] [universe] [delay] <expr> | backtest clear | backtest sanitize [limit] | alphas clear | fields sync | fields stats | fields sample [region] [universe] [delay] [n] | errors export [limit] [path] | filter [text] [nofail] [source=generate|repair|template|evolve|catch|backtest] [model=xxx] [is|os|train|test_sharpe|fitness|turnover|returns>=v] [operators|depth|fields|categories|lookback<=v] | generate once <n> [model] [region] [universe] [delay] [sample_size] [auto_backtest] | generate loop <n> <sec> [model] [region] [universe] [delay] [sample_size] [auto_backtest] | generate stop | evolve once <n> [region] [universe] [delay] | evolve loop <n> <sec> [region] [universe] [delay] | evolve stop | eval <expr> | similar <expr> | cluster | families | usage [days] | template <n> [region] [universe] [delay] <模板，占位符 {field[:类别/数据集]} {window:5,10,20} {group}> | __INTERNAL_GET_DETAIL__ <expr>".to_string()));
                 }
                 AppCommand::Quit => {
                     let _ = evt_tx_bg.send(AppEvent::Message("收到退出命令".to_string()));
@@ -725,14 +1012,23 @@ async fn run_app_loop<B: ratatui::backend::Backend>(
                     app.clamp_selection();
                 }
                 AppEvent::Detail(dto) => {
-                    app.selected_detail = Some(dto);
+                    app.selected_detail = Some(*dto);
                 }
                 AppEvent::Stats(stats) => {
-                    app.backtest_stats = stats;
+                    app.backtest_stats = *stats;
                 }
                 AppEvent::FieldStatsRows(rows) => {
                     app.field_stats = rows;
                 }
+                AppEvent::Families(rows) => {
+                    app.families = rows;
+                }
+                AppEvent::Usage(rows) => {
+                    app.usage = rows;
+                }
+                AppEvent::Similar { expr, items } => {
+                    app.similar_alphas = Some((expr, items));
+                }
             }
         }
 
@@ -0,0 +1,185 @@
+//! CSV 面板数据：每个字段一个 `<字段名>.csv`，首列为日期，其余列为股票代码。
+//!
+//! 日期与股票轴以 `returns.csv` 为准；没有时用 `close.csv` 并由收盘价推算日收益。
+//! 分组字段（sector/industry 等）的取值可以是文本，读入时按出现顺序编号。
+
+use crate::panel::EvalError;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// 日期 × 股票 的数值矩阵，缺失为 NaN
+pub type Grid = Vec<Vec<f64>>;
+
+pub struct Panel {
+    dir: PathBuf,
+    pub dates: Vec<String>,
+    pub instruments: Vec<String>,
+    returns: Grid,
+    cache: HashMap<String, Grid>,
+}
+
+struct RawCsv {
+    instruments: Vec<String>,
+    rows: Vec<(String, Vec<String>)>,
+}
+
+impl Panel {
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, EvalError> {
+        let dir = dir.as_ref().to_path_buf();
+        let returns_path = dir.join("returns.csv");
+        let close_path = dir.join("close.csv");
+        let (raw, from_close) = if returns_path.is_file() {
+            (read_csv(&returns_path)?, false)
+        } else if close_path.is_file() {
+            (read_csv(&close_path)?, true)
+        } else {
+            return Err(EvalError::Io(format!(
+                "{} 下缺少 returns.csv 或 close.csv",
+                dir.display()
+            )));
+        };
+        let dates: Vec<String> = raw.rows.iter().map(|(d, _)| d.clone()).collect();
+        let instruments = raw.instruments.clone();
+        let mut panel = Self {
+            dir,
+            dates,
+            instruments,
+            returns: Vec::new(),
+            cache: HashMap::new(),
+        };
+        let grid = panel.align(raw);
+        panel.returns = if from_close {
+            let mut r = vec![vec![f64::NAN; panel.instruments.len()]; panel.dates.len()];
+            for (t, row) in r.iter_mut().enumerate().skip(1) {
+                for (i, cell) in row.iter_mut().enumerate() {
+                    let (prev, cur) = (grid[t - 1][i], grid[t][i]);
+                    if prev.is_finite() && cur.is_finite() && prev != 0.0 {
+                        *cell = cur / prev - 1.0;
+                    }
+                }
+            }
+            panel.cache.insert("close".to_string(), grid);
+            r
+        } else {
+            panel.cache.insert("returns".to_string(), grid.clone());
+            grid
+        };
+        Ok(panel)
+    }
+
+    pub fn returns(&self) -> &Grid {
+        &self.returns
+    }
+
+    pub fn shape(&self) -> (usize, usize) {
+        (self.dates.len(), self.instruments.len())
+    }
+
+    /// 读取字段（带缓存）；目录中没有该字段文件时返回 None
+    pub fn field(&mut self, name: &str) -> Result<Option<&Grid>, EvalError> {
+        if !self.cache.contains_key(name) {
+            let path = self.dir.join(format!("{}.csv", name));
+            if !path.is_file() {
+                return Ok(None);
+            }
+            let raw = read_csv(&path)?;
+            let grid = self.align(raw);
+            self.cache.insert(name.to_string(), grid);
+        }
+        Ok(self.cache.get(name))
+    }
+
+    /// 按面板的日期与股票轴对齐；文本值按出现顺序编号（用于分组字段）
+    fn align(&self, raw: RawCsv) -> Grid {
+        let date_idx: HashMap<&str, usize> = self
+            .dates
+            .iter()
+            .enumerate()
+            .map(|(i, d)| (d.as_str(), i))
+            .collect();
+        let inst_idx: HashMap<&str, usize> = self
+            .instruments
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (s.as_str(), i))
+            .collect();
+        let cols: Vec<Option<usize>> = raw
+            .instruments
+            .iter()
+            .map(|s| inst_idx.get(s.as_str()).copied())
+            .collect();
+        let mut labels: HashMap<String, f64> = HashMap::new();
+        let mut grid = vec![vec![f64::NAN; self.instruments.len()]; self.dates.len()];
+        for (date, cells) in raw.rows {
+            let Some(&t) = date_idx.get(date.as_str()) else {
+                continue;
+            };
+            for (cell, col) in cells.into_iter().zip(&cols) {
+                let Some(i) = *col else {
+                    continue;
+                };
+                grid[t][i] = parse_cell(&cell).unwrap_or_else(|| {
+                    let next = labels.len() as f64;
+                    *labels.entry(cell).or_insert(next)
+                });
+            }
+        }
+        grid
+    }
+}
+
+/// 空值/NaN 记为 NaN；非数值文本返回 None 交给调用方编号
+fn parse_cell(cell: &str) -> Option<f64> {
+    match cell {
+        "" | "nan" | "NaN" | "NA" | "N/A" | "null" => Some(f64::NAN),
+        s => s.parse::<f64>().ok(),
+    }
+}
+
+fn split_line(line: &str) -> Vec<String> {
+    line.split(',')
+        .map(|c| c.trim().trim_matches('"').to_string())
+        .collect()
+}
+
+fn read_csv(path: &Path) -> Result<RawCsv, EvalError> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| EvalError::Io(format!("{}: {}", path.display(), e)))?;
+    let mut lines = text.lines().filter(|l| !l.trim().is_empty());
+    let header = lines
+        .next()
+        .ok_or_else(|| EvalError::Io(format!("{}: 文件为空", path.display())))?;
+    let instruments: Vec<String> = split_line(header).into_iter().skip(1).collect();
+    let rows = lines
+        .map(|l| {
+            let mut cells = split_line(l);
+            let date = if cells.is_empty() {
+                String::new()
+            } else {
+                cells.remove(0)
+            };
+            (date, cells)
+        })
+        .collect();
+    Ok(RawCsv { instruments, rows })
+}
+
+#[cfg(test)]
+impl Panel {
+    /// 测试用：由内存矩阵构造面板，日期与股票轴按 `returns` 的形状编号
+    pub fn from_grids(returns: Grid, fields: Vec<(&str, Grid)>) -> Self {
+        let n = returns.first().map_or(0, |r| r.len());
+        let mut cache: HashMap<String, Grid> = fields
+            .into_iter()
+            .map(|(name, g)| (name.to_string(), g))
+            .collect();
+        cache.insert("returns".to_string(), returns.clone());
+        Self {
+            dir: PathBuf::new(),
+            dates: (0..returns.len()).map(|t| format!("d{}", t)).collect(),
+            instruments: (0..n).map(|i| format!("s{}", i)).collect(),
+            returns,
+            cache,
+        }
+    }
+}
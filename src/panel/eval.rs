@@ -0,0 +1,743 @@
+//! FASTEXPR 子集解释器：在面板数据上逐日计算表达式取值。
+//!
+//! 时序运算窗口不足时为 NaN（与平台一致）；截面与分组运算只使用当日有值的股票。
+
+use crate::expr::ast::{Arg, BinaryOp, Expr, ExprKind, Program, Stmt, UnaryOp};
+use crate::expr::checker::LOOKBACK_PARAMS;
+use crate::panel::data::{Grid, Panel};
+use crate::panel::EvalError;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    Scalar(f64),
+    Grid(Grid),
+}
+
+pub struct Evaluator<'p> {
+    panel: &'p mut Panel,
+    locals: HashMap<String, Value>,
+}
+
+impl<'p> Evaluator<'p> {
+    pub fn new(panel: &'p mut Panel) -> Self {
+        Self {
+            panel,
+            locals: HashMap::new(),
+        }
+    }
+
+    /// 依次执行语句，返回最后一条表达式的取值
+    pub fn run(&mut self, program: &Program) -> Result<Value, EvalError> {
+        let mut last = None;
+        for st in &program.statements {
+            match st {
+                Stmt::Assign { name, value, .. } => {
+                    let v = self.eval(value)?;
+                    self.locals.insert(name.clone(), v);
+                }
+                Stmt::Expr(e) => last = Some(self.eval(e)?),
+            }
+        }
+        last.ok_or_else(|| EvalError::BadArgs("表达式没有结果语句".to_string()))
+    }
+
+    fn eval(&mut self, e: &Expr) -> Result<Value, EvalError> {
+        match &e.kind {
+            ExprKind::Number { value, .. } => Ok(Value::Scalar(*value)),
+            ExprKind::Str(s) => Err(EvalError::BadArgs(format!(
+                "字符串参数 \"{}\" 只能作为命名参数使用",
+                s
+            ))),
+            ExprKind::Ident(name) => self.ident(name),
+            ExprKind::Unary { op, operand } => {
+                let v = self.eval(operand)?;
+                Ok(match op {
+                    UnaryOp::Neg => map1(v, |x| -x),
+                    UnaryOp::Not => map1(v, |x| bool_val(x, |b| !b)),
+                })
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                let (a, b) = (self.eval(lhs)?, self.eval(rhs)?);
+                let f: fn(f64, f64) -> f64 = match op {
+                    BinaryOp::Add => |a, b| a + b,
+                    BinaryOp::Sub => |a, b| a - b,
+                    BinaryOp::Mul => |a, b| a * b,
+                    BinaryOp::Div => div,
+                    BinaryOp::Pow => f64::powf,
+                    BinaryOp::Lt => |a, b| cmp(a, b, a < b),
+                    BinaryOp::Le => |a, b| cmp(a, b, a <= b),
+                    BinaryOp::Gt => |a, b| cmp(a, b, a > b),
+                    BinaryOp::Ge => |a, b| cmp(a, b, a >= b),
+                    BinaryOp::Eq => |a, b| cmp(a, b, a == b),
+                    BinaryOp::Ne => |a, b| cmp(a, b, a != b),
+                    BinaryOp::And => |a, b| cmp(a, b, a != 0.0 && b != 0.0),
+                    BinaryOp::Or => |a, b| cmp(a, b, a != 0.0 || b != 0.0),
+                };
+                Ok(map2(a, b, f))
+            }
+            ExprKind::Ternary {
+                cond,
+                then_branch,
+                else_branch,
+            } => {
+                let c = self.eval(cond)?;
+                let a = self.eval(then_branch)?;
+                let b = self.eval(else_branch)?;
+                Ok(select(c, a, b))
+            }
+            ExprKind::Call { name, args } => self.call(&name.to_ascii_lowercase(), args),
+        }
+    }
+
+    fn ident(&mut self, name: &str) -> Result<Value, EvalError> {
+        if let Some(v) = self.locals.get(name) {
+            return Ok(v.clone());
+        }
+        match name.to_ascii_lowercase().as_str() {
+            "nan" => return Ok(Value::Scalar(f64::NAN)),
+            "inf" => return Ok(Value::Scalar(f64::INFINITY)),
+            "true" => return Ok(Value::Scalar(1.0)),
+            "false" => return Ok(Value::Scalar(0.0)),
+            _ => {}
+        }
+        if let Some(g) = self.panel.field(name)? {
+            return Ok(Value::Grid(g.clone()));
+        }
+        if name.eq_ignore_ascii_case("market") {
+            let (t, n) = self.panel.shape();
+            return Ok(Value::Grid(vec![vec![0.0; n]; t]));
+        }
+        Err(EvalError::UnknownField(name.to_string()))
+    }
+
+    fn call(&mut self, name: &str, args: &[Arg]) -> Result<Value, EvalError> {
+        let positional: Vec<&Expr> = args
+            .iter()
+            .filter(|a| a.name.is_none())
+            .map(|a| &a.value)
+            .collect();
+        let named = |keys: &[&str]| -> Option<&Expr> {
+            args.iter()
+                .find(|a| a.name.as_deref().is_some_and(|k| keys.contains(&k)))
+                .map(|a| &a.value)
+        };
+        let need = |n: usize| -> Result<(), EvalError> {
+            if positional.len() < n {
+                Err(EvalError::BadArgs(format!(
+                    "{} 需要至少 {} 个位置参数",
+                    name, n
+                )))
+            } else {
+                Ok(())
+            }
+        };
+
+        // 逐元素运算
+        let unary: Option<fn(f64) -> f64> = match name {
+            "abs" => Some(f64::abs),
+            "log" => Some(|x| if x > 0.0 { x.ln() } else { f64::NAN }),
+            "sqrt" => Some(|x| if x >= 0.0 { x.sqrt() } else { f64::NAN }),
+            "sign" => Some(|x| {
+                if x.is_nan() || x == 0.0 {
+                    x
+                } else {
+                    x.signum()
+                }
+            }),
+            "exp" => Some(f64::exp),
+            "inverse" => Some(|x| div(1.0, x)),
+            "reverse" => Some(|x| -x),
+            "s_log_1p" => Some(|x| x.signum() * x.abs().ln_1p()),
+            "is_nan" => Some(|x| if x.is_nan() { 1.0 } else { 0.0 }),
+            "densify" => Some(|x| x),
+            _ => None,
+        };
+        if let Some(f) = unary {
+            need(1)?;
+            return Ok(map1(self.eval(positional[0])?, f));
+        }
+        let fold: Option<fn(f64, f64) -> f64> = match name {
+            "add" => Some(|a, b| a + b),
+            "multiply" => Some(|a, b| a * b),
+            "max" => Some(|a, b| {
+                if a.is_nan() || b.is_nan() {
+                    f64::NAN
+                } else {
+                    a.max(b)
+                }
+            }),
+            "min" => Some(|a, b| {
+                if a.is_nan() || b.is_nan() {
+                    f64::NAN
+                } else {
+                    a.min(b)
+                }
+            }),
+            "subtract" => Some(|a, b| a - b),
+            "divide" => Some(div),
+            "power" => Some(f64::powf),
+            "signed_power" => Some(|a, b| a.signum() * a.abs().powf(b)),
+            _ => None,
+        };
+        if let Some(f) = fold {
+            need(2)?;
+            let mut acc = self.eval(positional[0])?;
+            for e in &positional[1..] {
+                acc = map2(acc, self.eval(e)?, f);
+            }
+            return Ok(acc);
+        }
+
+        match name {
+            "if_else" => {
+                need(3)?;
+                let c = self.eval(positional[0])?;
+                let a = self.eval(positional[1])?;
+                let b = self.eval(positional[2])?;
+                Ok(select(c, a, b))
+            }
+            "rank" => {
+                need(1)?;
+                let x = self.grid(positional[0])?;
+                Ok(Value::Grid(by_row(&x, rank_row)))
+            }
+            "zscore" => {
+                need(1)?;
+                let x = self.grid(positional[0])?;
+                Ok(Value::Grid(by_row(&x, zscore_row)))
+            }
+            "scale" => {
+                need(1)?;
+                let x = self.grid(positional[0])?;
+                let target = self.scalar_or(named(&["scale"]), 1.0)?;
+                Ok(Value::Grid(by_row(&x, |row| {
+                    let total: f64 = row.iter().filter(|v| v.is_finite()).map(|v| v.abs()).sum();
+                    row.iter().map(|v| div(v * target, total)).collect()
+                })))
+            }
+            "normalize" => {
+                need(1)?;
+                let x = self.grid(positional[0])?;
+                let use_std = self.scalar_or(named(&["useStd", "usestd"]), 0.0)? != 0.0;
+                Ok(Value::Grid(by_row(&x, |row| {
+                    if use_std {
+                        zscore_row(row)
+                    } else {
+                        let (mean, _) = mean_std(row);
+                        row.iter().map(|v| v - mean).collect()
+                    }
+                })))
+            }
+            "winsorize" => {
+                need(1)?;
+                let x = self.grid(positional[0])?;
+                let k = self.scalar_or(named(&["std"]).or(positional.get(1).copied()), 4.0)?;
+                if !k.is_finite() || k < 0.0 {
+                    return Err(EvalError::BadArgs(
+                        "winsorize 的 std 需要非负常数".to_string(),
+                    ));
+                }
+                Ok(Value::Grid(by_row(&x, |row| winsorize_row(row, k))))
+            }
+            "ts_corr" => {
+                need(2)?;
+                let x = self.grid(positional[0])?;
+                let y = self.grid(positional[1])?;
+                let d = self
+                    .lookback(named(LOOKBACK_PARAMS.as_slice()).or(positional.get(2).copied()))?;
+                Ok(Value::Grid(ts_corr(&x, &y, d)))
+            }
+            "ts_delta" | "ts_delay" => {
+                need(1)?;
+                let x = self.grid(positional[0])?;
+                let d = self
+                    .lookback(named(LOOKBACK_PARAMS.as_slice()).or(positional.get(1).copied()))?;
+                let delta = name == "ts_delta";
+                let mut out = vec![vec![f64::NAN; x.first().map_or(0, |r| r.len())]; x.len()];
+                for t in d..x.len() {
+                    for i in 0..x[t].len() {
+                        out[t][i] = if delta {
+                            x[t][i] - x[t - d][i]
+                        } else {
+                            x[t - d][i]
+                        };
+                    }
+                }
+                Ok(Value::Grid(out))
+            }
+            _ if name.starts_with("ts_") => {
+                let f: fn(&[f64]) -> f64 = match name {
+                    "ts_mean" => |w| mean_std(w).0,
+                    "ts_std_dev" | "ts_std" => |w| mean_std(w).1,
+                    "ts_sum" => |w| finite(w).sum(),
+                    "ts_product" => |w| finite(w).product(),
+                    "ts_min" => |w| finite(w).fold(f64::NAN, f64::min),
+                    "ts_max" => |w| finite(w).fold(f64::NAN, f64::max),
+                    "ts_rank" => ts_rank_window,
+                    "ts_zscore" => |w| {
+                        let (mean, sd) = mean_std(w);
+                        div(w[w.len() - 1] - mean, sd)
+                    },
+                    "ts_decay_linear" => decay_linear_window,
+                    "ts_arg_max" => |w| arg_extreme(w, |a, b| a > b),
+                    "ts_arg_min" => |w| arg_extreme(w, |a, b| a < b),
+                    _ => return Err(EvalError::Unsupported(name.to_string())),
+                };
+                need(1)?;
+                let x = self.grid(positional[0])?;
+                let d = self
+                    .lookback(named(LOOKBACK_PARAMS.as_slice()).or(positional.get(1).copied()))?;
+                Ok(Value::Grid(ts_window(&x, d, f)))
+            }
+            "group_neutralize" | "group_rank" | "group_zscore" | "group_mean" => {
+                need(2)?;
+                let x = self.grid(positional[0])?;
+                let g = self.grid(positional[positional.len() - 1])?;
+                let f: fn(&[f64]) -> Vec<f64> = match name {
+                    "group_neutralize" => |row| {
+                        let (mean, _) = mean_std(row);
+                        row.iter().map(|v| v - mean).collect()
+                    },
+                    "group_rank" => rank_row,
+                    "group_zscore" => zscore_row,
+                    _ => |row| {
+                        let (mean, _) = mean_std(row);
+                        row.iter()
+                            .map(|v| if v.is_finite() { mean } else { f64::NAN })
+                            .collect()
+                    },
+                };
+                Ok(Value::Grid(by_group(&x, &g, f)))
+            }
+            _ => Err(EvalError::Unsupported(name.to_string())),
+        }
+    }
+
+    fn grid(&mut self, e: &Expr) -> Result<Grid, EvalError> {
+        Ok(match self.eval(e)? {
+            Value::Grid(g) => g,
+            Value::Scalar(v) => {
+                let (t, n) = self.panel.shape();
+                vec![vec![v; n]; t]
+            }
+        })
+    }
+
+    fn scalar_or(&mut self, e: Option<&Expr>, default: f64) -> Result<f64, EvalError> {
+        let Some(e) = e else {
+            return Ok(default);
+        };
+        match &e.kind {
+            ExprKind::Ident(s) | ExprKind::Str(s) if s.eq_ignore_ascii_case("true") => Ok(1.0),
+            ExprKind::Ident(s) | ExprKind::Str(s) if s.eq_ignore_ascii_case("false") => Ok(0.0),
+            _ => match self.eval(e)? {
+                Value::Scalar(v) => Ok(v),
+                Value::Grid(_) => Err(EvalError::BadArgs("此处参数需要常数".to_string())),
+            },
+        }
+    }
+
+    fn lookback(&mut self, e: Option<&Expr>) -> Result<usize, EvalError> {
+        let d = self.scalar_or(e, f64::NAN)?;
+        if !d.is_finite() || d < 1.0 {
+            return Err(EvalError::BadArgs("时序运算需要正整数回看窗口".to_string()));
+        }
+        Ok(d.round() as usize)
+    }
+}
+
+fn div(a: f64, b: f64) -> f64 {
+    if b == 0.0 {
+        f64::NAN
+    } else {
+        a / b
+    }
+}
+
+fn cmp(a: f64, b: f64, r: bool) -> f64 {
+    if a.is_nan() || b.is_nan() {
+        f64::NAN
+    } else if r {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+fn bool_val(x: f64, f: impl Fn(bool) -> bool) -> f64 {
+    if x.is_nan() {
+        f64::NAN
+    } else if f(x != 0.0) {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+fn map1(v: Value, f: impl Fn(f64) -> f64) -> Value {
+    match v {
+        Value::Scalar(x) => Value::Scalar(f(x)),
+        Value::Grid(g) => Value::Grid(
+            g.into_iter()
+                .map(|row| row.into_iter().map(&f).collect())
+                .collect(),
+        ),
+    }
+}
+
+fn map2(a: Value, b: Value, f: impl Fn(f64, f64) -> f64) -> Value {
+    match (a, b) {
+        (Value::Scalar(x), Value::Scalar(y)) => Value::Scalar(f(x, y)),
+        (Value::Grid(g), Value::Scalar(y)) => map1(Value::Grid(g), |x| f(x, y)),
+        (Value::Scalar(x), Value::Grid(g)) => map1(Value::Grid(g), |y| f(x, y)),
+        (Value::Grid(g), Value::Grid(h)) => Value::Grid(
+            g.iter()
+                .zip(&h)
+                .map(|(r, s)| r.iter().zip(s).map(|(x, y)| f(*x, *y)).collect())
+                .collect(),
+        ),
+    }
+}
+
+fn select(c: Value, a: Value, b: Value) -> Value {
+    // 先把条件与两个分支打包成逐元素的 (c, a, b)，再按条件取值
+    let pick = |c: f64, a: f64, b: f64| {
+        if c.is_nan() {
+            f64::NAN
+        } else if c != 0.0 {
+            a
+        } else {
+            b
+        }
+    };
+    match (c, a, b) {
+        (Value::Scalar(c), a, b) => {
+            if c.is_nan() {
+                Value::Scalar(f64::NAN)
+            } else if c != 0.0 {
+                a
+            } else {
+                b
+            }
+        }
+        (Value::Grid(cg), a, b) => {
+            let at = |v: &Value, t: usize, i: usize| match v {
+                Value::Scalar(x) => *x,
+                Value::Grid(g) => g[t][i],
+            };
+            Value::Grid(
+                cg.iter()
+                    .enumerate()
+                    .map(|(t, row)| {
+                        row.iter()
+                            .enumerate()
+                            .map(|(i, c)| pick(*c, at(&a, t, i), at(&b, t, i)))
+                            .collect()
+                    })
+                    .collect(),
+            )
+        }
+    }
+}
+
+fn finite(w: &[f64]) -> impl Iterator<Item = f64> + '_ {
+    w.iter().copied().filter(|v| v.is_finite())
+}
+
+/// 有值元素的均值与总体标准差；没有有值元素时为 NaN
+fn mean_std(w: &[f64]) -> (f64, f64) {
+    let (n, sum) = finite(w).fold((0usize, 0.0), |(n, s), v| (n + 1, s + v));
+    if n == 0 {
+        return (f64::NAN, f64::NAN);
+    }
+    let mean = sum / n as f64;
+    let var = finite(w).map(|v| (v - mean).powi(2)).sum::<f64>() / n as f64;
+    (mean, var.sqrt())
+}
+
+fn by_row(x: &Grid, f: impl Fn(&[f64]) -> Vec<f64>) -> Grid {
+    x.iter().map(|row| f(row)).collect()
+}
+
+/// 截面百分位排名，取值 0..=1，并列取平均名次
+fn rank_row(row: &[f64]) -> Vec<f64> {
+    let mut idx: Vec<usize> = (0..row.len()).filter(|&i| row[i].is_finite()).collect();
+    idx.sort_by(|&a, &b| row[a].total_cmp(&row[b]));
+    let mut out = vec![f64::NAN; row.len()];
+    let m = idx.len();
+    let mut k = 0;
+    while k < m {
+        let mut j = k;
+        while j + 1 < m && row[idx[j + 1]] == row[idx[k]] {
+            j += 1;
+        }
+        let pos = (k + j) as f64 / 2.0;
+        for &i in &idx[k..=j] {
+            out[i] = if m > 1 { pos / (m - 1) as f64 } else { 0.5 };
+        }
+        k = j + 1;
+    }
+    out
+}
+
+/// 截断到均值 ± k 倍标准差；当日没有有值股票（标准差非有限）时原样返回
+fn winsorize_row(row: &[f64], k: f64) -> Vec<f64> {
+    let (mean, sd) = mean_std(row);
+    if !sd.is_finite() {
+        return row.to_vec();
+    }
+    row.iter()
+        .map(|v| v.clamp(mean - k * sd, mean + k * sd))
+        .collect()
+}
+
+fn zscore_row(row: &[f64]) -> Vec<f64> {
+    let (mean, sd) = mean_std(row);
+    row.iter().map(|v| div(v - mean, sd)).collect()
+}
+
+fn by_group(x: &Grid, g: &Grid, f: fn(&[f64]) -> Vec<f64>) -> Grid {
+    x.iter()
+        .zip(g)
+        .map(|(row, groups)| {
+            let mut members: HashMap<u64, Vec<usize>> = HashMap::new();
+            for (i, gid) in groups.iter().enumerate() {
+                if gid.is_finite() {
+                    members.entry(gid.to_bits()).or_default().push(i);
+                }
+            }
+            let mut out = vec![f64::NAN; row.len()];
+            for idx in members.values() {
+                let vals: Vec<f64> = idx.iter().map(|&i| row[i]).collect();
+                for (&i, v) in idx.iter().zip(f(&vals)) {
+                    out[i] = v;
+                }
+            }
+            out
+        })
+        .collect()
+}
+
+/// 对每只股票的最近 `d` 天（含当天）窗口应用 `f`；历史不足 `d` 天时为 NaN
+fn ts_window(x: &Grid, d: usize, f: fn(&[f64]) -> f64) -> Grid {
+    let n = x.first().map_or(0, |r| r.len());
+    let mut out = vec![vec![f64::NAN; n]; x.len()];
+    let mut window = Vec::with_capacity(d);
+    for (t, row) in out.iter_mut().enumerate().skip(d.saturating_sub(1)) {
+        for (i, cell) in row.iter_mut().enumerate() {
+            window.clear();
+            window.extend((t + 1 - d..=t).map(|s| x[s][i]));
+            *cell = f(&window);
+        }
+    }
+    out
+}
+
+fn ts_rank_window(w: &[f64]) -> f64 {
+    let last = w[w.len() - 1];
+    if !last.is_finite() {
+        return f64::NAN;
+    }
+    let vals: Vec<f64> = finite(w).collect();
+    if vals.len() < 2 {
+        return 0.5;
+    }
+    let below = vals.iter().filter(|v| **v < last).count() as f64;
+    let ties = vals.iter().filter(|v| **v == last).count() as f64 - 1.0;
+    (below + ties / 2.0) / (vals.len() - 1) as f64
+}
+
+/// 线性衰减加权均值，最近一天权重为 d，最早一天为 1
+fn decay_linear_window(w: &[f64]) -> f64 {
+    let (mut num, mut den) = (0.0, 0.0);
+    for (k, v) in w.iter().enumerate() {
+        if v.is_finite() {
+            let weight = (k + 1) as f64;
+            num += weight * v;
+            den += weight;
+        }
+    }
+    div(num, den)
+}
+
+/// 极值距今的天数（当天为 0）
+fn arg_extreme(w: &[f64], better: fn(f64, f64) -> bool) -> f64 {
+    let mut best: Option<(usize, f64)> = None;
+    for (k, v) in w.iter().enumerate() {
+        if v.is_finite() && best.is_none_or(|(_, b)| better(*v, b)) {
+            best = Some((k, *v));
+        }
+    }
+    best.map_or(f64::NAN, |(k, _)| (w.len() - 1 - k) as f64)
+}
+
+fn ts_corr(x: &Grid, y: &Grid, d: usize) -> Grid {
+    let n = x.first().map_or(0, |r| r.len());
+    let mut out = vec![vec![f64::NAN; n]; x.len()];
+    for (t, row) in out.iter_mut().enumerate().skip(d.saturating_sub(1)) {
+        for (i, cell) in row.iter_mut().enumerate() {
+            let pairs: Vec<(f64, f64)> = (t + 1 - d..=t)
+                .map(|s| (x[s][i], y[s][i]))
+                .filter(|(a, b)| a.is_finite() && b.is_finite())
+                .collect();
+            if pairs.len() < 2 {
+                continue;
+            }
+            let m = pairs.len() as f64;
+            let (mx, my) = pairs
+                .iter()
+                .fold((0.0, 0.0), |(sx, sy), (a, b)| (sx + a, sy + b));
+            let (mx, my) = (mx / m, my / m);
+            let (mut sxy, mut sxx, mut syy) = (0.0, 0.0, 0.0);
+            for (a, b) in &pairs {
+                sxy += (a - mx) * (b - my);
+                sxx += (a - mx).powi(2);
+                syy += (b - my).powi(2);
+            }
+            *cell = div(sxy, (sxx * syy).sqrt());
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAN: f64 = f64::NAN;
+
+    fn assert_row(actual: &[f64], expected: &[f64]) {
+        assert_eq!(
+            actual.len(),
+            expected.len(),
+            "{:?} vs {:?}",
+            actual,
+            expected
+        );
+        for (a, e) in actual.iter().zip(expected) {
+            assert!(
+                (a.is_nan() && e.is_nan()) || (a - e).abs() < 1e-9,
+                "{:?} vs {:?}",
+                actual,
+                expected
+            );
+        }
+    }
+
+    /// 4 天 × 3 只股票的小面板
+    fn panel() -> Panel {
+        let returns = vec![vec![0.0; 3]; 4];
+        let x = vec![
+            vec![1.0, 10.0, NAN],
+            vec![2.0, 20.0, NAN],
+            vec![3.0, 30.0, NAN],
+            vec![4.0, 40.0, 5.0],
+        ];
+        let g = vec![vec![0.0, 0.0, 1.0]; 4];
+        Panel::from_grids(returns, vec![("x", x), ("sector", g)])
+    }
+
+    fn eval_grid(expr: &str) -> Result<Grid, EvalError> {
+        let mut p = panel();
+        let program = crate::expr::parse(expr).unwrap();
+        match Evaluator::new(&mut p).run(&program)? {
+            Value::Grid(g) => Ok(g),
+            Value::Scalar(v) => panic!("expected grid, got {}", v),
+        }
+    }
+
+    #[test]
+    fn rank_row_averages_ties_and_skips_nan() {
+        assert_row(
+            &rank_row(&[3.0, 1.0, NAN, 2.0, 2.0]),
+            &[1.0, 0.0, NAN, 0.5, 0.5],
+        );
+        assert_row(&rank_row(&[7.0, NAN]), &[0.5, NAN]);
+        assert_row(&rank_row(&[NAN, NAN]), &[NAN, NAN]);
+    }
+
+    #[test]
+    fn ts_window_warm_up_is_nan() {
+        let g = eval_grid("ts_mean(x, 3)").unwrap();
+        let col: Vec<f64> = g.iter().map(|r| r[0]).collect();
+        assert_row(&col, &[NAN, NAN, 2.0, 3.0]);
+        // 窗口内缺失值跳过
+        let col: Vec<f64> = g.iter().map(|r| r[2]).collect();
+        assert_row(&col, &[NAN, NAN, NAN, 5.0]);
+
+        let g = eval_grid("ts_delta(x, 1)").unwrap();
+        assert_row(&g[0], &[NAN, NAN, NAN]);
+        assert_row(&g[1], &[1.0, 10.0, NAN]);
+        assert!(matches!(
+            eval_grid("ts_mean(x, 0)"),
+            Err(EvalError::BadArgs(_))
+        ));
+    }
+
+    #[test]
+    fn by_group_works_within_groups() {
+        let x = vec![vec![1.0, 2.0, 3.0, 4.0, 5.0]];
+        let g = vec![vec![0.0, 0.0, 1.0, NAN, 1.0]];
+        let neutral = by_group(&x, &g, |row| {
+            let (mean, _) = mean_std(row);
+            row.iter().map(|v| v - mean).collect()
+        });
+        assert_row(&neutral[0], &[-0.5, 0.5, -1.0, NAN, 1.0]);
+        let ranked = by_group(&x, &g, rank_row);
+        assert_row(&ranked[0], &[0.0, 1.0, 0.0, NAN, 1.0]);
+
+        let g = eval_grid("group_rank(x, sector)").unwrap();
+        assert_row(&g[3], &[0.0, 1.0, 0.5]);
+    }
+
+    #[test]
+    fn winsorize_clamps_and_survives_nan_rows() {
+        assert_row(
+            &winsorize_row(&[0.0, 0.0, 0.0, 0.0, 100.0], 1.0),
+            &[0.0, 0.0, 0.0, 0.0, 60.0],
+        );
+        assert_row(&winsorize_row(&[NAN, NAN], 4.0), &[NAN, NAN]);
+        assert_row(&winsorize_row(&[1.0, NAN], 0.0), &[1.0, NAN]);
+
+        // 前 3 天第三列全缺失，不应 panic
+        let g = eval_grid("winsorize(x, std=1)").unwrap();
+        assert_row(&g[0], &[1.0, 10.0, NAN]);
+        assert!(matches!(
+            eval_grid("winsorize(x, std=-1)"),
+            Err(EvalError::BadArgs(_))
+        ));
+        assert!(matches!(
+            eval_grid("winsorize(x, std=nan)"),
+            Err(EvalError::BadArgs(_))
+        ));
+    }
+
+    #[test]
+    fn report_pnl_and_turnover() {
+        let alpha = vec![
+            vec![1.0, -1.0],
+            vec![-1.0, 1.0],
+            vec![NAN, NAN],
+            vec![1.0, 2.0],
+        ];
+        let returns = vec![
+            vec![0.0, 0.0],
+            vec![0.02, -0.02],
+            vec![0.01, 0.03],
+            vec![0.05, 0.05],
+        ];
+        let r = crate::panel::Report::compute(&alpha, &returns);
+        // 权重 [0.5,-0.5] -> [-0.5,0.5] -> 空仓；PnL 0.02, 0.01, 0
+        assert_eq!(r.days, 3);
+        assert!((r.annual_return - 0.01 * 252.0).abs() < 1e-9);
+        let sd = (((0.01f64).powi(2) + 0.0 + (0.01f64).powi(2)) / 2.0).sqrt();
+        assert!((r.sharpe - 0.01 / sd * 252f64.sqrt()).abs() < 1e-9);
+        // 换手：|Δw| 之和 2（翻仓）与 1（清仓）
+        assert!((r.turnover - 1.5).abs() < 1e-9);
+        assert!((r.coverage - 0.75).abs() < 1e-9);
+        assert!((r.flat_ratio - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(r.max_drawdown, 0.0);
+        assert!(r.problems(0.3).is_empty());
+    }
+}
@@ -0,0 +1,85 @@
+//! 本地表达式评估：在用户提供的 CSV 面板上运行 FASTEXPR 子集，
+//! 得到简易多空 PnL、Sharpe、换手与覆盖率，用于在消耗回测额度前筛掉明显无效的表达式。
+//!
+//! 面板目录 PANEL_DIR（默认 data/panel），覆盖率下限 LOCAL_EVAL_MIN_COVERAGE（默认 0.3），
+//! LOCAL_EVAL_GATE=1 时生成结果入队前先做本地筛查。
+
+pub mod data;
+pub mod eval;
+pub mod report;
+
+pub use data::Panel;
+pub use report::Report;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+#[derive(thiserror::Error, Debug)]
+pub enum EvalError {
+    #[error("表达式无法解析: {0}")]
+    Parse(String),
+    #[error("读取面板数据失败: {0}")]
+    Io(String),
+    #[error("面板中没有字段: {0}")]
+    UnknownField(String),
+    #[error("本地评估不支持运算符: {0}")]
+    Unsupported(String),
+    #[error("{0}")]
+    BadArgs(String),
+}
+
+pub fn panel_dir() -> PathBuf {
+    std::env::var("PANEL_DIR")
+        .ok()
+        .filter(|s| !s.trim().is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("data/panel"))
+}
+
+pub fn min_coverage() -> f64 {
+    std::env::var("LOCAL_EVAL_MIN_COVERAGE")
+        .ok()
+        .and_then(|s| s.trim().parse::<f64>().ok())
+        .unwrap_or(0.3)
+}
+
+/// 在面板上评估表达式；结果为常数时按全截面相同处理（没有持仓）
+pub fn evaluate(panel: &mut Panel, expr: &str) -> Result<Report, EvalError> {
+    let program = crate::expr::parse(expr).map_err(|e| EvalError::Parse(e.describe(expr)))?;
+    let alpha = match eval::Evaluator::new(panel).run(&program)? {
+        eval::Value::Grid(g) => g,
+        eval::Value::Scalar(v) => {
+            let (t, n) = panel.shape();
+            vec![vec![v; n]; t]
+        }
+    };
+    Ok(Report::compute(&alpha, panel.returns()))
+}
+
+pub fn gate_enabled() -> bool {
+    std::env::var("LOCAL_EVAL_GATE")
+        .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+}
+
+/// 批量筛查，返回 表达式 -> 拒绝原因；面板缺字段或不支持的运算符无从判断，不计为拒绝
+pub fn screen(exprs: &[String]) -> Result<HashMap<String, String>, EvalError> {
+    let mut panel = Panel::open(panel_dir())?;
+    let floor = min_coverage();
+    let mut out = HashMap::new();
+    for e in exprs {
+        match evaluate(&mut panel, e) {
+            Ok(r) => {
+                let problems = r.problems(floor);
+                if !problems.is_empty() {
+                    out.insert(e.clone(), problems.join("；"));
+                }
+            }
+            Err(EvalError::UnknownField(_)) | Err(EvalError::Unsupported(_)) => {}
+            Err(err) => {
+                out.insert(e.clone(), err.to_string());
+            }
+        }
+    }
+    Ok(out)
+}
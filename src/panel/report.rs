@@ -0,0 +1,147 @@
+//! 由 Alpha 取值与日收益计算简易多空组合表现。
+//!
+//! 每日把有值的 Alpha 去均值后按绝对值之和归一为权重（多空各半、总敞口 1），
+//! 以当日权重乘次日收益得到 PnL；年化按 252 个交易日。
+
+use crate::panel::data::Grid;
+
+const TRADING_DAYS: f64 = 252.0;
+
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    /// 计入 PnL 的交易日数
+    pub days: usize,
+    pub sharpe: f64,
+    pub annual_return: f64,
+    /// 日均换手（权重变化绝对值之和）
+    pub turnover: f64,
+    /// 日均有值股票占比
+    pub coverage: f64,
+    pub max_drawdown: f64,
+    /// 没有持仓（信号全缺失或截面恒定）的交易日占比
+    pub flat_ratio: f64,
+}
+
+impl Report {
+    pub fn compute(alpha: &Grid, returns: &Grid) -> Self {
+        let weights: Vec<Vec<f64>> = alpha.iter().map(|row| weights(row)).collect();
+        let n_days = alpha.len();
+        let n = alpha.first().map_or(0, |r| r.len()).max(1);
+
+        let coverage = if n_days == 0 {
+            0.0
+        } else {
+            alpha
+                .iter()
+                .map(|row| row.iter().filter(|v| v.is_finite()).count() as f64 / n as f64)
+                .sum::<f64>()
+                / n_days as f64
+        };
+
+        let mut pnl = Vec::new();
+        let mut turnover = Vec::new();
+        let mut flat = 0usize;
+        for t in 0..n_days.saturating_sub(1) {
+            let w = &weights[t];
+            if w.iter().all(|x| *x == 0.0) {
+                flat += 1;
+            }
+            pnl.push(
+                w.iter()
+                    .zip(&returns[t + 1])
+                    .filter(|(_, r)| r.is_finite())
+                    .map(|(w, r)| w * r)
+                    .sum::<f64>(),
+            );
+            if t > 0 {
+                turnover.push(
+                    w.iter()
+                        .zip(&weights[t - 1])
+                        .map(|(a, b)| (a - b).abs())
+                        .sum::<f64>(),
+                );
+            }
+        }
+
+        let days = pnl.len();
+        let avg = mean(&pnl);
+        let sd = if days > 1 {
+            (pnl.iter().map(|p| (p - avg).powi(2)).sum::<f64>() / (days - 1) as f64).sqrt()
+        } else {
+            0.0
+        };
+        let (mut equity, mut peak, mut max_drawdown) = (0.0f64, 0.0f64, 0.0f64);
+        for p in &pnl {
+            equity += p;
+            peak = peak.max(equity);
+            max_drawdown = max_drawdown.max(peak - equity);
+        }
+        Self {
+            days,
+            sharpe: if sd > 0.0 {
+                avg / sd * TRADING_DAYS.sqrt()
+            } else {
+                0.0
+            },
+            annual_return: avg * TRADING_DAYS,
+            turnover: mean(&turnover),
+            coverage,
+            max_drawdown,
+            flat_ratio: if days > 0 {
+                flat as f64 / days as f64
+            } else {
+                1.0
+            },
+        }
+    }
+
+    /// 明显无效的迹象；为空表示未发现问题
+    pub fn problems(&self, min_coverage: f64) -> Vec<String> {
+        let mut out = Vec::new();
+        if self.days == 0 {
+            out.push("面板天数不足，无法计算 PnL".to_string());
+            return out;
+        }
+        if self.coverage < min_coverage {
+            out.push(format!(
+                "覆盖率 {:.0}% 低于 {:.0}%",
+                self.coverage * 100.0,
+                min_coverage * 100.0
+            ));
+        }
+        if self.flat_ratio >= 0.5 {
+            out.push(format!(
+                "{:.0}% 的交易日没有持仓（信号缺失或截面恒定）",
+                self.flat_ratio * 100.0
+            ));
+        }
+        if self.turnover == 0.0 && self.flat_ratio < 1.0 {
+            out.push("持仓从不变化".to_string());
+        }
+        out
+    }
+}
+
+/// 去均值并按绝对值之和归一；有值股票不足两只或截面恒定时全为 0
+fn weights(row: &[f64]) -> Vec<f64> {
+    let vals: Vec<f64> = row.iter().copied().filter(|v| v.is_finite()).collect();
+    if vals.len() < 2 {
+        return vec![0.0; row.len()];
+    }
+    let m = mean(&vals);
+    let gross: f64 = vals.iter().map(|v| (v - m).abs()).sum();
+    if gross <= f64::EPSILON {
+        return vec![0.0; row.len()];
+    }
+    row.iter()
+        .map(|v| if v.is_finite() { (v - m) / gross } else { 0.0 })
+        .collect()
+}
+
+fn mean(v: &[f64]) -> f64 {
+    if v.is_empty() {
+        0.0
+    } else {
+        v.iter().sum::<f64>() / v.len() as f64
+    }
+}
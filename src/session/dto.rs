@@ -17,6 +17,12 @@ pub struct AlphaDetailResponse {
     pub settings: Value,
     pub regular: Value,
     pub is: Option<Value>,
+    #[serde(default)]
+    pub os: Option<Value>,
+    #[serde(default)]
+    pub train: Option<Value>,
+    #[serde(default)]
+    pub test: Option<Value>,
     #[serde(rename = "dateCreated")]
     pub date_created: String,
 }
@@ -37,6 +37,7 @@ pub async fn establish_connection(db_url: &str) -> Result<DatabaseConnection, Db
             .if_not_exists(),
     );
     db.execute(stmt).await?;
+    ensure_alphas_columns(&db).await?;
 
     // Backtest Jobs table
     let stmt = builder.build(
@@ -47,6 +48,114 @@ pub async fn establish_connection(db_url: &str) -> Result<DatabaseConnection, Db
     db.execute(stmt).await?;
     ensure_backtest_jobs_columns(&db).await?;
 
+    // Backtest Job Transitions table（状态迁移审计）
+    let stmt = builder.build(
+        schema
+            .create_table_from_entity(crate::storage::entity::backtest_job_transition::Entity)
+            .if_not_exists(),
+    );
+    db.execute(stmt).await?;
+    let _ = sea_orm::ConnectionTrait::execute(
+        &db,
+        sea_orm::Statement::from_string(
+            sea_orm::DatabaseBackend::Sqlite,
+            "CREATE INDEX IF NOT EXISTS idx_backtest_job_transitions_job ON backtest_job_transitions(job_id, created_at);".to_string(),
+        ),
+    )
+    .await?;
+    let _ = sea_orm::ConnectionTrait::execute(
+        &db,
+        sea_orm::Statement::from_string(
+            sea_orm::DatabaseBackend::Sqlite,
+            "CREATE INDEX IF NOT EXISTS idx_backtest_job_transitions_to ON backtest_job_transitions(to_status, created_at);".to_string(),
+        ),
+    )
+    .await?;
+
+    // Alpha Lineage table（进化子代 -> 父代）
+    let stmt = builder.build(
+        schema
+            .create_table_from_entity(crate::storage::entity::alpha_lineage::Entity)
+            .if_not_exists(),
+    );
+    db.execute(stmt).await?;
+    let _ = sea_orm::ConnectionTrait::execute(
+        &db,
+        sea_orm::Statement::from_string(
+            sea_orm::DatabaseBackend::Sqlite,
+            "CREATE INDEX IF NOT EXISTS idx_alpha_lineage_child ON alpha_lineage(child_expression);".to_string(),
+        ),
+    )
+    .await?;
+
+    // Alpha Provenance table（来源：生成/模板/进化/抓取/手动回测）
+    let stmt = builder.build(
+        schema
+            .create_table_from_entity(crate::storage::entity::alpha_provenance::Entity)
+            .if_not_exists(),
+    );
+    db.execute(stmt).await?;
+    let _ = sea_orm::ConnectionTrait::execute(
+        &db,
+        sea_orm::Statement::from_string(
+            sea_orm::DatabaseBackend::Sqlite,
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_alpha_provenance_expr ON alpha_provenance(expression);".to_string(),
+        ),
+    )
+    .await?;
+    ensure_alpha_provenance_columns(&db).await?;
+
+    // LLM Usage table（每次生成请求的 token/耗时/费用）
+    let stmt = builder.build(
+        schema
+            .create_table_from_entity(crate::storage::entity::llm_usage::Entity)
+            .if_not_exists(),
+    );
+    db.execute(stmt).await?;
+    let _ = sea_orm::ConnectionTrait::execute(
+        &db,
+        sea_orm::Statement::from_string(
+            sea_orm::DatabaseBackend::Sqlite,
+            "CREATE INDEX IF NOT EXISTS idx_llm_usage_created ON llm_usage(created_at);"
+                .to_string(),
+        ),
+    )
+    .await?;
+    ensure_llm_usage_columns(&db).await?;
+
+    // LLM Reasoning table（推理模型思考过程存档，按生成批次）
+    let stmt = builder.build(
+        schema
+            .create_table_from_entity(crate::storage::entity::llm_reasoning::Entity)
+            .if_not_exists(),
+    );
+    db.execute(stmt).await?;
+    let _ = sea_orm::ConnectionTrait::execute(
+        &db,
+        sea_orm::Statement::from_string(
+            sea_orm::DatabaseBackend::Sqlite,
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_llm_reasoning_batch ON llm_reasoning(batch_id);"
+                .to_string(),
+        ),
+    )
+    .await?;
+
+    // Simulation Budget table（每日/每小时模拟次数计数）
+    let stmt = builder.build(
+        schema
+            .create_table_from_entity(crate::storage::entity::simulation_budget::Entity)
+            .if_not_exists(),
+    );
+    db.execute(stmt).await?;
+    let _ = sea_orm::ConnectionTrait::execute(
+        &db,
+        sea_orm::Statement::from_string(
+            sea_orm::DatabaseBackend::Sqlite,
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_simulation_budget_key ON simulation_budget(account, period_key);".to_string(),
+        ),
+    )
+    .await?;
+
     // Data Fields table
     let stmt = builder.build(
         schema
@@ -123,7 +232,166 @@ async fn ensure_backtest_jobs_columns(db: &DatabaseConnection) -> Result<(), DbE
         ))
         .await?;
     }
+    if !cols.contains("delay") {
+        db.execute(sea_orm::Statement::from_string(
+            backend,
+            "ALTER TABLE backtest_jobs ADD COLUMN delay INTEGER NOT NULL DEFAULT 1;".to_string(),
+        ))
+        .await?;
+    }
+    if !cols.contains("canonical_hash") {
+        db.execute(sea_orm::Statement::from_string(
+            backend,
+            "ALTER TABLE backtest_jobs ADD COLUMN canonical_hash TEXT;".to_string(),
+        ))
+        .await?;
+    }
+    db.execute(sea_orm::Statement::from_string(
+        backend,
+        "CREATE INDEX IF NOT EXISTS idx_backtest_jobs_canonical_hash ON backtest_jobs(canonical_hash);"
+            .to_string(),
+    ))
+    .await?;
+
+    Ok(())
+}
 
+async fn ensure_alphas_columns(db: &DatabaseConnection) -> Result<(), DbErr> {
+    let backend = db.get_database_backend();
+    if backend != sea_orm::DatabaseBackend::Sqlite {
+        return Ok(());
+    }
+    let rows = db
+        .query_all(sea_orm::Statement::from_string(
+            backend,
+            "PRAGMA table_info(alphas);".to_string(),
+        ))
+        .await?;
+    let mut cols = std::collections::HashSet::new();
+    for row in rows {
+        if let Ok(name) = row.try_get::<String>("", "name") {
+            cols.insert(name);
+        }
+    }
+    if !cols.contains("canonical_hash") {
+        db.execute(sea_orm::Statement::from_string(
+            backend,
+            "ALTER TABLE alphas ADD COLUMN canonical_hash TEXT;".to_string(),
+        ))
+        .await?;
+    }
+    for period in ["os", "train", "test"] {
+        for metric in ["sharpe", "fitness", "turnover", "returns"] {
+            let col = format!("{}_{}", period, metric);
+            if !cols.contains(&col) {
+                db.execute(sea_orm::Statement::from_string(
+                    backend,
+                    format!("ALTER TABLE alphas ADD COLUMN {} REAL;", col),
+                ))
+                .await?;
+            }
+        }
+    }
+    for col in [
+        "expr_depth",
+        "distinct_fields",
+        "distinct_categories",
+        "max_lookback",
+    ] {
+        if !cols.contains(col) {
+            db.execute(sea_orm::Statement::from_string(
+                backend,
+                format!("ALTER TABLE alphas ADD COLUMN {} INTEGER;", col),
+            ))
+            .await?;
+        }
+    }
+    if !cols.contains("family_id") {
+        db.execute(sea_orm::Statement::from_string(
+            backend,
+            "ALTER TABLE alphas ADD COLUMN family_id TEXT;".to_string(),
+        ))
+        .await?;
+    }
+    db.execute(sea_orm::Statement::from_string(
+        backend,
+        "CREATE INDEX IF NOT EXISTS idx_alphas_canonical_hash ON alphas(canonical_hash);"
+            .to_string(),
+    ))
+    .await?;
+    db.execute(sea_orm::Statement::from_string(
+        backend,
+        "CREATE INDEX IF NOT EXISTS idx_alphas_family_id ON alphas(family_id);".to_string(),
+    ))
+    .await?;
+    Ok(())
+}
+
+async fn ensure_alpha_provenance_columns(db: &DatabaseConnection) -> Result<(), DbErr> {
+    let backend = db.get_database_backend();
+    if backend != sea_orm::DatabaseBackend::Sqlite {
+        return Ok(());
+    }
+    let rows = db
+        .query_all(sea_orm::Statement::from_string(
+            backend,
+            "PRAGMA table_info(alpha_provenance);".to_string(),
+        ))
+        .await?;
+    let mut cols = std::collections::HashSet::new();
+    for row in rows {
+        if let Ok(name) = row.try_get::<String>("", "name") {
+            cols.insert(name);
+        }
+    }
+    if !cols.contains("hypothesis") {
+        db.execute(sea_orm::Statement::from_string(
+            backend,
+            "ALTER TABLE alpha_provenance ADD COLUMN hypothesis TEXT;".to_string(),
+        ))
+        .await?;
+    }
+    for col in ["region", "universe"] {
+        if !cols.contains(col) {
+            db.execute(sea_orm::Statement::from_string(
+                backend,
+                format!("ALTER TABLE alpha_provenance ADD COLUMN {} TEXT;", col),
+            ))
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+async fn ensure_llm_usage_columns(db: &DatabaseConnection) -> Result<(), DbErr> {
+    let backend = db.get_database_backend();
+    if backend != sea_orm::DatabaseBackend::Sqlite {
+        return Ok(());
+    }
+    let rows = db
+        .query_all(sea_orm::Statement::from_string(
+            backend,
+            "PRAGMA table_info(llm_usage);".to_string(),
+        ))
+        .await?;
+    let mut cols = std::collections::HashSet::new();
+    for row in rows {
+        if let Ok(name) = row.try_get::<String>("", "name") {
+            cols.insert(name);
+        }
+    }
+    for col in ["repair_attempted", "repair_rescued"] {
+        if !cols.contains(col) {
+            db.execute(sea_orm::Statement::from_string(
+                backend,
+                format!(
+                    "ALTER TABLE llm_usage ADD COLUMN {} INTEGER NOT NULL DEFAULT 0;",
+                    col
+                ),
+            ))
+            .await?;
+        }
+    }
     Ok(())
 }
 
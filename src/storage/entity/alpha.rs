@@ -31,9 +31,57 @@ pub struct Model {
     #[sea_orm(nullable)]
     pub is_pnl: Option<f64>,
 
+    // 样本外 OS 指标（平台返回时才有）
+    #[sea_orm(nullable)]
+    pub os_sharpe: Option<f64>,
+    #[sea_orm(nullable)]
+    pub os_fitness: Option<f64>,
+    #[sea_orm(nullable)]
+    pub os_turnover: Option<f64>,
+    #[sea_orm(nullable)]
+    pub os_returns: Option<f64>,
+
+    // Train 指标（平台返回时才有）
+    #[sea_orm(nullable)]
+    pub train_sharpe: Option<f64>,
+    #[sea_orm(nullable)]
+    pub train_fitness: Option<f64>,
+    #[sea_orm(nullable)]
+    pub train_turnover: Option<f64>,
+    #[sea_orm(nullable)]
+    pub train_returns: Option<f64>,
+
+    // Test 指标（平台返回时才有）
+    #[sea_orm(nullable)]
+    pub test_sharpe: Option<f64>,
+    #[sea_orm(nullable)]
+    pub test_fitness: Option<f64>,
+    #[sea_orm(nullable)]
+    pub test_turnover: Option<f64>,
+    #[sea_orm(nullable)]
+    pub test_returns: Option<f64>,
+
     // JSON 字段
     pub metrics_json: String,
     pub checks_json: String,
+
+    // 规范化表达式哈希（去重用）
+    #[sea_orm(nullable)]
+    pub canonical_hash: Option<String>,
+
+    // 结构复杂度（operator_count 之外的部分；尚未计算时为空，无法解析的表达式 expr_depth 为 -1）
+    #[sea_orm(nullable)]
+    pub expr_depth: Option<i32>,
+    #[sea_orm(nullable)]
+    pub distinct_fields: Option<i32>,
+    #[sea_orm(nullable)]
+    pub distinct_categories: Option<i32>,
+    #[sea_orm(nullable)]
+    pub max_lookback: Option<i32>,
+
+    // 结构家族（cluster 命令按骨架哈希归类；未归类为空）
+    #[sea_orm(nullable)]
+    pub family_id: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
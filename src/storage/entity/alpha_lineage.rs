@@ -0,0 +1,19 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// 进化产生的 Alpha 与其父代的对应关系（交叉产生的子代有两条记录）
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Deserialize, Serialize)]
+#[sea_orm(table_name = "alpha_lineage")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub child_expression: String,
+    pub parent_expression: String,
+    pub variation: String, // operator / lookback / field / crossover
+    pub created_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,40 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Alpha 的来源记录：每个表达式只保留首次入库时的一条
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Deserialize, Serialize)]
+#[sea_orm(table_name = "alpha_provenance")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub expression: String,
+    pub source: String, // generate / template / evolve / catch / backtest
+    #[sea_orm(nullable)]
+    pub provider: Option<String>,
+    #[sea_orm(nullable)]
+    pub model: Option<String>,
+    #[sea_orm(nullable)]
+    pub prompt_hash: Option<String>,
+    #[sea_orm(nullable)]
+    pub prompt_version: Option<String>,
+    #[sea_orm(nullable)]
+    pub batch_id: Option<String>,
+    #[sea_orm(nullable)]
+    pub worker_index: Option<i32>,
+    /// 生成时提示中采样的字段（JSON 数组）
+    pub sampled_fields_json: String,
+    /// 结构化输出时模型给出的假设（一句话的经济逻辑）
+    #[sea_orm(nullable)]
+    pub hypothesis: Option<String>,
+    /// 入库时的回测范围；历史记录为 None，列表关联时视为匹配任意范围
+    #[sea_orm(nullable)]
+    pub region: Option<String>,
+    #[sea_orm(nullable)]
+    pub universe: Option<String>,
+    pub created_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -9,7 +9,7 @@ pub struct Model {
     pub alpha_id: Option<String>,
     pub expression: String,
     pub simulation_id: Option<String>,
-    pub status: String, // QUEUED/CLAIMED/SUBMITTING/RUNNING/FETCHING/DONE/RETRY_WAIT/FAILED_PERMANENT
+    pub status: String, // JobStatus: QUEUED/CLAIMED/SUBMITTING/RUNNING/FETCHING/DONE/RETRY_WAIT/FAILED_PERMANENT
     pub priority: i32,
     pub retry_count: i32,
     pub max_retries: i32,
@@ -18,13 +18,15 @@ pub struct Model {
     pub claimed_at: Option<i64>,
     pub metrics_json: Option<String>,
     pub checks_json: Option<String>,
-    pub last_error_kind: Option<String>, // RETRYABLE / PERMANENT / RETRY_EXCEEDED
+    pub last_error_kind: Option<String>, // JobErrorKind: RETRYABLE / PERMANENT / RETRY_EXCEEDED
     pub last_error_code: Option<String>, // HTTP_429 / TIMEOUT / INVALID_EXPRESSION ...
     pub last_error_message: Option<String>,
     pub created_at: i64,
     pub updated_at: i64,
-    pub region: String,   // 新增：回测区域
-    pub universe: String, // 新增：回测universe
+    pub region: String,                 // 新增：回测区域
+    pub universe: String,               // 新增：回测universe
+    pub delay: i32,                     // 回测 delay
+    pub canonical_hash: Option<String>, // 规范化表达式哈希（去重用）
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -0,0 +1,21 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// 回测任务状态迁移审计记录
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Deserialize, Serialize)]
+#[sea_orm(table_name = "backtest_job_transitions")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub job_id: i32,
+    pub from_status: Option<String>, // 创建任务时为空
+    pub to_status: String,
+    pub actor: Option<String>, // worker id / recover / manual
+    pub note: Option<String>,
+    pub created_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,33 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// 每次 LLM 生成/修复请求的用量记录（token、耗时、估算费用及产出的 Alpha）
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Deserialize, Serialize)]
+#[sea_orm(table_name = "llm_usage")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub created_at: i64,
+    pub day: String, // YYYY-MM-DD（本地时间）
+    pub provider: String,
+    pub model: String,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub latency_ms: i64,
+    #[sea_orm(nullable)]
+    pub cost: Option<f64>,
+    pub success: bool,
+    #[sea_orm(nullable)]
+    pub error: Option<String>,
+    pub alphas_inserted: i32,
+    /// 本次入库的表达式（JSON 数组），用于统计回测通过数
+    pub expressions_json: String,
+    /// 修复轮：交回 LLM 修复的被拒表达式数与修复后通过校验的数目；普通生成请求为 0
+    pub repair_attempted: i32,
+    pub repair_rescued: i32,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -1,9 +1,15 @@
 pub mod alpha;
 pub mod alpha_field_relation;
+pub mod alpha_lineage;
+pub mod alpha_provenance;
 pub mod backtest_job;
+pub mod backtest_job_transition;
 pub mod data_field;
 pub mod data_field_scope;
+pub mod llm_reasoning;
+pub mod llm_usage;
 pub mod operator_event_compat;
+pub mod simulation_budget;
 
 pub use alpha::Entity as Alpha;
 pub use backtest_job::Entity as BacktestJob;
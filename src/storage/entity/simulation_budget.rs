@@ -0,0 +1,19 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// 模拟次数预算计数（按账号 + 时间桶持久化，重启不清零）
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Deserialize, Serialize)]
+#[sea_orm(table_name = "simulation_budget")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub account: String,
+    pub period_key: String, // D2026-01-01 / H2026-01-01T13（本地时间）
+    pub count: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
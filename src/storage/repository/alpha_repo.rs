@@ -1,16 +1,18 @@
+use crate::expr::complexity::Complexity;
 use crate::storage::entity::alpha::{
     self, ActiveModel as AlphaActiveModel, Entity as Alpha, Model as AlphaModel,
 };
 use crate::storage::entity::alpha_field_relation::Entity as AlphaFieldRelation;
+use crate::storage::entity::alpha_provenance::{self, Entity as AlphaProvenance};
 use chrono::Utc;
-use sea_orm::sea_query::Expr;
+use sea_orm::sea_query::{Condition, Expr};
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder,
-    QuerySelect, Set,
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, FromQueryResult, JoinType,
+    QueryFilter, QueryOrder, QuerySelect, RelationDef, Set,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AlphaDefinition {
@@ -24,7 +26,24 @@ pub struct AlphaDefinition {
     pub operator_count: i32,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+impl AlphaDefinition {
+    /// 按默认回测设置（FASTEXPR、decay 10、INDUSTRY 中性化）构造；运算符个数按本地口径计算
+    pub fn with_defaults(expression: String, region: String, universe: String, delay: i32) -> Self {
+        let operator_count = Complexity::of(&expression).map_or(0, |c| c.operators);
+        Self {
+            expression,
+            region,
+            universe,
+            language: "FASTEXPR".to_string(),
+            delay,
+            decay: 10,
+            neutralization: "INDUSTRY".to_string(),
+            operator_count,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct CoreMetrics {
     pub is_sharpe: Option<f64>,
     pub is_fitness: Option<f64>,
@@ -32,6 +51,78 @@ pub struct CoreMetrics {
     pub is_returns: Option<f64>,
     pub is_drawdown: Option<f64>,
     pub is_pnl: Option<f64>,
+    // 样本外 / 训练 / 测试阶段（平台未返回时全为 None）
+    #[serde(default)]
+    pub os: PeriodMetrics,
+    #[serde(default)]
+    pub train: PeriodMetrics,
+    #[serde(default)]
+    pub test: PeriodMetrics,
+}
+
+/// 单个阶段（OS / TRAIN / TEST）的核心指标
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct PeriodMetrics {
+    pub sharpe: Option<f64>,
+    pub fitness: Option<f64>,
+    pub turnover: Option<f64>,
+    pub returns: Option<f64>,
+}
+
+impl PeriodMetrics {
+    /// 从平台返回的阶段对象（is / os / train / test）提取核心指标
+    pub fn from_json(v: &Value) -> Self {
+        Self {
+            sharpe: v.get("sharpe").and_then(|x| x.as_f64()),
+            fitness: v.get("fitness").and_then(|x| x.as_f64()),
+            turnover: v.get("turnover").and_then(|x| x.as_f64()),
+            returns: v.get("returns").and_then(|x| x.as_f64()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sharpe.is_none()
+            && self.fitness.is_none()
+            && self.turnover.is_none()
+            && self.returns.is_none()
+    }
+
+    pub fn get(&self, metric: &str) -> Option<f64> {
+        match metric {
+            "sharpe" => self.sharpe,
+            "fitness" => self.fitness,
+            "turnover" => self.turnover,
+            "returns" => self.returns,
+            _ => None,
+        }
+    }
+}
+
+/// 可用于筛选的阶段与指标名
+pub const METRIC_PERIODS: [&str; 4] = ["is", "os", "train", "test"];
+pub const METRIC_NAMES: [&str; 4] = ["sharpe", "fitness", "turnover", "returns"];
+
+impl CoreMetrics {
+    pub fn period(&self, period: &str) -> Option<PeriodMetrics> {
+        match period {
+            "is" => Some(PeriodMetrics {
+                sharpe: self.is_sharpe,
+                fitness: self.is_fitness,
+                turnover: self.is_turnover,
+                returns: self.is_returns,
+            }),
+            "os" => Some(self.os.clone()),
+            "train" => Some(self.train.clone()),
+            "test" => Some(self.test.clone()),
+            _ => None,
+        }
+    }
+
+    /// 按 "os_sharpe" 这类键取值
+    pub fn get(&self, key: &str) -> Option<f64> {
+        let (period, metric) = key.split_once('_')?;
+        self.period(period)?.get(metric)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -48,6 +139,17 @@ pub struct AlphaDto {
     pub created_at: i64,
     pub updated_at: i64,
     pub core_metrics: CoreMetrics,
+    /// 结构复杂度；历史记录尚未补齐时为 None
+    pub complexity: Option<Complexity>,
+    /// 进化产生时的父代：(父代表达式, 变异方式)；仅详情查询时填充
+    #[serde(default)]
+    pub parents: Vec<(String, String)>,
+    /// 来源记录；仅详情查询时填充
+    #[serde(default)]
+    pub provenance: Option<crate::storage::entity::alpha_provenance::Model>,
+    /// 产出该 Alpha 的请求的思考过程（LLM_ARCHIVE_REASONING）；仅详情查询时填充
+    #[serde(default)]
+    pub reasoning: Option<String>,
     pub metrics_json: Value,
     pub checks_json: Value,
 }
@@ -63,6 +165,19 @@ impl From<AlphaModel> for AlphaDto {
             decay: model.decay,
             neutralization: model.neutralization,
             operator_count: model.operator_count,
+            complexity: model
+                .expr_depth
+                .filter(|d| *d != UNPARSEABLE_DEPTH)
+                .map(|depth| Complexity {
+                    operators: model.operator_count,
+                    depth,
+                    fields: model.distinct_fields.unwrap_or(0),
+                    categories: model.distinct_categories.unwrap_or(0),
+                    lookback: model.max_lookback,
+                }),
+            parents: Vec::new(),
+            provenance: None,
+            reasoning: None,
             status: model.status,
             created_at: model.created_at,
             updated_at: model.updated_at,
@@ -73,6 +188,24 @@ impl From<AlphaModel> for AlphaDto {
                 is_returns: model.is_returns,
                 is_drawdown: model.is_drawdown,
                 is_pnl: model.is_pnl,
+                os: PeriodMetrics {
+                    sharpe: model.os_sharpe,
+                    fitness: model.os_fitness,
+                    turnover: model.os_turnover,
+                    returns: model.os_returns,
+                },
+                train: PeriodMetrics {
+                    sharpe: model.train_sharpe,
+                    fitness: model.train_fitness,
+                    turnover: model.train_turnover,
+                    returns: model.train_returns,
+                },
+                test: PeriodMetrics {
+                    sharpe: model.test_sharpe,
+                    fitness: model.test_fitness,
+                    turnover: model.test_turnover,
+                    returns: model.test_returns,
+                },
             },
             metrics_json: serde_json::from_str(&model.metrics_json)
                 .unwrap_or(Value::Object(Default::default())),
@@ -82,15 +215,88 @@ impl From<AlphaModel> for AlphaDto {
     }
 }
 
+/// 结构家族汇总（Families 视图）
+#[derive(Debug, Clone)]
+pub struct FamilyRow {
+    pub family_id: String,
+    /// 家族骨架（字段为 x、数值为 #）
+    pub skeleton: String,
+    pub size: usize,
+    /// 已完成回测的数量
+    pub done: usize,
+    /// 已完成且没有 FAIL 检查项的数量
+    pub passed: usize,
+    pub best_sharpe: Option<f64>,
+    pub best_fitness: Option<f64>,
+    /// IS Sharpe 最高的成员
+    pub best_expression: String,
+}
+
+impl FamilyRow {
+    pub fn pass_rate(&self) -> Option<f64> {
+        (self.done > 0).then(|| self.passed as f64 / self.done as f64)
+    }
+}
+
 pub struct AlphaRepository;
 
+/// `family_stats` 只读取的列
+#[derive(Debug, FromQueryResult)]
+struct FamilyMember {
+    family_id: String,
+    expression: String,
+    status: String,
+    checks_json: String,
+    is_sharpe: Option<f64>,
+    is_fitness: Option<f64>,
+}
+
+/// expr_depth 取此值表示表达式无法解析，补齐时不再重复解析
+const UNPARSEABLE_DEPTH: i32 = -1;
+
+/// 按表达式填充结构复杂度列与家族 id；平台已给出 operator_count 时保留平台口径
+fn with_structure(mut am: AlphaActiveModel) -> AlphaActiveModel {
+    let Some(expression) = am.expression.try_as_ref() else {
+        return am;
+    };
+    am.family_id = Set(crate::expr::canonical::family_id(expression));
+    let Some(c) = Complexity::of(expression) else {
+        am.expr_depth = Set(Some(UNPARSEABLE_DEPTH));
+        return am;
+    };
+    if am.operator_count.try_as_ref().is_none_or(|n| *n <= 0) {
+        am.operator_count = Set(c.operators);
+    }
+    am.expr_depth = Set(Some(c.depth));
+    am.distinct_fields = Set(Some(c.fields));
+    am.distinct_categories = Set(Some(c.categories));
+    am.max_lookback = Set(c.lookback);
+    am
+}
+
+fn checks_have_fail(checks_json: &str) -> bool {
+    serde_json::from_str::<Value>(checks_json)
+        .ok()
+        .and_then(|v| {
+            v.as_array().map(|checks| {
+                checks.iter().any(|c| {
+                    c.get("result")
+                        .and_then(|x| x.as_str())
+                        .is_some_and(|s| s.eq_ignore_ascii_case("FAIL"))
+                })
+            })
+        })
+        .unwrap_or(false)
+}
+
 impl AlphaRepository {
     pub async fn insert_or_ignore_alpha(
         db: &DatabaseConnection,
         def: AlphaDefinition,
     ) -> Result<(), sea_orm::DbErr> {
         let now = Utc::now().timestamp();
-        let active_model = AlphaActiveModel {
+        let hash = crate::expr::canonical_hash(&def.expression);
+        let active_model = with_structure(AlphaActiveModel {
             expression: Set(def.expression),
             region: Set(def.region),
             universe: Set(def.universe),
@@ -104,8 +310,9 @@ impl AlphaRepository {
             updated_at: Set(now),
             metrics_json: Set("{}".to_string()),
             checks_json: Set("[]".to_string()),
+            canonical_hash: Set(Some(hash)),
             ..Default::default()
-        };
+        });
 
         // SQLite "INSERT OR IGNORE" isn't directly exposed as a single method in SeaORM for all backends easily,
         // but we can use on_conflict in some versions or just try and ignore error.
@@ -138,33 +345,51 @@ impl AlphaRepository {
         Ok(())
     }
 
+    /// 批量插入，按规范化哈希去重（批内 + 已入库），返回实际插入条数
     pub async fn insert_batch(
         db: &DatabaseConnection,
         defs: Vec<AlphaDefinition>,
-    ) -> Result<(), sea_orm::DbErr> {
+    ) -> Result<usize, sea_orm::DbErr> {
         if defs.is_empty() {
-            return Ok(());
+            return Ok(0);
         }
+        let hashed: Vec<(String, AlphaDefinition)> = defs
+            .into_iter()
+            .map(|def| (crate::expr::canonical_hash(&def.expression), def))
+            .collect();
+        let existing =
+            Self::existing_canonical_hashes(db, hashed.iter().map(|(h, _)| h.clone()).collect())
+                .await?;
+
         let now = Utc::now().timestamp();
-        let models: Vec<AlphaActiveModel> = defs
+        let mut seen: HashSet<String> = HashSet::new();
+        let models: Vec<AlphaActiveModel> = hashed
             .into_iter()
-            .map(|def| AlphaActiveModel {
-                expression: Set(def.expression),
-                region: Set(def.region),
-                universe: Set(def.universe),
-                language: Set(def.language),
-                delay: Set(def.delay),
-                decay: Set(def.decay),
-                neutralization: Set(def.neutralization),
-                operator_count: Set(def.operator_count),
-                status: Set("PENDING".to_string()),
-                created_at: Set(now),
-                updated_at: Set(now),
-                metrics_json: Set("{}".to_string()),
-                checks_json: Set("[]".to_string()),
-                ..Default::default()
+            .filter(|(h, _)| !existing.contains(h) && seen.insert(h.clone()))
+            .map(|(hash, def)| {
+                with_structure(AlphaActiveModel {
+                    expression: Set(def.expression),
+                    region: Set(def.region),
+                    universe: Set(def.universe),
+                    language: Set(def.language),
+                    delay: Set(def.delay),
+                    decay: Set(def.decay),
+                    neutralization: Set(def.neutralization),
+                    operator_count: Set(def.operator_count),
+                    status: Set("PENDING".to_string()),
+                    created_at: Set(now),
+                    updated_at: Set(now),
+                    metrics_json: Set("{}".to_string()),
+                    checks_json: Set("[]".to_string()),
+                    canonical_hash: Set(Some(hash)),
+                    ..Default::default()
+                })
             })
             .collect();
+        if models.is_empty() {
+            return Ok(0);
+        }
+        let count = models.len();
 
         Alpha::insert_many(models)
             .on_conflict(
@@ -172,10 +397,265 @@ impl AlphaRepository {
                     .do_nothing()
                     .to_owned(),
             )
+            .do_nothing()
             .exec(db)
             .await?;
 
-        Ok(())
+        Ok(count)
+    }
+
+    /// 返回给定哈希中已存在于 alphas 表的部分
+    pub async fn existing_canonical_hashes(
+        db: &DatabaseConnection,
+        hashes: Vec<String>,
+    ) -> Result<HashSet<String>, sea_orm::DbErr> {
+        if hashes.is_empty() {
+            return Ok(HashSet::new());
+        }
+        let rows: Vec<Option<String>> = Alpha::find()
+            .select_only()
+            .column(alpha::Column::CanonicalHash)
+            .filter(alpha::Column::CanonicalHash.is_in(hashes))
+            .into_tuple()
+            .all(db)
+            .await?;
+        Ok(rows.into_iter().flatten().collect())
+    }
+
+    /// 为缺少规范化哈希的历史记录补齐，返回补齐条数
+    pub async fn backfill_canonical_hashes(db: &DatabaseConnection) -> Result<u64, sea_orm::DbErr> {
+        let rows: Vec<String> = Alpha::find()
+            .select_only()
+            .column(alpha::Column::Expression)
+            .filter(alpha::Column::CanonicalHash.is_null())
+            .into_tuple()
+            .all(db)
+            .await?;
+        let mut n = 0u64;
+        for expression in rows {
+            let hash = crate::expr::canonical_hash(&expression);
+            Alpha::update_many()
+                .col_expr(alpha::Column::CanonicalHash, Expr::value(hash))
+                .filter(alpha::Column::Expression.eq(expression))
+                .exec(db)
+                .await?;
+            n += 1;
+        }
+        Ok(n)
+    }
+
+    /// 为缺少复杂度列的历史记录补齐，返回补齐条数；无法解析的表达式记为已处理，不再重复解析
+    pub async fn backfill_complexity(db: &DatabaseConnection) -> Result<u64, sea_orm::DbErr> {
+        let rows: Vec<(String, i32)> = Alpha::find()
+            .select_only()
+            .column(alpha::Column::Expression)
+            .column(alpha::Column::OperatorCount)
+            .filter(alpha::Column::ExprDepth.is_null())
+            .into_tuple()
+            .all(db)
+            .await?;
+        let mut n = 0u64;
+        for (expression, operator_count) in rows {
+            let Some(c) = Complexity::of(&expression) else {
+                Alpha::update_many()
+                    .col_expr(alpha::Column::ExprDepth, Expr::value(UNPARSEABLE_DEPTH))
+                    .filter(alpha::Column::Expression.eq(expression))
+                    .exec(db)
+                    .await?;
+                continue;
+            };
+            let ops = if operator_count > 0 {
+                operator_count
+            } else {
+                c.operators
+            };
+            Alpha::update_many()
+                .col_expr(alpha::Column::OperatorCount, Expr::value(ops))
+                .col_expr(alpha::Column::ExprDepth, Expr::value(c.depth))
+                .col_expr(alpha::Column::DistinctFields, Expr::value(c.fields))
+                .col_expr(alpha::Column::DistinctCategories, Expr::value(c.categories))
+                .col_expr(alpha::Column::MaxLookback, Expr::value(c.lookback))
+                .filter(alpha::Column::Expression.eq(expression))
+                .exec(db)
+                .await?;
+            n += 1;
+        }
+        Ok(n)
+    }
+
+    /// 按结构骨架为全部 Alpha 重新归类（新入库的记录已在插入时归类，这里补齐历史记录并在骨架规则变更后纠正），
+    /// 返回 (更新条数, 家族数)；无法解析的表达式不归类
+    pub async fn assign_families(db: &DatabaseConnection) -> Result<(u64, usize), sea_orm::DbErr> {
+        let rows: Vec<(String, Option<String>)> = Alpha::find()
+            .select_only()
+            .column(alpha::Column::Expression)
+            .column(alpha::Column::FamilyId)
+            .into_tuple()
+            .all(db)
+            .await?;
+        let mut families = HashSet::new();
+        let mut n = 0u64;
+        for (expression, old) in rows {
+            let family = crate::expr::canonical::family_id(&expression);
+            if let Some(f) = &family {
+                families.insert(f.clone());
+            }
+            if old == family {
+                continue;
+            }
+            Alpha::update_many()
+                .col_expr(alpha::Column::FamilyId, Expr::value(family))
+                .filter(alpha::Column::Expression.eq(expression))
+                .exec(db)
+                .await?;
+            n += 1;
+        }
+        Ok((n, families.len()))
+    }
+
+    /// 各家族的规模与表现，按规模、最佳 Sharpe 降序
+    pub async fn family_stats(db: &DatabaseConnection) -> Result<Vec<FamilyRow>, sea_orm::DbErr> {
+        let members = Alpha::find()
+            .select_only()
+            .column(alpha::Column::FamilyId)
+            .column(alpha::Column::Expression)
+            .column(alpha::Column::Status)
+            .column(alpha::Column::ChecksJson)
+            .column(alpha::Column::IsSharpe)
+            .column(alpha::Column::IsFitness)
+            .filter(alpha::Column::FamilyId.is_not_null())
+            .into_model::<FamilyMember>()
+            .all(db)
+            .await?;
+        let mut by_family: HashMap<String, FamilyRow> = HashMap::new();
+        for m in members {
+            let fid = m.family_id;
+            let row = by_family.entry(fid.clone()).or_insert_with(|| FamilyRow {
+                family_id: fid,
+                skeleton: crate::expr::canonical::skeleton(&m.expression).unwrap_or_default(),
+                size: 0,
+                done: 0,
+                passed: 0,
+                best_sharpe: None,
+                best_fitness: None,
+                best_expression: m.expression.clone(),
+            });
+            row.size += 1;
+            if m.status == "DONE" {
+                row.done += 1;
+                if !checks_have_fail(&m.checks_json) {
+                    row.passed += 1;
+                }
+            }
+            if let Some(s) = m.is_sharpe {
+                if row.best_sharpe.is_none_or(|b| s > b) {
+                    row.best_sharpe = Some(s);
+                    row.best_expression = m.expression.clone();
+                }
+            }
+            if let Some(f) = m.is_fitness {
+                row.best_fitness = Some(row.best_fitness.map_or(f, |b| b.max(f)));
+            }
+        }
+        let mut rows: Vec<FamilyRow> = by_family.into_values().collect();
+        rows.sort_by(|a, b| {
+            b.size.cmp(&a.size).then(
+                b.best_sharpe
+                    .unwrap_or(f64::MIN)
+                    .total_cmp(&a.best_sharpe.unwrap_or(f64::MIN)),
+            )
+        });
+        Ok(rows)
+    }
+
+    /// 给定表达式中已完成且检查项没有 FAIL 的那些
+    pub async fn passed_among(
+        db: &DatabaseConnection,
+        expressions: Vec<String>,
+    ) -> Result<HashSet<String>, sea_orm::DbErr> {
+        let mut passed = HashSet::new();
+        // SQLite 单条语句的参数个数有限，分批查询
+        for chunk in expressions.chunks(500) {
+            let rows = Alpha::find()
+                .filter(alpha::Column::Expression.is_in(chunk.to_vec()))
+                .filter(alpha::Column::Status.eq("DONE"))
+                .all(db)
+                .await?;
+            passed.extend(
+                rows.into_iter()
+                    .filter(|m| !checks_have_fail(&m.checks_json))
+                    .map(|m| m.expression),
+            );
+        }
+        Ok(passed)
+    }
+
+    /// 进化用的父代：指定范围内已完成且有 IS 指标的 Alpha，按 fitness、sharpe 降序
+    pub async fn top_by_fitness(
+        db: &DatabaseConnection,
+        region: &str,
+        universe: &str,
+        limit: u64,
+    ) -> Result<Vec<AlphaModel>, sea_orm::DbErr> {
+        Alpha::find()
+            .filter(alpha::Column::Status.eq("DONE"))
+            .filter(alpha::Column::Region.eq(region))
+            .filter(alpha::Column::Universe.eq(universe))
+            .filter(alpha::Column::IsFitness.is_not_null())
+            .order_by_desc(alpha::Column::IsFitness)
+            .order_by_desc(alpha::Column::IsSharpe)
+            .limit(limit)
+            .all(db)
+            .await
+    }
+
+    /// 最近完成且 IS Sharpe 最高的 Alpha（生成提示的正例）；region/universe 为 None 时不限范围
+    pub async fn best_recent(
+        db: &DatabaseConnection,
+        region: Option<&str>,
+        universe: Option<&str>,
+        since: i64,
+        limit: u64,
+    ) -> Result<Vec<AlphaModel>, sea_orm::DbErr> {
+        let mut query = Alpha::find()
+            .filter(alpha::Column::Status.eq("DONE"))
+            .filter(alpha::Column::IsSharpe.is_not_null())
+            .filter(alpha::Column::UpdatedAt.gte(since));
+        if let Some(r) = region {
+            query = query.filter(alpha::Column::Region.eq(r));
+        }
+        if let Some(u) = universe {
+            query = query.filter(alpha::Column::Universe.eq(u));
+        }
+        query
+            .order_by_desc(alpha::Column::IsSharpe)
+            .limit(limit)
+            .all(db)
+            .await
+    }
+
+    /// 最近入库的表达式（近似重复比对用）；region/universe 为 None 时不限范围
+    pub async fn recent_expressions(
+        db: &DatabaseConnection,
+        region: Option<&str>,
+        universe: Option<&str>,
+        limit: u64,
+    ) -> Result<Vec<String>, sea_orm::DbErr> {
+        let mut query = Alpha::find()
+            .select_only()
+            .column(alpha::Column::Expression);
+        if let Some(r) = region {
+            query = query.filter(alpha::Column::Region.eq(r));
+        }
+        if let Some(u) = universe {
+            query = query.filter(alpha::Column::Universe.eq(u));
+        }
+        query
+            .order_by_desc(alpha::Column::CreatedAt)
+            .limit(limit)
+            .into_tuple()
+            .all(db)
+            .await
     }
 
     pub async fn load_by_status(
@@ -207,28 +687,45 @@ impl AlphaRepository {
         Ok(models.into_iter().map(AlphaDto::from).collect())
     }
 
-    pub async fn load_all_by_status(
+    /// 列表用：全部 Alpha 连同来源记录（按表达式与回测范围左连接，一次查询），按状态、更新时间排序
+    pub async fn load_all_with_provenance(
         db: &DatabaseConnection,
-        status: &str,
     ) -> Result<Vec<AlphaDto>, sea_orm::DbErr> {
-        let mut query = Alpha::find();
-
-        if status != "ALL" {
-            query = query.filter(alpha::Column::Status.eq(status));
-            let models = query
-                .order_by_desc(alpha::Column::UpdatedAt)
-                .all(db)
-                .await?;
-            return Ok(models.into_iter().map(AlphaDto::from).collect());
-        }
-
-        let models = query
+        let rel: RelationDef = Alpha::belongs_to(AlphaProvenance)
+            .from(alpha::Column::Expression)
+            .to(alpha_provenance::Column::Expression)
+            .on_condition(|left, right| {
+                // 历史来源记录没有范围，视为匹配
+                let same = |a: alpha::Column, p: alpha_provenance::Column| {
+                    Condition::any()
+                        .add(Expr::col((right.clone(), p)).is_null())
+                        .add(Expr::col((right.clone(), p)).equals((left.clone(), a)))
+                };
+                Condition::all()
+                    .add(same(
+                        alpha::Column::Region,
+                        alpha_provenance::Column::Region,
+                    ))
+                    .add(same(
+                        alpha::Column::Universe,
+                        alpha_provenance::Column::Universe,
+                    ))
+            })
+            .into();
+        let rows = Alpha::find()
+            .join(JoinType::LeftJoin, rel)
+            .select_also(AlphaProvenance)
             .order_by_asc(alpha::Column::Status)
             .order_by_desc(alpha::Column::UpdatedAt)
             .all(db)
             .await?;
-
-        Ok(models.into_iter().map(AlphaDto::from).collect())
+        Ok(rows
+            .into_iter()
+            .map(|(a, p)| AlphaDto {
+                provenance: p,
+                ..AlphaDto::from(a)
+            })
+            .collect())
     }
 
     pub async fn mark_simulating(
@@ -281,6 +778,24 @@ impl AlphaRepository {
                 if let Some(v) = core.is_pnl {
                     active_model.is_pnl = Set(Some(v));
                 }
+                if !core.os.is_empty() {
+                    active_model.os_sharpe = Set(core.os.sharpe);
+                    active_model.os_fitness = Set(core.os.fitness);
+                    active_model.os_turnover = Set(core.os.turnover);
+                    active_model.os_returns = Set(core.os.returns);
+                }
+                if !core.train.is_empty() {
+                    active_model.train_sharpe = Set(core.train.sharpe);
+                    active_model.train_fitness = Set(core.train.fitness);
+                    active_model.train_turnover = Set(core.train.turnover);
+                    active_model.train_returns = Set(core.train.returns);
+                }
+                if !core.test.is_empty() {
+                    active_model.test_sharpe = Set(core.test.sharpe);
+                    active_model.test_fitness = Set(core.test.fitness);
+                    active_model.test_turnover = Set(core.test.turnover);
+                    active_model.test_returns = Set(core.test.returns);
+                }
             }
 
             if let Some(new_metrics) = metrics_json {
@@ -1,14 +1,19 @@
+use crate::backtest::metrics::Attempt;
+use crate::backtest::model::{JobErrorKind, JobStatus};
 use crate::storage::entity::backtest_job::{
     self, ActiveModel as BacktestJobActiveModel, Entity as BacktestJob,
 };
+use crate::storage::entity::backtest_job_transition::{
+    ActiveModel as TransitionActiveModel, Entity as BacktestJobTransition,
+};
 use crate::storage::repository::data_field_repo::{DataFieldRepository, EventOpValidationErr};
 use chrono::Utc;
 use sea_orm::sea_query::Expr;
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder,
-    QuerySelect, Set, TransactionTrait,
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait,
+    FromQueryResult, NotSet, QueryFilter, QueryOrder, QuerySelect, Set, Statement,
+    TransactionTrait, UpdateMany,
 };
-use sea_orm::{ConnectionTrait, Statement};
 use serde_json::Value;
 
 pub struct BacktestRepository;
@@ -16,24 +21,29 @@ pub struct BacktestRepository;
 impl BacktestRepository {
     pub async fn delete_all(db: &DatabaseConnection) -> Result<u64, sea_orm::DbErr> {
         let res = backtest_job::Entity::delete_many().exec(db).await?;
+        let _ = BacktestJobTransition::delete_many().exec(db).await?;
         Ok(res.rows_affected)
     }
+    /// 同一表达式（按规范化哈希）在同一 region/universe/delay 下已有活跃任务时不重复入队，返回 None
     pub async fn create_job(
         db: &DatabaseConnection,
         expression: String,
         region: String,
         universe: String,
+        delay: i32,
     ) -> Result<Option<i32>, sea_orm::DbErr> {
+        let active: Vec<&str> = JobStatus::ALL
+            .iter()
+            .filter(|s| s.is_active())
+            .map(|s| s.as_str())
+            .collect();
+        let hash = crate::expr::canonical_hash(&expression);
         let exists = BacktestJob::find()
-            .filter(backtest_job::Column::Expression.eq(expression.clone()))
-            .filter(backtest_job::Column::Status.is_in([
-                "QUEUED",
-                "RETRY_WAIT",
-                "CLAIMED",
-                "SUBMITTING",
-                "RUNNING",
-                "FETCHING",
-            ]))
+            .filter(backtest_job::Column::CanonicalHash.eq(hash.clone()))
+            .filter(backtest_job::Column::Region.eq(region.clone()))
+            .filter(backtest_job::Column::Universe.eq(universe.clone()))
+            .filter(backtest_job::Column::Delay.eq(delay))
+            .filter(backtest_job::Column::Status.is_in(active))
             .one(db)
             .await?;
         if exists.is_some() {
@@ -45,7 +55,7 @@ impl BacktestRepository {
         let active_model = BacktestJobActiveModel {
             alpha_id: Set(None),
             expression: Set(expression),
-            status: Set("QUEUED".to_string()),
+            status: Set(JobStatus::Queued.as_str().to_string()),
             priority: Set(0),
             retry_count: Set(0),
             max_retries: Set(5),
@@ -54,40 +64,109 @@ impl BacktestRepository {
             updated_at: Set(now),
             region: Set(region),
             universe: Set(universe),
+            delay: Set(delay),
+            canonical_hash: Set(Some(hash)),
             ..Default::default()
         };
 
         let result = active_model.insert(db).await?;
+        record_transition(db, result.id, None, JobStatus::Queued, None, None).await?;
         Ok(Some(result.id))
     }
 
+    /// 为缺少规范化哈希的历史任务补齐，返回补齐条数
+    pub async fn backfill_canonical_hashes(db: &DatabaseConnection) -> Result<u64, sea_orm::DbErr> {
+        let rows: Vec<(i32, String)> = BacktestJob::find()
+            .select_only()
+            .column(backtest_job::Column::Id)
+            .column(backtest_job::Column::Expression)
+            .filter(backtest_job::Column::CanonicalHash.is_null())
+            .into_tuple()
+            .all(db)
+            .await?;
+        let mut n = 0u64;
+        for (id, expression) in rows {
+            let hash = crate::expr::canonical_hash(&expression);
+            BacktestJob::update_many()
+                .col_expr(backtest_job::Column::CanonicalHash, Expr::value(hash))
+                .filter(backtest_job::Column::Id.eq(id))
+                .exec(db)
+                .await?;
+            n += 1;
+        }
+        Ok(n)
+    }
+
+    /// 按迁移表执行一次状态变更，并写入审计记录。
+    /// `build` 用于附加本次迁移需要一并更新的列。
+    async fn transition<C, F>(
+        conn: &C,
+        id: i32,
+        to: JobStatus,
+        actor: Option<&str>,
+        note: Option<String>,
+        build: F,
+    ) -> Result<backtest_job::Model, DbErr>
+    where
+        C: ConnectionTrait + TransactionTrait,
+        F: FnOnce(UpdateMany<BacktestJob>) -> UpdateMany<BacktestJob>,
+    {
+        // 状态更新与迁移记录同一事务提交（传入事务时为保存点），审计表不会与 status 脱节
+        let txn = conn.begin().await?;
+        let job = BacktestJob::find_by_id(id)
+            .one(&txn)
+            .await?
+            .ok_or_else(|| DbErr::RecordNotFound(format!("backtest_job {}", id)))?;
+        let from = JobStatus::parse(&job.status)
+            .ok_or_else(|| DbErr::Custom(format!("任务 [{}] 状态未知: {}", id, job.status)))?;
+        if !from.can_transition_to(to) {
+            return Err(DbErr::Custom(format!(
+                "任务 [{}] 非法状态迁移: {} -> {}",
+                id, from, to
+            )));
+        }
+
+        let now = Utc::now().timestamp();
+        let update = BacktestJob::update_many()
+            .col_expr(backtest_job::Column::Status, Expr::value(to.as_str()))
+            .col_expr(backtest_job::Column::UpdatedAt, Expr::value(now))
+            .filter(backtest_job::Column::Id.eq(id))
+            // 乐观锁：只有状态仍为读取时的值才更新，避免并发覆盖
+            .filter(backtest_job::Column::Status.eq(from.as_str()));
+        let res = build(update).exec(&txn).await?;
+        if res.rows_affected == 0 {
+            return Err(DbErr::Custom(format!(
+                "任务 [{}] 状态已被并发修改，放弃迁移 {} -> {}",
+                id, from, to
+            )));
+        }
+
+        record_transition(&txn, id, Some(from), to, actor, note).await?;
+        txn.commit().await?;
+        Ok(job)
+    }
+
     pub async fn update_status(
         db: &DatabaseConnection,
         id: i32,
-        status: String,
+        status: JobStatus,
         sim_id: Option<String>,
         alpha_id: Option<String>,
         error_message: Option<String>,
     ) -> Result<(), sea_orm::DbErr> {
-        let now = Utc::now().timestamp();
-        let mut update = BacktestJobActiveModel {
-            id: Set(id),
-            status: Set(status),
-            updated_at: Set(now),
-            ..Default::default()
-        };
-
-        if let Some(s) = sim_id {
-            update.simulation_id = Set(Some(s));
-        }
-        if let Some(a) = alpha_id {
-            update.alpha_id = Set(Some(a));
-        }
-        if let Some(m) = error_message {
-            update.last_error_message = Set(Some(m));
-        }
-
-        update.update(db).await?;
+        Self::transition(db, id, status, None, None, |mut q| {
+            if let Some(s) = sim_id {
+                q = q.col_expr(backtest_job::Column::SimulationId, Expr::value(s));
+            }
+            if let Some(a) = alpha_id {
+                q = q.col_expr(backtest_job::Column::AlphaId, Expr::value(a));
+            }
+            if let Some(m) = error_message {
+                q = q.col_expr(backtest_job::Column::LastErrorMessage, Expr::value(m));
+            }
+            q
+        })
+        .await?;
         Ok(())
     }
 
@@ -95,7 +174,7 @@ impl BacktestRepository {
         db: &DatabaseConnection,
     ) -> Result<(usize, Vec<String>), sea_orm::DbErr> {
         let jobs = BacktestJob::find()
-            .filter(backtest_job::Column::Status.eq("QUEUED"))
+            .filter(backtest_job::Column::Status.eq(JobStatus::Queued.as_str()))
             .all(db)
             .await?;
         let mut deleted = 0usize;
@@ -131,7 +210,7 @@ impl BacktestRepository {
         limit: u64,
     ) -> Result<Vec<backtest_job::Model>, sea_orm::DbErr> {
         BacktestJob::find()
-            .filter(backtest_job::Column::Status.eq("QUEUED"))
+            .filter(backtest_job::Column::Status.eq(JobStatus::Queued.as_str()))
             .limit(limit)
             .all(db)
             .await
@@ -154,9 +233,7 @@ impl BacktestRepository {
 
         let picked = BacktestJob::find()
             .filter(
-                backtest_job::Column::Status
-                    .eq("QUEUED")
-                    .or(backtest_job::Column::Status.eq("RETRY_WAIT")),
+                backtest_job::Column::Status.is_in(JobStatus::CLAIMABLE.iter().map(|s| s.as_str())),
             )
             .filter(backtest_job::Column::NextRunAt.lte(now))
             .order_by_desc(backtest_job::Column::Priority)
@@ -167,43 +244,70 @@ impl BacktestRepository {
         if let Some(job) = picked {
             let job_id = job.id;
             let now2 = Utc::now().timestamp();
-            BacktestJob::update_many()
-                .col_expr(backtest_job::Column::Status, Expr::value("CLAIMED"))
-                .col_expr(
-                    backtest_job::Column::ClaimedBy,
-                    Expr::value(worker_id.to_string()),
-                )
-                .col_expr(backtest_job::Column::ClaimedAt, Expr::value(now2))
-                .col_expr(backtest_job::Column::UpdatedAt, Expr::value(now2))
-                .filter(backtest_job::Column::Id.eq(job_id))
-                .exec(&txn)
-                .await?;
+            Self::transition(
+                &txn,
+                job_id,
+                JobStatus::Claimed,
+                Some(worker_id),
+                None,
+                |q| {
+                    q.col_expr(
+                        backtest_job::Column::ClaimedBy,
+                        Expr::value(worker_id.to_string()),
+                    )
+                    .col_expr(backtest_job::Column::ClaimedAt, Expr::value(now2))
+                },
+            )
+            .await?;
 
             txn.commit().await?;
-            return Ok(BacktestJob::find_by_id(job_id).one(db).await?);
+            return BacktestJob::find_by_id(job_id).one(db).await;
         }
 
         txn.commit().await?;
         Ok(None)
     }
 
+    /// 认领后因预算不足放回队列（CLAIMED -> QUEUED）
+    pub async fn release_claim(
+        db: &DatabaseConnection,
+        id: i32,
+        worker_id: &str,
+        note: &str,
+    ) -> Result<(), sea_orm::DbErr> {
+        Self::transition(
+            db,
+            id,
+            JobStatus::Queued,
+            Some(worker_id),
+            Some(note.to_string()),
+            |q| {
+                q.col_expr(
+                    backtest_job::Column::ClaimedBy,
+                    Expr::value(Option::<String>::None),
+                )
+                .col_expr(
+                    backtest_job::Column::ClaimedAt,
+                    Expr::value(Option::<i64>::None),
+                )
+            },
+        )
+        .await?;
+        Ok(())
+    }
+
     pub async fn mark_status(
         db: &DatabaseConnection,
         id: i32,
-        status: &str,
+        status: JobStatus,
         simulation_id: Option<String>,
+        actor: Option<&str>,
     ) -> Result<(), sea_orm::DbErr> {
-        let now = Utc::now().timestamp();
-        let mut update = BacktestJobActiveModel {
-            id: Set(id),
-            status: Set(status.to_string()),
-            updated_at: Set(now),
-            ..Default::default()
-        };
-        if let Some(s) = simulation_id {
-            update.simulation_id = Set(Some(s));
-        }
-        update.update(db).await?;
+        Self::transition(db, id, status, actor, None, |q| match simulation_id {
+            Some(s) => q.col_expr(backtest_job::Column::SimulationId, Expr::value(s)),
+            None => q,
+        })
+        .await?;
         Ok(())
     }
 
@@ -214,49 +318,49 @@ impl BacktestRepository {
         alpha_id: Option<String>,
         metrics_json: Option<Value>,
         checks_json: Option<Value>,
+        actor: Option<&str>,
     ) -> Result<(), sea_orm::DbErr> {
-        let now = Utc::now().timestamp();
-        let mut update = BacktestJobActiveModel {
-            id: Set(id),
-            status: Set("DONE".to_string()),
-            updated_at: Set(now),
-            ..Default::default()
-        };
-        if let Some(s) = simulation_id {
-            update.simulation_id = Set(Some(s));
-        }
-        if let Some(a) = alpha_id {
-            update.alpha_id = Set(Some(a));
-        }
-        if let Some(m) = metrics_json {
-            update.metrics_json = Set(Some(m.to_string()));
-        }
-        if let Some(c) = checks_json {
-            update.checks_json = Set(Some(c.to_string()));
-        }
-        update.update(db).await?;
+        Self::transition(db, id, JobStatus::Done, actor, None, |mut q| {
+            if let Some(s) = simulation_id {
+                q = q.col_expr(backtest_job::Column::SimulationId, Expr::value(s));
+            }
+            if let Some(a) = alpha_id {
+                q = q.col_expr(backtest_job::Column::AlphaId, Expr::value(a));
+            }
+            if let Some(m) = metrics_json {
+                q = q.col_expr(
+                    backtest_job::Column::MetricsJson,
+                    Expr::value(m.to_string()),
+                );
+            }
+            if let Some(c) = checks_json {
+                q = q.col_expr(backtest_job::Column::ChecksJson, Expr::value(c.to_string()));
+            }
+            q
+        })
+        .await?;
         Ok(())
     }
 
     pub async fn mark_failed_retryable(
         db: &DatabaseConnection,
         id: i32,
-        kind: &str,
+        kind: JobErrorKind,
         code: Option<String>,
         message: Option<String>,
         next_run_at: i64,
+        actor: Option<&str>,
     ) -> Result<(), sea_orm::DbErr> {
-        let now = Utc::now().timestamp();
-        BacktestJob::update_many()
-            .col_expr(backtest_job::Column::Status, Expr::value("RETRY_WAIT"))
-            .col_expr(
+        let note = message.clone();
+        Self::transition(db, id, JobStatus::RetryWait, actor, note, |q| {
+            q.col_expr(
                 backtest_job::Column::RetryCount,
                 Expr::col(backtest_job::Column::RetryCount).add(1),
             )
             .col_expr(backtest_job::Column::NextRunAt, Expr::value(next_run_at))
             .col_expr(
                 backtest_job::Column::LastErrorKind,
-                Expr::value(kind.to_string()),
+                Expr::value(kind.as_str()),
             )
             .col_expr(
                 backtest_job::Column::LastErrorCode,
@@ -266,29 +370,24 @@ impl BacktestRepository {
                 backtest_job::Column::LastErrorMessage,
                 Expr::value(message.unwrap_or_default()),
             )
-            .col_expr(backtest_job::Column::UpdatedAt, Expr::value(now))
-            .filter(backtest_job::Column::Id.eq(id))
-            .exec(db)
-            .await?;
+        })
+        .await?;
         Ok(())
     }
 
     pub async fn mark_failed_permanent(
         db: &DatabaseConnection,
         id: i32,
-        kind: &str,
+        kind: JobErrorKind,
         code: Option<String>,
         message: Option<String>,
+        actor: Option<&str>,
     ) -> Result<(), sea_orm::DbErr> {
-        let now = Utc::now().timestamp();
-        BacktestJob::update_many()
-            .col_expr(
-                backtest_job::Column::Status,
-                Expr::value("FAILED_PERMANENT"),
-            )
-            .col_expr(
+        let note = message.clone();
+        Self::transition(db, id, JobStatus::FailedPermanent, actor, note, |q| {
+            q.col_expr(
                 backtest_job::Column::LastErrorKind,
-                Expr::value(kind.to_string()),
+                Expr::value(kind.as_str()),
             )
             .col_expr(
                 backtest_job::Column::LastErrorCode,
@@ -298,10 +397,8 @@ impl BacktestRepository {
                 backtest_job::Column::LastErrorMessage,
                 Expr::value(message.unwrap_or_default()),
             )
-            .col_expr(backtest_job::Column::UpdatedAt, Expr::value(now))
-            .filter(backtest_job::Column::Id.eq(id))
-            .exec(db)
-            .await?;
+        })
+        .await?;
         Ok(())
     }
 
@@ -309,64 +406,49 @@ impl BacktestRepository {
         db: &DatabaseConnection,
     ) -> Result<Vec<backtest_job::Model>, sea_orm::DbErr> {
         BacktestJob::find()
-            .filter(backtest_job::Column::Status.eq("POLLING"))
+            .filter(backtest_job::Column::Status.eq(JobStatus::Running.as_str()))
             .all(db)
             .await
     }
 
-    /// 将所有中间状态的任务重置为 PENDING
+    /// 将所有中间状态的任务重置为 QUEUED
     pub async fn reset_stale_jobs(db: &DatabaseConnection) -> Result<u64, sea_orm::DbErr> {
-        let now = Utc::now().timestamp();
-        let res = BacktestJob::update_many()
-            .col_expr(
-                backtest_job::Column::Status,
-                sea_orm::sea_query::Expr::value("QUEUED"),
-            )
-            .col_expr(
-                backtest_job::Column::NextRunAt,
-                sea_orm::sea_query::Expr::value(now),
-            )
-            .col_expr(
-                backtest_job::Column::UpdatedAt,
-                sea_orm::sea_query::Expr::value(now),
-            )
+        let stale = BacktestJob::find()
             .filter(
-                backtest_job::Column::Status
-                    .eq("RUNNING")
-                    .or(backtest_job::Column::Status.eq("FETCHING"))
-                    .or(backtest_job::Column::Status.eq("SUBMITTING"))
-                    .or(backtest_job::Column::Status.eq("CLAIMED")),
+                backtest_job::Column::Status.is_in(JobStatus::IN_FLIGHT.iter().map(|s| s.as_str())),
             )
-            .exec(db)
+            .all(db)
             .await?;
-        Ok(res.rows_affected)
+        let now = Utc::now().timestamp();
+        let mut count = 0u64;
+        for job in stale {
+            let res = Self::transition(
+                db,
+                job.id,
+                JobStatus::Queued,
+                Some("recover"),
+                Some("启动恢复：中断的任务重新排队".to_string()),
+                |q| q.col_expr(backtest_job::Column::NextRunAt, Expr::value(now)),
+            )
+            .await;
+            if res.is_ok() {
+                count += 1;
+            }
+        }
+        Ok(count)
     }
 
-    /// 增加重试计数并重置为 PENDING
+    /// 增加重试计数并重置为 QUEUED
     pub async fn increment_retry(db: &DatabaseConnection, id: i32) -> Result<(), sea_orm::DbErr> {
         let now = Utc::now().timestamp();
-        // 这里的逻辑稍微复杂，我们需要先读再写，或者使用表达式更新
-        // SeaORM 允许使用表达式更新
-        BacktestJob::update_many()
-            .col_expr(
+        Self::transition(db, id, JobStatus::Queued, Some("manual"), None, |q| {
+            q.col_expr(
                 backtest_job::Column::RetryCount,
-                sea_orm::sea_query::Expr::col(backtest_job::Column::RetryCount).add(1),
-            )
-            .col_expr(
-                backtest_job::Column::Status,
-                sea_orm::sea_query::Expr::value("QUEUED"),
-            )
-            .col_expr(
-                backtest_job::Column::NextRunAt,
-                sea_orm::sea_query::Expr::value(now),
-            )
-            .col_expr(
-                backtest_job::Column::UpdatedAt,
-                sea_orm::sea_query::Expr::value(now),
+                Expr::col(backtest_job::Column::RetryCount).add(1),
             )
-            .filter(backtest_job::Column::Id.eq(id))
-            .exec(db)
-            .await?;
+            .col_expr(backtest_job::Column::NextRunAt, Expr::value(now))
+        })
+        .await?;
         Ok(())
     }
 
@@ -375,54 +457,96 @@ impl BacktestRepository {
     ) -> Result<crate::backtest::model::BacktestStats, sea_orm::DbErr> {
         use sea_orm::PaginatorTrait;
 
-        let total = BacktestJob::find().count(db).await? as usize;
-        let pending = BacktestJob::find()
-            .filter(backtest_job::Column::Status.eq("QUEUED"))
+        let rows = BacktestJob::find()
+            .select_only()
+            .column(backtest_job::Column::Status)
+            .column_as(backtest_job::Column::Id.count(), "count")
+            .group_by(backtest_job::Column::Status)
+            .into_tuple::<(String, i64)>()
+            .all(db)
+            .await?;
+        let mut stats = crate::backtest::model::BacktestStats::default();
+        for (status, count) in rows {
+            let count = count as usize;
+            stats.total += count;
+            match JobStatus::parse(&status) {
+                Some(JobStatus::Queued) => stats.pending += count,
+                Some(JobStatus::Claimed) => stats.claimed += count,
+                Some(JobStatus::Submitting) => stats.submitting += count,
+                Some(JobStatus::Running) => stats.simulating += count,
+                Some(JobStatus::Fetching) => stats.fetching += count,
+                Some(JobStatus::Done) => stats.completed += count,
+                Some(JobStatus::RetryWait) => stats.error_retryable += count,
+                Some(JobStatus::FailedPermanent) => stats.error_fatal += count,
+                None => {}
+            }
+        }
+        stats.running = stats.claimed + stats.submitting + stats.simulating + stats.fetching;
+        stats.error_exceeded = BacktestJob::find()
+            .filter(backtest_job::Column::LastErrorKind.eq(JobErrorKind::RetryExceeded.as_str()))
             .count(db)
             .await? as usize;
-        let running = BacktestJob::find()
+        stats.active_jobs = BacktestJob::find()
             .filter(
-                backtest_job::Column::Status
-                    .eq("RUNNING")
-                    .or(backtest_job::Column::Status.eq("SUBMITTING"))
-                    .or(backtest_job::Column::Status.eq("FETCHING"))
-                    .or(backtest_job::Column::Status.eq("CLAIMED")),
+                backtest_job::Column::Status.is_in(JobStatus::IN_FLIGHT.iter().map(|s| s.as_str())),
             )
-            .count(db)
-            .await? as usize;
-        let completed = BacktestJob::find()
-            .filter(backtest_job::Column::Status.eq("DONE"))
-            .count(db)
-            .await? as usize;
-        let error_retryable = BacktestJob::find()
-            .filter(backtest_job::Column::Status.eq("RETRY_WAIT"))
-            .count(db)
-            .await? as usize;
-        let error_fatal = BacktestJob::find()
-            .filter(backtest_job::Column::Status.eq("FAILED_PERMANENT"))
-            .count(db)
-            .await? as usize;
-        let error_exceeded = BacktestJob::find()
-            .filter(backtest_job::Column::LastErrorKind.eq("RETRY_EXCEEDED"))
-            .count(db)
-            .await? as usize;
+            .order_by_asc(backtest_job::Column::ClaimedAt)
+            .select_only()
+            .column(backtest_job::Column::Id)
+            .column(backtest_job::Column::Status)
+            .column(backtest_job::Column::ClaimedBy)
+            .column(backtest_job::Column::Expression)
+            .column(backtest_job::Column::UpdatedAt)
+            .into_model::<ActiveJobRow>()
+            .all(db)
+            .await?;
 
-        Ok(crate::backtest::model::BacktestStats {
-            total,
-            pending,
-            running,
-            completed,
-            error_retryable,
-            error_fatal,
-            error_exceeded,
-        })
+        let now = Utc::now().timestamp();
+        let since = now - crate::backtest::metrics::SPARKLINE_HOURS as i64 * 3600;
+        let attempts = Self::list_attempts_since(db, since).await?;
+        let remaining = stats.pending + stats.error_retryable + stats.running;
+        stats.throughput = crate::backtest::metrics::compute_throughput(&attempts, now, remaining);
+
+        Ok(stats)
+    }
+
+    /// 某时间点之后结束的执行尝试：每条结束迁移（DONE / RETRY_WAIT / FAILED_PERMANENT）配对
+    /// 同一任务此前最近的 CLAIMED（即使早于 `since`）；中途被重新排队的尝试不计入
+    pub async fn list_attempts_since(
+        db: &DatabaseConnection,
+        since: i64,
+    ) -> Result<Vec<Attempt>, sea_orm::DbErr> {
+        let sql = r#"
+            SELECT COALESCE(c.actor, '-') AS worker_id,
+                   c.created_at AS started_at,
+                   t.created_at AS finished_at,
+                   t.to_status = 'DONE' AS success
+            FROM backtest_job_transitions t
+            JOIN backtest_job_transitions c ON c.id = (
+                SELECT MAX(p.id) FROM backtest_job_transitions p
+                WHERE p.job_id = t.job_id AND p.id < t.id AND p.to_status = 'CLAIMED'
+            )
+            WHERE t.created_at >= ?
+              AND t.to_status IN ('DONE', 'RETRY_WAIT', 'FAILED_PERMANENT')
+              AND NOT EXISTS (
+                SELECT 1 FROM backtest_job_transitions q
+                WHERE q.job_id = t.job_id AND q.id > c.id AND q.id < t.id
+                  AND q.to_status = 'QUEUED'
+              )
+        "#;
+        Attempt::find_by_statement(Statement::from_sql_and_values(
+            db.get_database_backend(),
+            sql,
+            [since.into()],
+        ))
+        .all(db)
+        .await
     }
 
     pub async fn list_recent_errors(
         db: &DatabaseConnection,
         limit: u64,
     ) -> Result<Vec<BacktestErrorRow>, sea_orm::DbErr> {
-        use sea_orm::FromQueryResult;
         BacktestJob::find()
             .filter(backtest_job::Column::LastErrorMessage.is_not_null())
             .order_by_desc(backtest_job::Column::UpdatedAt)
@@ -436,16 +560,53 @@ impl BacktestRepository {
             .await
     }
 
+    /// 最近永久失败的任务，每种错误只取最新一条：(表达式, 错误原因)
+    pub async fn representative_failures(
+        db: &DatabaseConnection,
+        since: i64,
+        limit: usize,
+    ) -> Result<Vec<(String, String)>, sea_orm::DbErr> {
+        let rows = BacktestJob::find()
+            .filter(backtest_job::Column::Status.eq(JobStatus::FailedPermanent.as_str()))
+            .filter(backtest_job::Column::UpdatedAt.gte(since))
+            .order_by_desc(backtest_job::Column::UpdatedAt)
+            .limit((limit as u64).saturating_mul(20))
+            .all(db)
+            .await?;
+        let mut seen = std::collections::HashSet::new();
+        let mut out = Vec::new();
+        for j in rows {
+            if out.len() >= limit {
+                break;
+            }
+            let code = j.last_error_code.clone().unwrap_or_default();
+            let message = j.last_error_message.clone().unwrap_or_default();
+            // 同一错误码下消息不同（如不同未知字段）也算同一类
+            let key = if code.is_empty() {
+                message.clone()
+            } else {
+                code.clone()
+            };
+            if key.is_empty() || !seen.insert(key) {
+                continue;
+            }
+            let reason = match (code.is_empty(), message.is_empty()) {
+                (false, false) => format!("{}: {}", code, message),
+                (false, true) => code,
+                _ => message,
+            };
+            out.push((j.expression, reason.chars().take(200).collect()));
+        }
+        Ok(out)
+    }
+
     pub async fn sanitize_queued_expressions(
         db: &DatabaseConnection,
         limit: u64,
     ) -> Result<(usize, usize), sea_orm::DbErr> {
-        use sea_orm::PaginatorTrait;
         let jobs = BacktestJob::find()
             .filter(
-                backtest_job::Column::Status
-                    .eq("QUEUED")
-                    .or(backtest_job::Column::Status.eq("RETRY_WAIT")),
+                backtest_job::Column::Status.is_in(JobStatus::CLAIMABLE.iter().map(|s| s.as_str())),
             )
             .filter(backtest_job::Column::Expression.like("%{%"))
             .order_by_desc(backtest_job::Column::UpdatedAt)
@@ -463,6 +624,7 @@ impl BacktestRepository {
                     updated_at: Set(now),
                     ..Default::default()
                 };
+                m.canonical_hash = Set(Some(crate::expr::canonical_hash(&new_expr)));
                 m.expression = Set(new_expr);
                 m.update(db).await?;
                 cleaned += 1;
@@ -472,6 +634,15 @@ impl BacktestRepository {
     }
 }
 
+#[derive(Debug, Clone, Default, sea_orm::FromQueryResult)]
+pub struct ActiveJobRow {
+    pub id: i32,
+    pub status: String,
+    pub claimed_by: Option<String>,
+    pub expression: String,
+    pub updated_at: i64,
+}
+
 #[derive(Debug, Clone, sea_orm::FromQueryResult)]
 pub struct BacktestErrorRow {
     pub updated_at: i64,
@@ -479,6 +650,27 @@ pub struct BacktestErrorRow {
     pub last_error_message: Option<String>,
 }
 
+async fn record_transition<C: ConnectionTrait>(
+    conn: &C,
+    job_id: i32,
+    from: Option<JobStatus>,
+    to: JobStatus,
+    actor: Option<&str>,
+    note: Option<String>,
+) -> Result<(), DbErr> {
+    let am = TransitionActiveModel {
+        id: NotSet,
+        job_id: Set(job_id),
+        from_status: Set(from.map(|s| s.as_str().to_string())),
+        to_status: Set(to.as_str().to_string()),
+        actor: Set(actor.map(|a| a.to_string())),
+        note: Set(note),
+        created_at: Set(Utc::now().timestamp()),
+    };
+    am.insert(conn).await?;
+    Ok(())
+}
+
 fn sanitize_expr(expr: &str) -> String {
     let mut out = String::with_capacity(expr.len());
     let mut skip = false;
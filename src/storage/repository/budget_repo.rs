@@ -0,0 +1,100 @@
+use crate::storage::entity::simulation_budget::{
+    self, ActiveModel as BudgetActiveModel, Entity as SimulationBudget,
+};
+use chrono::Utc;
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, QueryFilter,
+    Set, TransactionTrait,
+};
+
+pub struct BudgetRepository;
+
+impl BudgetRepository {
+    /// 某账号某时间桶已消耗的模拟次数
+    pub async fn get_count<C: ConnectionTrait>(
+        conn: &C,
+        account: &str,
+        period_key: &str,
+    ) -> Result<i64, sea_orm::DbErr> {
+        let row = SimulationBudget::find()
+            .filter(simulation_budget::Column::Account.eq(account))
+            .filter(simulation_budget::Column::PeriodKey.eq(period_key))
+            .one(conn)
+            .await?;
+        Ok(row.map(|r| r.count).unwrap_or(0))
+    }
+
+    /// 在事务内检查上限并为每个时间桶 +1；任一桶已达上限则不计数并返回 false
+    pub async fn try_consume(
+        db: &DatabaseConnection,
+        account: &str,
+        buckets: &[(String, Option<i64>)],
+    ) -> Result<bool, sea_orm::DbErr> {
+        let txn = db.begin().await?;
+        for (key, cap) in buckets {
+            if let Some(cap) = cap {
+                if Self::get_count(&txn, account, key).await? >= *cap {
+                    txn.rollback().await?;
+                    return Ok(false);
+                }
+            }
+        }
+        for (key, _) in buckets {
+            Self::increment(&txn, account, key).await?;
+        }
+        txn.commit().await?;
+        Ok(true)
+    }
+
+    /// 退还先前扣减的额度（各时间桶 -1，不低于 0）
+    pub async fn refund(
+        db: &DatabaseConnection,
+        account: &str,
+        period_keys: &[String],
+    ) -> Result<(), sea_orm::DbErr> {
+        let now = Utc::now().timestamp();
+        SimulationBudget::update_many()
+            .col_expr(
+                simulation_budget::Column::Count,
+                Expr::col(simulation_budget::Column::Count).sub(1),
+            )
+            .col_expr(simulation_budget::Column::UpdatedAt, Expr::value(now))
+            .filter(simulation_budget::Column::Account.eq(account))
+            .filter(simulation_budget::Column::PeriodKey.is_in(period_keys.iter().cloned()))
+            .filter(simulation_budget::Column::Count.gt(0))
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
+    async fn increment<C: ConnectionTrait>(
+        conn: &C,
+        account: &str,
+        period_key: &str,
+    ) -> Result<(), sea_orm::DbErr> {
+        let now = Utc::now().timestamp();
+        let res = SimulationBudget::update_many()
+            .col_expr(
+                simulation_budget::Column::Count,
+                Expr::col(simulation_budget::Column::Count).add(1),
+            )
+            .col_expr(simulation_budget::Column::UpdatedAt, Expr::value(now))
+            .filter(simulation_budget::Column::Account.eq(account))
+            .filter(simulation_budget::Column::PeriodKey.eq(period_key))
+            .exec(conn)
+            .await?;
+        if res.rows_affected == 0 {
+            BudgetActiveModel {
+                account: Set(account.to_string()),
+                period_key: Set(period_key.to_string()),
+                count: Set(1),
+                updated_at: Set(now),
+                ..Default::default()
+            }
+            .insert(conn)
+            .await?;
+        }
+        Ok(())
+    }
+}
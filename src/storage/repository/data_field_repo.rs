@@ -329,21 +329,9 @@ impl DataFieldRepository {
         db: &DatabaseConnection,
         expression: &str,
     ) -> Result<Vec<String>, sea_orm::DbErr> {
-        let mut tokens = Vec::new();
-        let mut cur = String::new();
-        for ch in expression.chars() {
-            if ch.is_ascii_alphanumeric() || ch == '_' {
-                cur.push(ch);
-            } else {
-                if !cur.is_empty() {
-                    tokens.push(cur.clone());
-                    cur.clear();
-                }
-            }
-        }
-        if !cur.is_empty() {
-            tokens.push(cur);
-        }
+        let mut tokens = crate::expr::identifiers(expression);
+        tokens.sort();
+        tokens.dedup();
         if tokens.is_empty() {
             return Ok(Vec::new());
         }
@@ -354,6 +342,83 @@ impl DataFieldRepository {
         Ok(rows.into_iter().map(|m| m.field_id).collect())
     }
 
+    /// 目标 region/universe/delay 下可用字段的完整信息
+    pub async fn fields_in_scope(
+        db: &DatabaseConnection,
+        region: &str,
+        universe: &str,
+        delay: i32,
+    ) -> Result<Vec<DataFieldModel>, sea_orm::DbErr> {
+        let ids: Vec<String> = DataFieldScope::find()
+            .select_only()
+            .column(DataFieldScopeColumn::FieldId)
+            .filter(DataFieldScopeColumn::Region.eq(region.to_string()))
+            .filter(DataFieldScopeColumn::Universe.eq(universe.to_string()))
+            .filter(DataFieldScopeColumn::Delay.eq(delay))
+            .into_tuple()
+            .all(db)
+            .await?;
+        let mut out = Vec::with_capacity(ids.len());
+        for chunk in ids.chunks(500) {
+            out.extend(
+                DataField::find()
+                    .filter(DataFieldColumn::FieldId.is_in(chunk.to_vec()))
+                    .all(db)
+                    .await?,
+            );
+        }
+        Ok(out)
+    }
+
+    /// 目标 region/universe/delay 下可用字段按数据集分组：dataset_id -> field_ids
+    pub async fn fields_by_dataset(
+        db: &DatabaseConnection,
+        region: &str,
+        universe: &str,
+        delay: i32,
+    ) -> Result<HashMap<String, Vec<String>>, sea_orm::DbErr> {
+        let mut out: HashMap<String, Vec<String>> = HashMap::new();
+        for m in Self::fields_in_scope(db, region, universe, delay).await? {
+            out.entry(m.dataset_id).or_default().push(m.field_id);
+        }
+        Ok(out)
+    }
+
+    /// 表达式中在目标 region/universe/delay 下不存在的标识符（按出现顺序、去重，已排除内置关键字）。
+    /// 该范围尚未同步任何字段时无从判断，返回空列表。
+    pub async fn unknown_fields_in_scope(
+        db: &DatabaseConnection,
+        expression: &str,
+        region: &str,
+        universe: &str,
+        delay: i32,
+    ) -> Result<Vec<String>, sea_orm::DbErr> {
+        let mut names: Vec<String> = Vec::new();
+        for id in crate::expr::identifiers(expression) {
+            if !crate::expr::is_keyword(&id) && !names.contains(&id) {
+                names.push(id);
+            }
+        }
+        if names.is_empty() {
+            return Ok(Vec::new());
+        }
+        let scope = DataFieldScope::find()
+            .filter(DataFieldScopeColumn::Region.eq(region.to_string()))
+            .filter(DataFieldScopeColumn::Universe.eq(universe.to_string()))
+            .filter(DataFieldScopeColumn::Delay.eq(delay));
+        if scope.clone().one(db).await?.is_none() {
+            return Ok(Vec::new());
+        }
+        let found: HashSet<String> = scope
+            .filter(DataFieldScopeColumn::FieldId.is_in(names.clone()))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|m| m.field_id)
+            .collect();
+        Ok(names.into_iter().filter(|n| !found.contains(n)).collect())
+    }
+
     pub async fn validate_event_operator_compatibility(
         db: &DatabaseConnection,
         expression: &str,
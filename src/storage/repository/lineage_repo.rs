@@ -0,0 +1,50 @@
+use crate::storage::entity::alpha_lineage::{
+    self, ActiveModel as LineageActiveModel, Entity as AlphaLineage,
+};
+use chrono::Utc;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, Set};
+
+pub struct LineageRepository;
+
+impl LineageRepository {
+    /// 记录子代与全部父代的关系
+    pub async fn record(
+        db: &DatabaseConnection,
+        child: &str,
+        parents: &[String],
+        variation: &str,
+    ) -> Result<(), sea_orm::DbErr> {
+        if parents.is_empty() {
+            return Ok(());
+        }
+        let now = Utc::now().timestamp();
+        let models: Vec<LineageActiveModel> = parents
+            .iter()
+            .map(|p| LineageActiveModel {
+                child_expression: Set(child.to_string()),
+                parent_expression: Set(p.clone()),
+                variation: Set(variation.to_string()),
+                created_at: Set(now),
+                ..Default::default()
+            })
+            .collect();
+        AlphaLineage::insert_many(models).exec(db).await?;
+        Ok(())
+    }
+
+    /// 子代的父代列表：(父代表达式, 变异方式)
+    pub async fn parents_of(
+        db: &DatabaseConnection,
+        child: &str,
+    ) -> Result<Vec<(String, String)>, sea_orm::DbErr> {
+        let rows = AlphaLineage::find()
+            .filter(alpha_lineage::Column::ChildExpression.eq(child))
+            .order_by_asc(alpha_lineage::Column::Id)
+            .all(db)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|r| (r.parent_expression, r.variation))
+            .collect())
+    }
+}
@@ -1,9 +1,22 @@
 pub mod alpha_repo;
 pub mod backtest_repo;
+pub mod budget_repo;
 pub mod data_field_repo;
+pub mod lineage_repo;
 pub mod operator_compat_repo;
+pub mod provenance_repo;
+pub mod reasoning_repo;
+pub mod usage_repo;
 
-pub use alpha_repo::{AlphaDefinition, AlphaDto, AlphaRepository, CoreMetrics};
-pub use backtest_repo::BacktestRepository;
+pub use alpha_repo::{
+    AlphaDefinition, AlphaDto, AlphaRepository, CoreMetrics, FamilyRow, PeriodMetrics,
+    METRIC_NAMES, METRIC_PERIODS,
+};
+pub use backtest_repo::{ActiveJobRow, BacktestRepository};
+pub use budget_repo::BudgetRepository;
 pub use data_field_repo::{DataFieldRepository, FieldStatsRow};
+pub use lineage_repo::LineageRepository;
 pub use operator_compat_repo::OperatorCompatRepository;
+pub use provenance_repo::{Provenance, ProvenanceRepository};
+pub use reasoning_repo::ReasoningRepository;
+pub use usage_repo::{LlmUsageRepository, NewUsage, UsageRow};
@@ -0,0 +1,113 @@
+use crate::storage::entity::alpha_provenance::{
+    self, ActiveModel as ProvenanceActiveModel, Entity as AlphaProvenance, Model as ProvenanceModel,
+};
+use chrono::Utc;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set};
+use std::collections::HashMap;
+
+/// 一批 Alpha 共用的来源信息
+#[derive(Debug, Clone, Default)]
+pub struct Provenance {
+    pub source: String,
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub prompt_hash: Option<String>,
+    pub prompt_version: Option<String>,
+    pub batch_id: Option<String>,
+    pub worker_index: Option<usize>,
+    pub sampled_fields: Vec<String>,
+    /// 结构化输出中每条表达式的假设；未给出的表达式不记录
+    pub hypotheses: HashMap<String, String>,
+    pub region: Option<String>,
+    pub universe: Option<String>,
+}
+
+impl Provenance {
+    pub fn source(source: &str) -> Self {
+        Self {
+            source: source.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// 记录入库时的回测范围，列表按 (表达式, region, universe) 关联来源
+    pub fn with_scope(mut self, region: &str, universe: &str) -> Self {
+        self.region = Some(region.to_string());
+        self.universe = Some(universe.to_string());
+        self
+    }
+
+    /// 一次生成/展开的批次号：毫秒时间戳 + 随机后缀
+    pub fn new_batch_id() -> String {
+        format!(
+            "{:x}-{:04x}",
+            Utc::now().timestamp_millis(),
+            rand::random::<u16>()
+        )
+    }
+
+    pub fn hash_prompt(prompt: &str) -> String {
+        format!(
+            "{:016x}",
+            crate::expr::canonical::fnv1a64(prompt.as_bytes())
+        )
+    }
+}
+
+pub struct ProvenanceRepository;
+
+impl ProvenanceRepository {
+    /// 为表达式记录来源；已有记录的表达式保持不变
+    pub async fn record(
+        db: &DatabaseConnection,
+        p: &Provenance,
+        expressions: &[String],
+    ) -> Result<(), sea_orm::DbErr> {
+        if expressions.is_empty() {
+            return Ok(());
+        }
+        let now = Utc::now().timestamp();
+        let fields = serde_json::to_string(&p.sampled_fields).unwrap_or_else(|_| "[]".to_string());
+        let models: Vec<ProvenanceActiveModel> = expressions
+            .iter()
+            .map(|e| ProvenanceActiveModel {
+                expression: Set(e.clone()),
+                source: Set(p.source.clone()),
+                provider: Set(p.provider.clone()),
+                model: Set(p.model.clone()),
+                prompt_hash: Set(p.prompt_hash.clone()),
+                prompt_version: Set(p.prompt_version.clone()),
+                batch_id: Set(p.batch_id.clone()),
+                worker_index: Set(p.worker_index.map(|w| w as i32)),
+                sampled_fields_json: Set(fields.clone()),
+                hypothesis: Set(p.hypotheses.get(e).cloned()),
+                region: Set(p.region.clone()),
+                universe: Set(p.universe.clone()),
+                created_at: Set(now),
+                ..Default::default()
+            })
+            .collect();
+        for chunk in models.chunks(200) {
+            AlphaProvenance::insert_many(chunk.to_vec())
+                .on_conflict(
+                    sea_orm::sea_query::OnConflict::column(alpha_provenance::Column::Expression)
+                        .do_nothing()
+                        .to_owned(),
+                )
+                .do_nothing()
+                .exec(db)
+                .await?;
+        }
+        Ok(())
+    }
+
+    pub async fn of(
+        db: &DatabaseConnection,
+        expression: &str,
+    ) -> Result<Option<ProvenanceModel>, sea_orm::DbErr> {
+        AlphaProvenance::find()
+            .filter(alpha_provenance::Column::Expression.eq(expression))
+            .one(db)
+            .await
+    }
+}
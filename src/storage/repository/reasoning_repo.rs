@@ -0,0 +1,47 @@
+use crate::storage::entity::llm_reasoning::{
+    self, ActiveModel as ReasoningActiveModel, Entity as LlmReasoning,
+};
+use chrono::Utc;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set};
+
+pub struct ReasoningRepository;
+
+impl ReasoningRepository {
+    /// 保存一个批次的思考过程；同一批次已有记录时保持不变
+    pub async fn archive(
+        db: &DatabaseConnection,
+        batch_id: &str,
+        provider: &str,
+        model: &str,
+        reasoning: &str,
+    ) -> Result<(), sea_orm::DbErr> {
+        LlmReasoning::insert(ReasoningActiveModel {
+            batch_id: Set(batch_id.to_string()),
+            provider: Set(provider.to_string()),
+            model: Set(model.to_string()),
+            reasoning: Set(reasoning.to_string()),
+            created_at: Set(Utc::now().timestamp()),
+            ..Default::default()
+        })
+        .on_conflict(
+            sea_orm::sea_query::OnConflict::column(llm_reasoning::Column::BatchId)
+                .do_nothing()
+                .to_owned(),
+        )
+        .do_nothing()
+        .exec(db)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn of_batch(
+        db: &DatabaseConnection,
+        batch_id: &str,
+    ) -> Result<Option<String>, sea_orm::DbErr> {
+        Ok(LlmReasoning::find()
+            .filter(llm_reasoning::Column::BatchId.eq(batch_id))
+            .one(db)
+            .await?
+            .map(|m| m.reasoning))
+    }
+}
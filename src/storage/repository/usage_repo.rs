@@ -0,0 +1,137 @@
+use crate::storage::entity::llm_usage::{
+    self, ActiveModel as UsageActiveModel, Entity as LlmUsage,
+};
+use crate::storage::repository::AlphaRepository;
+use chrono::{Local, Utc};
+use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set};
+use std::collections::{HashMap, HashSet};
+
+/// 一次生成或修复请求的用量
+#[derive(Debug, Clone, Default)]
+pub struct NewUsage {
+    pub provider: String,
+    pub model: String,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub latency_ms: u64,
+    pub cost: Option<f64>,
+    pub success: bool,
+    pub error: Option<String>,
+    pub expressions: Vec<String>,
+    pub repair_attempted: usize,
+    pub repair_rescued: usize,
+}
+
+/// 按 日期/供应商/模型 汇总的用量
+#[derive(Debug, Clone, Default)]
+pub struct UsageRow {
+    pub day: String,
+    pub provider: String,
+    pub model: String,
+    pub requests: usize,
+    pub failures: usize,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub avg_latency_ms: i64,
+    /// 只累计有价格的请求；全部未配置价格时为 None
+    pub cost: Option<f64>,
+    pub alphas: usize,
+    pub passed: usize,
+    pub repair_attempted: usize,
+    pub repair_rescued: usize,
+}
+
+/// 汇总中的一组：(汇总行, 延迟之和, 该组入库的表达式)
+type UsageGroup = (UsageRow, i64, Vec<String>);
+
+pub struct LlmUsageRepository;
+
+impl LlmUsageRepository {
+    pub async fn record(db: &DatabaseConnection, u: NewUsage) -> Result<(), sea_orm::DbErr> {
+        UsageActiveModel {
+            created_at: Set(Utc::now().timestamp()),
+            day: Set(Local::now().format("%Y-%m-%d").to_string()),
+            provider: Set(u.provider),
+            model: Set(u.model),
+            prompt_tokens: Set(u.prompt_tokens as i64),
+            completion_tokens: Set(u.completion_tokens as i64),
+            latency_ms: Set(u.latency_ms as i64),
+            cost: Set(u.cost),
+            success: Set(u.success),
+            error: Set(u.error),
+            alphas_inserted: Set(u.expressions.len() as i32),
+            expressions_json: Set(serde_json::to_string(&u.expressions).unwrap_or_default()),
+            repair_attempted: Set(u.repair_attempted as i32),
+            repair_rescued: Set(u.repair_rescued as i32),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+        Ok(())
+    }
+
+    /// 最近 `days` 天按 日期/供应商/模型 汇总，日期降序、同日按请求数降序
+    pub async fn daily_stats(
+        db: &DatabaseConnection,
+        days: i64,
+    ) -> Result<Vec<UsageRow>, sea_orm::DbErr> {
+        let since = Utc::now().timestamp() - days.max(1) * 86_400;
+        let models = LlmUsage::find()
+            .filter(llm_usage::Column::CreatedAt.gte(since))
+            .all(db)
+            .await?;
+
+        let mut groups: HashMap<(String, String, String), UsageGroup> = HashMap::new();
+        for m in models {
+            let key = (m.day.clone(), m.provider.clone(), m.model.clone());
+            let (row, latency_sum, exprs) = groups.entry(key).or_insert_with(|| {
+                (
+                    UsageRow {
+                        day: m.day.clone(),
+                        provider: m.provider.clone(),
+                        model: m.model.clone(),
+                        ..Default::default()
+                    },
+                    0,
+                    Vec::new(),
+                )
+            });
+            row.requests += 1;
+            if !m.success {
+                row.failures += 1;
+            }
+            row.prompt_tokens += m.prompt_tokens;
+            row.completion_tokens += m.completion_tokens;
+            *latency_sum += m.latency_ms;
+            row.repair_attempted += m.repair_attempted.max(0) as usize;
+            row.repair_rescued += m.repair_rescued.max(0) as usize;
+            if let Some(c) = m.cost {
+                row.cost = Some(row.cost.unwrap_or(0.0) + c);
+            }
+            exprs.extend(
+                serde_json::from_str::<Vec<String>>(&m.expressions_json).unwrap_or_default(),
+            );
+        }
+
+        let all: Vec<String> = groups
+            .values()
+            .flat_map(|(_, _, e)| e.iter().cloned())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let passed = AlphaRepository::passed_among(db, all).await?;
+
+        let mut rows: Vec<UsageRow> = groups
+            .into_values()
+            .map(|(mut row, latency_sum, exprs)| {
+                row.avg_latency_ms = latency_sum / row.requests.max(1) as i64;
+                let exprs: HashSet<String> = exprs.into_iter().collect();
+                row.alphas = exprs.len();
+                row.passed = exprs.iter().filter(|e| passed.contains(*e)).count();
+                row
+            })
+            .collect();
+        rows.sort_by(|a, b| b.day.cmp(&a.day).then(b.requests.cmp(&a.requests)));
+        Ok(rows)
+    }
+}
@@ -1,4 +1,7 @@
 use crate::app_state::{App, FocusArea, InputMode, ViewMode};
+use crate::backtest::model::JobStatus;
+use crate::expr::format::pretty;
+use crate::storage::repository::PeriodMetrics;
 use ratatui::{
     layout::{Constraint, Layout, Rect},
     style::{Color, Modifier, Style},
@@ -60,8 +63,17 @@ fn render_top_bar(f: &mut Frame, area: Rect) {
     f.render_widget(paragraph, area);
 }
 
+const MENU_ITEMS: [&str; 6] = [
+    "Alpha 列表",
+    "回测任务",
+    "详细信息",
+    "字段统计",
+    "Alpha 家族",
+    "LLM 用量",
+];
+
 fn render_left_menu(f: &mut Frame, area: Rect, app: &App) {
-    let menu_items: Vec<ListItem> = ["Alpha 列表", "回测任务", "详细信息", "字段统计"]
+    let menu_items: Vec<ListItem> = MENU_ITEMS
         .iter()
         .enumerate()
         .map(|(i, text)| {
@@ -71,6 +83,8 @@ fn render_left_menu(f: &mut Frame, area: Rect, app: &App) {
                 (1, ViewMode::BacktestQueue) => true,
                 (2, ViewMode::Detail) => true,
                 (3, ViewMode::FieldStats) => true,
+                (4, ViewMode::Families) => true,
+                (5, ViewMode::Usage) => true,
                 _ => false,
             };
 
@@ -160,11 +174,20 @@ fn render_main_view(f: &mut Frame, area: Rect, app: &mut App) {
                 .collect();
 
             let status_filter = app.filter_status.as_deref().unwrap_or("ALL");
-            let query_info = if app.filter_query.is_empty() {
+            let mut query_info = if app.filter_query.is_empty() {
                 String::new()
             } else {
                 format!(" 搜索: \"{}\"", app.filter_query)
             };
+            for mf in &app.filter_metrics {
+                query_info.push_str(&format!(" {}{}{}", mf.key, mf.op, mf.value));
+            }
+            if let Some(s) = &app.filter_source {
+                query_info.push_str(&format!(" source={}", s));
+            }
+            if let Some(m) = &app.filter_model {
+                query_info.push_str(&format!(" model={}", m));
+            }
             let title = if app.focus_area == FocusArea::MainView {
                 format!(
                     "Alpha 列表 [Filter: {}]{} (f 切换, / 搜索, Enter/c 详情, ← 菜单)",
@@ -208,7 +231,14 @@ fn render_main_view(f: &mut Frame, area: Rect, app: &mut App) {
                     Style::default().fg(Color::Yellow),
                 )]),
                 Line::from(vec![Span::styled(
-                    format!("  模拟中  : {:>4}", stats.running),
+                    format!("  执行中  : {:>4}", stats.running),
+                    Style::default().fg(Color::Cyan),
+                )]),
+                Line::from(vec![Span::styled(
+                    format!(
+                        "    已认领 {:>3} | 提交中 {:>3} | 模拟中 {:>3} | 抓取中 {:>3}",
+                        stats.claimed, stats.submitting, stats.simulating, stats.fetching
+                    ),
                     Style::default().fg(Color::Cyan),
                 )]),
                 Line::from(vec![Span::styled(
@@ -229,12 +259,118 @@ fn render_main_view(f: &mut Frame, area: Rect, app: &mut App) {
                 )]),
                 Line::from(""),
                 Line::from(vec![Span::styled(
-                    "提示: 后台 Service 每 5 秒自动扫描并执行 PENDING 任务",
+                    "提示: 后台 workers 持续认领 QUEUED / RETRY_WAIT 任务",
                     Style::default()
                         .fg(Color::Gray)
                         .add_modifier(Modifier::ITALIC),
                 )]),
             ];
+            let mut content = content;
+            let bg = &stats.budget;
+            let cap_text = |used: i64, cap: Option<i64>| match cap {
+                Some(c) => format!("{}/{} (剩余 {})", used, c, (c - used).max(0)),
+                None => format!("{}/不限", used),
+            };
+            content.push(Line::from(""));
+            content.push(Line::from(vec![Span::styled(
+                "--- 模拟预算 ---",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            )]));
+            content.push(Line::from(vec![Span::raw(format!(
+                "  今日: {} | 本小时: {} | 运行时段: {}",
+                cap_text(bg.daily_used, bg.daily_cap),
+                cap_text(bg.hourly_used, bg.hourly_cap),
+                bg.active_hours
+            ))]));
+            if let Some(reason) = bg.blocked {
+                content.push(Line::from(vec![Span::styled(
+                    format!("  ⏸ workers 空闲中: {}", reason),
+                    Style::default().fg(Color::LightRed),
+                )]));
+            }
+            let tp = &stats.throughput;
+            content.push(Line::from(""));
+            content.push(Line::from(vec![Span::styled(
+                "--- 吞吐与预计完成 ---",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            )]));
+            content.push(Line::from(vec![Span::raw(format!(
+                "  吞吐: {:.1} 次/小时 | 耗时中位数 {} | P90 {} | 队列预计 {}",
+                tp.per_hour,
+                format_secs(tp.median_secs),
+                format_secs(tp.p90_secs),
+                format_secs(tp.eta_secs),
+            ))]));
+            let rate_text = match tp.recent_success_rate() {
+                Some(r) => format!(
+                    "  最近 {} 次: 成功 {} / 失败 {} ({:.0}%)",
+                    tp.recent_success + tp.recent_failure,
+                    tp.recent_success,
+                    tp.recent_failure,
+                    r * 100.0
+                ),
+                None => "  最近尝试: 暂无".to_string(),
+            };
+            content.push(Line::from(vec![Span::styled(
+                rate_text,
+                Style::default().fg(Color::Green),
+            )]));
+            content.push(Line::from(vec![
+                Span::raw(format!("  近{}小时: ", tp.hourly_done.len())),
+                Span::styled(
+                    sparkline_text(&tp.hourly_done),
+                    Style::default().fg(Color::Cyan),
+                ),
+            ]));
+            for w in &tp.workers {
+                content.push(Line::from(vec![Span::styled(
+                    format!(
+                        "    {:<4} 完成 {:>4} | 失败 {:>3} | {:>5.1} 次/小时 | 中位 {}",
+                        w.worker_id,
+                        w.done,
+                        w.failed,
+                        w.per_hour,
+                        format_secs(w.median_secs)
+                    ),
+                    Style::default().fg(Color::Gray),
+                )]));
+            }
+            if !stats.active_jobs.is_empty() {
+                content.push(Line::from(""));
+                content.push(Line::from(vec![Span::styled(
+                    "--- 执行中任务 ---",
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                )]));
+                let now = chrono::Utc::now().timestamp();
+                for job in &stats.active_jobs {
+                    let color = match JobStatus::parse(&job.status) {
+                        Some(JobStatus::Claimed) => Color::Gray,
+                        Some(JobStatus::Submitting) => Color::Yellow,
+                        Some(JobStatus::Running) => Color::Cyan,
+                        Some(JobStatus::Fetching) => Color::Green,
+                        _ => Color::White,
+                    };
+                    content.push(Line::from(vec![
+                        Span::raw(format!(
+                            "  [{:>5}] {:<4} ",
+                            job.id,
+                            job.claimed_by.as_deref().unwrap_or("-")
+                        )),
+                        Span::styled(format!("{:<11}", job.status), Style::default().fg(color)),
+                        Span::styled(
+                            format!("{:>5}s  ", (now - job.updated_at).max(0)),
+                            Style::default().fg(Color::Gray),
+                        ),
+                        Span::raw(job.expression.clone()),
+                    ]));
+                }
+            }
 
             let title = if app.focus_area == FocusArea::MainView {
                 "回测任务情况 (← 切换菜单)"
@@ -255,11 +391,27 @@ fn render_main_view(f: &mut Frame, area: Rect, app: &mut App) {
         }
         ViewMode::Detail => {
             let content = if let Some(ref detail) = app.selected_detail {
-                let mut lines = vec![
-                    Line::from(vec![
+                let mut lines = if app.detail_formatted {
+                    // 缩进树形式：宽度随面板调整（扣除边框与缩进）
+                    let width = area.width.saturating_sub(4) as usize;
+                    let mut v = vec![Line::from(vec![Span::styled(
+                        "表达式 (格式化):",
+                        Style::default().add_modifier(Modifier::BOLD),
+                    )])];
+                    for l in pretty(&detail.expression, width).lines() {
+                        v.push(Line::from(Span::styled(
+                            format!("  {}", l),
+                            Style::default().fg(Color::Cyan),
+                        )));
+                    }
+                    v
+                } else {
+                    vec![Line::from(vec![
                         Span::styled("表达式: ", Style::default().add_modifier(Modifier::BOLD)),
                         Span::styled(&detail.expression, Style::default().fg(Color::Cyan)),
-                    ]),
+                    ])]
+                };
+                lines.extend([
                     Line::from(vec![
                         Span::styled("状态: ", Style::default().add_modifier(Modifier::BOLD)),
                         Span::raw(&detail.status),
@@ -273,41 +425,184 @@ fn render_main_view(f: &mut Frame, area: Rect, app: &mut App) {
                     ]),
                     Line::from(""),
                     Line::from(vec![Span::styled(
-                        "--- 核心指标 (IS) ---",
+                        "--- 核心指标 ---",
                         Style::default().fg(Color::Yellow),
                     )]),
+                ]);
+
+                // IS 与 OS / TRAIN / TEST 并排展示，只显示有数据的阶段
+                let periods: Vec<(&str, PeriodMetrics)> = [
+                    ("IS", "is"),
+                    ("OS", "os"),
+                    ("TRAIN", "train"),
+                    ("TEST", "test"),
+                ]
+                .into_iter()
+                .filter_map(|(label, key)| {
+                    let p = detail.core_metrics.period(key)?;
+                    (key == "is" || !p.is_empty()).then_some((label, p))
+                })
+                .collect();
+                let fmt_ratio = |v: Option<f64>| {
+                    v.map(|v| format!("{:.2}", v))
+                        .unwrap_or_else(|| "N/A".to_string())
+                };
+                let fmt_pct = |v: Option<f64>| {
+                    v.map(|v| format!("{:.2}%", v * 100.0))
+                        .unwrap_or_else(|| "N/A".to_string())
+                };
+                let mut header = format!("{:<10}", "");
+                for (label, _) in &periods {
+                    header.push_str(&format!("{:>10}", label));
+                }
+                lines.push(Line::from(vec![Span::styled(
+                    header,
+                    Style::default().add_modifier(Modifier::BOLD),
+                )]));
+                let rows: [MetricRow; 4] = [
+                    ("Sharpe", |p| p.sharpe, false),
+                    ("Fitness", |p| p.fitness, false),
+                    ("Returns", |p| p.returns, true),
+                    ("Turnover", |p| p.turnover, true),
                 ];
+                for (name, getter, pct) in rows {
+                    let mut row = format!("{:<10}", name);
+                    for (_, p) in &periods {
+                        let v = getter(p);
+                        let cell = if pct { fmt_pct(v) } else { fmt_ratio(v) };
+                        row.push_str(&format!("{:>10}", cell));
+                    }
+                    lines.push(Line::from(row));
+                }
 
-                // 渲染数值指标 (从 core_metrics 字段读取)
-                let sharpe = detail
-                    .core_metrics
-                    .is_sharpe
-                    .map(|v| format!("{:.2}", v))
-                    .unwrap_or_else(|| "N/A".to_string());
-                let fitness = detail
-                    .core_metrics
-                    .is_fitness
-                    .map(|v| format!("{:.2}", v))
-                    .unwrap_or_else(|| "N/A".to_string());
-                let turnover = detail
-                    .core_metrics
-                    .is_turnover
-                    .map(|v| format!("{:.2}%", v * 100.0))
-                    .unwrap_or_else(|| "N/A".to_string());
-                let returns = detail
-                    .core_metrics
-                    .is_returns
-                    .map(|v| format!("{:.2}%", v * 100.0))
-                    .unwrap_or_else(|| "N/A".to_string());
-
-                lines.push(Line::from(format!(
-                    "Sharpe:  {:<10} Fitness: {:<10}",
-                    sharpe, fitness
-                )));
-                lines.push(Line::from(format!(
-                    "Returns: {:<10} Turnover: {:<10}",
-                    returns, turnover
-                )));
+                lines.push(Line::from(""));
+                lines.push(Line::from(vec![Span::styled(
+                    "--- 结构复杂度 ---",
+                    Style::default().fg(Color::Yellow),
+                )]));
+                match &detail.complexity {
+                    Some(c) => {
+                        lines.push(Line::from(format!(
+                            "  运算符: {}  深度: {}  字段: {}  类别: {}  最大回看: {}",
+                            c.operators,
+                            c.depth,
+                            c.fields,
+                            c.categories,
+                            c.lookback
+                                .map(|v| v.to_string())
+                                .unwrap_or_else(|| "-".to_string())
+                        )));
+                    }
+                    None => lines.push(Line::from("  N/A（表达式无法解析）")),
+                }
+
+                if !detail.parents.is_empty() {
+                    lines.push(Line::from(""));
+                    lines.push(Line::from(vec![Span::styled(
+                        "--- 谱系 (父代) ---",
+                        Style::default().fg(Color::Yellow),
+                    )]));
+                    for (parent, variation) in &detail.parents {
+                        lines.push(Line::from(vec![
+                            Span::styled(
+                                format!("  [{}] ", variation),
+                                Style::default().fg(Color::Gray),
+                            ),
+                            Span::raw(parent.clone()),
+                        ]));
+                    }
+                }
+
+                if let Some(p) = &detail.provenance {
+                    lines.push(Line::from(""));
+                    lines.push(Line::from(vec![Span::styled(
+                        "--- 来源 ---",
+                        Style::default().fg(Color::Yellow),
+                    )]));
+                    lines.push(Line::from(format!("  来源: {}", p.source)));
+                    if let Some(h) = &p.hypothesis {
+                        lines.push(Line::from(format!("  假设: {}", h)));
+                    }
+                    if p.provider.is_some() || p.model.is_some() {
+                        lines.push(Line::from(format!(
+                            "  模型: {}/{}",
+                            p.provider.as_deref().unwrap_or("-"),
+                            p.model.as_deref().unwrap_or("-")
+                        )));
+                    }
+                    if let Some(batch) = &p.batch_id {
+                        let worker = p
+                            .worker_index
+                            .map(|w| format!(" (worker {})", w))
+                            .unwrap_or_default();
+                        lines.push(Line::from(format!("  批次: {}{}", batch, worker)));
+                    }
+                    if p.prompt_hash.is_some() || p.prompt_version.is_some() {
+                        lines.push(Line::from(format!(
+                            "  提示: v{} #{}",
+                            p.prompt_version.as_deref().unwrap_or("-"),
+                            p.prompt_hash.as_deref().unwrap_or("-")
+                        )));
+                    }
+                    let fields: Vec<String> =
+                        serde_json::from_str(&p.sampled_fields_json).unwrap_or_default();
+                    if !fields.is_empty() {
+                        lines.push(Line::from(format!("  采样字段: {}", fields.join(", "))));
+                    }
+                }
+
+                if let Some(reasoning) = &detail.reasoning {
+                    lines.push(Line::from(""));
+                    lines.push(Line::from(vec![Span::styled(
+                        "--- 推理过程 ---",
+                        Style::default().fg(Color::Yellow),
+                    )]));
+                    for l in reasoning.lines() {
+                        lines.push(Line::from(vec![Span::styled(
+                            format!("  {}", l),
+                            Style::default().fg(Color::DarkGray),
+                        )]));
+                    }
+                }
+
+                if let Some((_, items)) = app
+                    .similar_alphas
+                    .as_ref()
+                    .filter(|(e, _)| *e == detail.expression)
+                {
+                    lines.push(Line::from(""));
+                    lines.push(Line::from(vec![Span::styled(
+                        "--- 相似 Alpha (结构相似度) ---",
+                        Style::default().fg(Color::Yellow),
+                    )]));
+                    if items.is_empty() {
+                        lines.push(Line::from("  无"));
+                    }
+                    for (expr, sim) in items {
+                        let summary = app.alphas_all.iter().find(|a| a.expression == *expr);
+                        let fmt = |v: Option<f64>| {
+                            v.map(|x| format!("{:.2}", x))
+                                .unwrap_or_else(|| "-".to_string())
+                        };
+                        lines.push(Line::from(vec![
+                            Span::styled(
+                                format!("  {:.2} ", sim),
+                                Style::default().fg(if *sim >= 0.9 {
+                                    Color::Red
+                                } else {
+                                    Color::Gray
+                                }),
+                            ),
+                            Span::raw(format!(
+                                "[{} | Sharpe {} | Fitness {}] ",
+                                summary.map(|a| a.status.as_str()).unwrap_or("?"),
+                                fmt(summary.and_then(|a| a.is_sharpe)),
+                                fmt(summary.and_then(|a| a.metrics.is_fitness)),
+                            )),
+                            Span::styled(expr.clone(), Style::default().fg(Color::Cyan)),
+                        ]));
+                    }
+                }
 
                 lines.push(Line::from(""));
                 lines.push(Line::from(vec![Span::styled(
@@ -340,7 +635,7 @@ fn render_main_view(f: &mut Frame, area: Rect, app: &mut App) {
             };
 
             let title = if app.focus_area == FocusArea::MainView {
-                "详细信息 (↑↓ 滚动, ← 切换菜单)"
+                "详细信息 (↑↓ 滚动, v 原文/格式化, s 相似 Alpha, ← 切换菜单)"
             } else {
                 "详细信息"
             };
@@ -408,6 +703,165 @@ fn render_main_view(f: &mut Frame, area: Rect, app: &mut App) {
             );
             f.render_widget(paragraph, area);
         }
+        ViewMode::Families => {
+            let fmt = |v: Option<f64>| {
+                v.map(|x| format!("{:.2}", x))
+                    .unwrap_or_else(|| "-".to_string())
+            };
+            let mut lines = vec![
+                Line::from(vec![Span::styled(
+                    format!(
+                        "--- Alpha 家族 (共 {} 个，按规模排序) ---",
+                        app.families.len()
+                    ),
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                )]),
+                Line::from(""),
+            ];
+            for row in &app.families {
+                lines.push(Line::from(vec![
+                    Span::styled(
+                        format!("{} ", row.family_id.get(..8).unwrap_or(&row.family_id)),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::styled(
+                        format!("{:>5} 条", row.size),
+                        Style::default().fg(Color::Green),
+                    ),
+                    Span::raw(" | "),
+                    Span::styled(
+                        format!("Sharpe {:>6}", fmt(row.best_sharpe)),
+                        Style::default().fg(Color::Cyan),
+                    ),
+                    Span::raw(" | "),
+                    Span::styled(
+                        format!("Fitness {:>6}", fmt(row.best_fitness)),
+                        Style::default().fg(Color::Cyan),
+                    ),
+                    Span::raw(" | "),
+                    Span::styled(
+                        format!(
+                            "通过率 {:>5} ({}/{})",
+                            row.pass_rate()
+                                .map(|r| format!("{:.0}%", r * 100.0))
+                                .unwrap_or_else(|| "-".to_string()),
+                            row.passed,
+                            row.done
+                        ),
+                        Style::default().fg(Color::Magenta),
+                    ),
+                    Span::raw(" | "),
+                    Span::raw(row.skeleton.clone()),
+                ]));
+                lines.push(Line::from(Span::styled(
+                    format!("        最佳: {}", row.best_expression),
+                    Style::default().fg(Color::DarkGray),
+                )));
+            }
+            if app.families.is_empty() {
+                lines.push(Line::from("暂无数据，输入 `cluster` 按结构骨架归类"));
+            }
+            let title = if app.focus_area == FocusArea::MainView {
+                "Alpha 家族 (↑↓ 滚动, ← 切换菜单)"
+            } else {
+                "Alpha 家族"
+            };
+            let paragraph = Paragraph::new(lines)
+                .block(Block::default().borders(Borders::ALL).title(title).style(
+                    if app.focus_area == FocusArea::MainView {
+                        Style::default().fg(Color::Cyan)
+                    } else {
+                        Style::default().fg(Color::White)
+                    },
+                ))
+                .scroll((app.detail_scroll, 0));
+            f.render_widget(paragraph, area);
+        }
+        ViewMode::Usage => {
+            let cost = |v: Option<f64>| {
+                v.map(|x| format!("${:.4}", x))
+                    .unwrap_or_else(|| "-".to_string())
+            };
+            let total_cost = app.usage.iter().filter_map(|r| r.cost).reduce(|a, b| a + b);
+            let mut lines = vec![
+                Line::from(vec![Span::styled(
+                    format!(
+                        "--- LLM 用量 (按日期/供应商/模型，合计请求 {}，Token {}，费用 {}) ---",
+                        app.usage.iter().map(|r| r.requests).sum::<usize>(),
+                        app.usage
+                            .iter()
+                            .map(|r| r.prompt_tokens + r.completion_tokens)
+                            .sum::<i64>(),
+                        cost(total_cost)
+                    ),
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                )]),
+                Line::from(""),
+            ];
+            for row in &app.usage {
+                lines.push(Line::from(vec![
+                    Span::styled(
+                        format!("{} ", row.day),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::styled(
+                        format!("{}/{}", row.provider, row.model),
+                        Style::default().fg(Color::Cyan),
+                    ),
+                    Span::raw(" | "),
+                    Span::raw(format!("请求 {} (失败 {})", row.requests, row.failures)),
+                    Span::raw(" | "),
+                    Span::raw(format!(
+                        "Token {}/{}",
+                        row.prompt_tokens, row.completion_tokens
+                    )),
+                    Span::raw(" | "),
+                    Span::raw(format!("平均 {:.1}s", row.avg_latency_ms as f64 / 1000.0)),
+                    Span::raw(" | "),
+                    Span::styled(cost(row.cost), Style::default().fg(Color::Green)),
+                    Span::raw(" | "),
+                    Span::styled(
+                        format!("Alpha {} 通过 {}", row.alphas, row.passed),
+                        Style::default().fg(Color::Magenta),
+                    ),
+                ]));
+                if row.repair_attempted > 0 {
+                    lines.push(Line::from(vec![Span::styled(
+                        format!(
+                            "    修复 {} 条，救回 {} 条 ({:.0}%)",
+                            row.repair_attempted,
+                            row.repair_rescued,
+                            row.repair_rescued as f64 * 100.0 / row.repair_attempted as f64
+                        ),
+                        Style::default().fg(Color::DarkGray),
+                    )]));
+                }
+            }
+            if app.usage.is_empty() {
+                lines.push(Line::from(
+                    "暂无记录；生成请求的用量会自动记录，`usage [天数]` 刷新",
+                ));
+            }
+            let title = if app.focus_area == FocusArea::MainView {
+                "LLM 用量 (↑↓ 滚动, ← 切换菜单)"
+            } else {
+                "LLM 用量"
+            };
+            let paragraph = Paragraph::new(lines)
+                .block(Block::default().borders(Borders::ALL).title(title).style(
+                    if app.focus_area == FocusArea::MainView {
+                        Style::default().fg(Color::Cyan)
+                    } else {
+                        Style::default().fg(Color::White)
+                    },
+                ))
+                .scroll((app.detail_scroll, 0));
+            f.render_widget(paragraph, area);
+        }
     }
 }
 
@@ -446,7 +900,7 @@ fn render_bottom_bar(f: &mut Frame, area: Rect, app: &App) {
                 Span::styled("命令: ", Style::default().fg(Color::Yellow)),
                 Span::raw("(按 / 进入命令模式)"),
             ]),
-            Line::from("/命令 f筛选 /搜索 ←→切换 ↑↓导航 Enter/c确认 x返回 q退出"),
+            Line::from("/命令 f筛选 /搜索 ←→切换 ↑↓导航 Enter/c确认 s相似 x返回 q退出"),
         ]
     };
     let command_paragraph = Paragraph::new(command_prompt).block(
@@ -494,3 +948,30 @@ fn render_bottom_bar(f: &mut Frame, area: Rect, app: &App) {
     );
     f.render_widget(log, bottom_chunks[1]);
 }
+
+/// 秒数格式化为 "1h02m" / "3m05s" / "42s"
+fn format_secs(secs: Option<i64>) -> String {
+    match secs {
+        None => "-".to_string(),
+        Some(s) if s >= 3600 => format!("{}h{:02}m", s / 3600, (s % 3600) / 60),
+        Some(s) if s >= 60 => format!("{}m{:02}s", s / 60, s % 60),
+        Some(s) => format!("{}s", s),
+    }
+}
+
+/// 详情页分期指标的一行：(名称, 取值, 是否按百分比显示)
+type MetricRow = (&'static str, fn(&PeriodMetrics) -> Option<f64>, bool);
+
+/// 用块字符绘制单行迷你趋势图
+fn sparkline_text(values: &[u64]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let max = values.iter().copied().max().unwrap_or(0);
+    values
+        .iter()
+        .map(|&v| {
+            (v * 7)
+                .checked_div(max)
+                .map_or(BARS[0], |i| BARS[i as usize])
+        })
+        .collect()
+}